@@ -0,0 +1,326 @@
+//! Bootstrap and jackknife estimates of parameter uncertainty: the fit repeated on resampled
+//! data.
+
+use ndarray::Array2;
+use rand::Rng;
+
+use crate::{solve, stats, Config, Curve, Error};
+
+/// Distribution of the parameters over the converged refits of a bootstrap.
+#[derive(Debug, Clone)]
+pub struct BootstrapResult<const N: usize> {
+    pub mean: [f64; N],
+    /// Sample standard deviation, the bootstrap estimate of each standard error.
+    pub std: [f64; N],
+    /// Percentile interval `(lower, upper)` of each parameter at the requested confidence.
+    pub intervals: [(f64, f64); N],
+    /// Number of resamples dropped because their refit did not converge.
+    pub n_failed: usize,
+    /// Parameters of every converged refit, one column per resample, when requested.
+    pub samples: Option<Array2<f64>>,
+}
+
+/// Distribution of the parameters over the converged leave-one-out refits of a jackknife.
+#[derive(Debug, Clone)]
+pub struct JackknifeResult<const N: usize> {
+    /// Mean of the leave-one-out parameters.
+    pub mean: [f64; N],
+    /// Jackknife standard error `sqrt((n - 1) / n * sum((p_i - mean)^2))` over the `n`
+    /// converged refits.
+    pub std: [f64; N],
+    /// Jackknife estimate `(n - 1) * (mean - p)` of the bias of the parameters `p` of the fit
+    /// to all points.
+    pub bias: [f64; N],
+    /// Number of points whose leave-one-out refit did not converge.
+    pub n_failed: usize,
+}
+
+/// Refits the data once without each point, starting from the parameters of `curve`, and
+/// summarizes the refits that converged.
+pub(crate) fn leave_one_out<const N: usize, F: Fn(f64, [f64; N]) -> f64>(
+    curve: &Curve<N, F>,
+    x_data: &[f64],
+    y_data: &[f64],
+    cfg: &Config,
+) -> Result<JackknifeResult<N>, Error> {
+    let m = x_data.len();
+    let mut x_left = Vec::with_capacity(m - 1);
+    let mut y_left = Vec::with_capacity(m - 1);
+    let mut samples = Vec::with_capacity(m);
+    for i in 0..m {
+        x_left.clear();
+        y_left.clear();
+        x_left.extend(x_data[..i].iter().chain(&x_data[i + 1..]));
+        y_left.extend(y_data[..i].iter().chain(&y_data[i + 1..]));
+        let solution = solve(
+            &curve.func,
+            &x_left,
+            &y_left,
+            curve.params,
+            cfg.scaling(),
+            cfg,
+        );
+        if solution.converged() {
+            samples.push(solution.params);
+        }
+    }
+    if samples.is_empty() {
+        return Err(Error::NoConvergedResample { n_resamples: m });
+    }
+
+    let n = samples.len() as f64;
+    let mean: [f64; N] = std::array::from_fn(|i| samples.iter().map(|s| s[i]).sum::<f64>() / n);
+    Ok(JackknifeResult {
+        mean,
+        std: std::array::from_fn(|i| {
+            let squares: f64 = samples.iter().map(|s| (s[i] - mean[i]).powi(2)).sum();
+            ((n - 1.0) / n * squares).sqrt()
+        }),
+        bias: std::array::from_fn(|i| (n - 1.0) * (mean[i] - curve.params[i])),
+        n_failed: m - samples.len(),
+    })
+}
+
+/// Refits the data with the residuals of `curve` resampled with replacement,
+/// `y*_i = f(x_i) + r_j`, starting each fit from the parameters of `curve`. Returns the
+/// parameters of every refit that converged.
+pub(crate) fn resample_residuals<const N: usize, F: Fn(f64, [f64; N]) -> f64>(
+    curve: &Curve<N, F>,
+    x_data: &[f64],
+    y_data: &[f64],
+    n_resamples: usize,
+    cfg: &Config,
+) -> Vec<[f64; N]> {
+    let fitted: Vec<f64> = x_data.iter().map(|&x| curve.eval(x)).collect();
+    let residuals: Vec<f64> = y_data.iter().zip(&fitted).map(|(y, f)| y - f).collect();
+    let mut rng = cfg.rng();
+    let mut y_resampled = vec![0.0; y_data.len()];
+    let mut samples = Vec::with_capacity(n_resamples);
+    for _ in 0..n_resamples {
+        for (y, f) in y_resampled.iter_mut().zip(&fitted) {
+            *y = f + residuals[rng.random_range(0..residuals.len())];
+        }
+        let solution = solve(
+            &curve.func,
+            x_data,
+            &y_resampled,
+            curve.params,
+            cfg.scaling(),
+            cfg,
+        );
+        if solution.converged() {
+            samples.push(solution.params);
+        }
+    }
+    samples
+}
+
+/// Refits `n_resamples` copies of the data whose `(x, y)` pairs are drawn with replacement,
+/// starting each fit from the parameters of `curve`. Returns the parameters of every refit
+/// that converged.
+pub(crate) fn resample_pairs<const N: usize, F: Fn(f64, [f64; N]) -> f64>(
+    curve: &Curve<N, F>,
+    x_data: &[f64],
+    y_data: &[f64],
+    n_resamples: usize,
+    cfg: &Config,
+) -> Vec<[f64; N]> {
+    let mut rng = cfg.rng();
+    let mut x_resampled = vec![0.0; x_data.len()];
+    let mut y_resampled = vec![0.0; y_data.len()];
+    let mut samples = Vec::with_capacity(n_resamples);
+    for _ in 0..n_resamples {
+        for (x, y) in x_resampled.iter_mut().zip(&mut y_resampled) {
+            let i = rng.random_range(0..x_data.len());
+            (*x, *y) = (x_data[i], y_data[i]);
+        }
+        let solution = solve(
+            &curve.func,
+            &x_resampled,
+            &y_resampled,
+            curve.params,
+            cfg.scaling(),
+            cfg,
+        );
+        if solution.converged() {
+            samples.push(solution.params);
+        }
+    }
+    samples
+}
+
+/// Summarizes the converged refits of `n_resamples` resamples.
+pub(crate) fn summarize<const N: usize>(
+    samples: &[[f64; N]],
+    n_resamples: usize,
+    confidence: f64,
+    keep_samples: bool,
+) -> Result<BootstrapResult<N>, Error> {
+    if samples.is_empty() {
+        return Err(Error::NoConvergedResample { n_resamples });
+    }
+    let n = samples.len() as f64;
+    let mean: [f64; N] = std::array::from_fn(|i| samples.iter().map(|s| s[i]).sum::<f64>() / n);
+    let std = std::array::from_fn(|i| {
+        let squares: f64 = samples.iter().map(|s| (s[i] - mean[i]).powi(2)).sum();
+        (squares / (n - 1.0)).sqrt()
+    });
+    Ok(BootstrapResult {
+        mean,
+        std,
+        intervals: percentile_intervals(samples, confidence),
+        n_failed: n_resamples - samples.len(),
+        samples: keep_samples
+            .then(|| Array2::from_shape_fn((N, samples.len()), |(i, j)| samples[j][i])),
+    })
+}
+
+/// Percentile interval `(lower, upper)` of each parameter holding the central `confidence`
+/// fraction of the samples.
+fn percentile_intervals<const N: usize>(samples: &[[f64; N]], confidence: f64) -> [(f64, f64); N] {
+    let tail = 0.5 * (1.0 - confidence);
+    std::array::from_fn(|i| {
+        let mut values: Vec<f64> = samples.iter().map(|sample| sample[i]).collect();
+        values.sort_by(f64::total_cmp);
+        (
+            stats::quantile(&values, tail),
+            stats::quantile(&values, 1.0 - tail),
+        )
+    })
+}
+
+pub(crate) fn check_confidence(confidence: f64) -> Result<(), Error> {
+    if confidence > 0.0 && confidence < 1.0 {
+        Ok(())
+    } else {
+        Err(Error::InvalidConfidence { confidence })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::Array;
+    use ndarray_rand::rand_distr::Normal;
+    use ndarray_rand::RandomExt;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::summarize;
+    use crate::{Config, CurveFit, Error};
+
+    fn line(x: f64, p: [f64; 2]) -> f64 {
+        p[0] * x + p[1]
+    }
+
+    #[test]
+    fn residual_bootstrap_covers_true_slope() {
+        let x_data = Array::linspace(0., 4., 40);
+        let normal = Normal::new(0.0, 0.3).unwrap();
+        let noise = Array::random_using(x_data.dim(), normal, &mut StdRng::seed_from_u64(5));
+        let y_data = x_data.map(|&x| line(x, [2.5, 1.3])) + noise;
+        let (x_data, y_data) = (x_data.to_vec(), y_data.to_vec());
+        let cfg = Config {
+            seed: Some(11),
+            ..Default::default()
+        };
+
+        let f = line.fit(&x_data, &y_data, cfg).unwrap().curve;
+        let [slope, _] = f.bootstrap_ci(&x_data, &y_data, 500, 0.95, cfg).unwrap();
+        assert!(slope.0 < 2.5 && 2.5 < slope.1, "{slope:?}");
+        assert!(slope.0 < f.params()[0] && f.params()[0] < slope.1);
+        assert_eq!(
+            f.bootstrap_ci(&x_data, &y_data, 500, 0.95, cfg).unwrap()[0],
+            slope
+        );
+
+        assert!(matches!(
+            f.bootstrap_ci(&x_data, &y_data, 500, 1.0, cfg),
+            Err(Error::InvalidConfidence { .. })
+        ));
+    }
+
+    #[test]
+    fn pairs_bootstrap_matches_analytic_errors() {
+        let x_data = Array::linspace(0., 4., 200);
+        let normal = Normal::new(0.0, 0.3).unwrap();
+        let noise = Array::random_using(x_data.dim(), normal, &mut StdRng::seed_from_u64(5));
+        let y_data = x_data.map(|&x| line(x, [2.5, 1.3])) + noise;
+        let (x_data, y_data) = (x_data.to_vec(), y_data.to_vec());
+        let cfg = Config {
+            seed: Some(2),
+            ..Default::default()
+        };
+
+        let analytic = line.fit(&x_data, &y_data, cfg).unwrap();
+        let bootstrap = line
+            .bootstrap_fit(&x_data, &y_data, cfg, 2000, 0.95, true)
+            .unwrap();
+        for (std, expected) in bootstrap.std.iter().zip(analytic.std_errors()) {
+            assert!((std / expected - 1.0).abs() < 0.15, "{std} vs {expected}");
+        }
+        for ((mean, interval), param) in bootstrap
+            .mean
+            .iter()
+            .zip(bootstrap.intervals)
+            .zip(analytic.curve.params())
+        {
+            assert!((mean - param).abs() < 0.1 * bootstrap.std[0].max(bootstrap.std[1]));
+            assert!(interval.0 < param && param < interval.1);
+        }
+        let samples = bootstrap.samples.unwrap();
+        assert_eq!(samples.dim(), (2, 2000 - bootstrap.n_failed));
+    }
+
+    #[test]
+    fn jackknife_matches_analytic_errors() {
+        let x_data = Array::linspace(0., 4., 60);
+        let normal = Normal::new(0.0, 0.3).unwrap();
+        let noise = Array::random_using(x_data.dim(), normal, &mut StdRng::seed_from_u64(5));
+        let y_data = x_data.map(|&x| line(x, [2.5, 1.3])) + noise;
+        let (x_data, y_data) = (x_data.to_vec(), y_data.to_vec());
+
+        let analytic = line.fit(&x_data, &y_data, Config::default()).unwrap();
+        let jackknife = line
+            .resample_jackknife(&x_data, &y_data, Config::default())
+            .unwrap();
+        assert_eq!(jackknife.n_failed, 0);
+        for (std, expected) in jackknife.std.iter().zip(analytic.std_errors()) {
+            assert!((std / expected - 1.0).abs() < 0.2, "{std} vs {expected}");
+        }
+        // least squares is unbiased for a linear model
+        for ((mean, bias), (param, std)) in jackknife
+            .mean
+            .iter()
+            .zip(jackknife.bias)
+            .zip(analytic.curve.params().iter().zip(jackknife.std))
+        {
+            assert!((mean - param).abs() < 0.01 * std, "{mean} vs {param}");
+            assert!(bias.abs() < 0.5 * std, "{bias} vs {std}");
+        }
+
+        // leaving a point out of two leaves too few for a line
+        assert!(matches!(
+            line.resample_jackknife(&x_data[..2], &y_data[..2], Config::default()),
+            Err(Error::InsufficientData {
+                n_data: 1,
+                n_params: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn failed_resamples_are_counted() {
+        let samples = [[1.0, 4.0], [2.0, 6.0], [3.0, 8.0]];
+        let result = summarize(&samples, 5, 0.5, false).unwrap();
+        assert_eq!(result.n_failed, 2);
+        assert_eq!(result.mean, [2.0, 6.0]);
+        assert_eq!(result.std, [1.0, 2.0]);
+        assert_eq!(result.intervals, [(1.5, 2.5), (5.0, 7.0)]);
+        assert!(result.samples.is_none());
+
+        assert!(matches!(
+            summarize::<2>(&[], 5, 0.5, true),
+            Err(Error::NoConvergedResample { n_resamples: 5 })
+        ));
+    }
+}
@@ -0,0 +1,184 @@
+//! K-fold cross-validation: out-of-sample errors of a model, for comparing competing ones.
+
+use rand::seq::SliceRandom;
+
+use crate::{check_input, solve, Config, Error};
+
+/// Outcome of one fold of a cross-validation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fold {
+    /// Number of held-out points the fold was tested on.
+    pub n_test: usize,
+    /// Mean squared error of the held-out points under the fit to the other folds, `None`
+    /// when that fit did not converge or had fewer points than parameters.
+    pub mse: Option<f64>,
+}
+
+/// Test errors of a cross-validation, over the folds whose fit converged.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CvResult {
+    /// Every fold, in the order they were held out.
+    pub folds: Vec<Fold>,
+    /// Mean of the folds' test errors.
+    pub mean: f64,
+    /// Sample standard deviation of the folds' test errors, NaN for a single one.
+    pub std: f64,
+}
+
+/// K-fold cross-validation of `func`: the points are shuffled with the generator of
+/// `cfg.seed` and split into `k` folds of nearly equal size, and each fold is held out in turn
+/// while the model is fitted to the others with `cfg`, the fit being tested on the held-out
+/// points by their mean squared error. `k` equal to the number of points is leave-one-out.
+///
+/// A fold whose fit fails is recorded without an error; only when all of them fail is
+/// `Error::NoConvergedFold` returned. `k < 2` or more folds than points give
+/// `Error::InvalidFoldCount`. The data are checked as by [`crate::CurveFit::fit`] first, so
+/// `k` counts the points that are kept.
+pub fn cross_validate<const N: usize, F: Fn(f64, [f64; N]) -> f64>(
+    func: &F,
+    x_data: &[f64],
+    y_data: &[f64],
+    cfg: Config,
+    k: usize,
+) -> Result<CvResult, Error> {
+    let (x_data, y_data) = check_input::<N>(x_data, y_data, &cfg)?;
+    let n_data = x_data.len();
+    if k < 2 || k > n_data {
+        return Err(Error::InvalidFoldCount { k, n_data });
+    }
+
+    let mut order: Vec<usize> = (0..n_data).collect();
+    order.shuffle(&mut cfg.rng());
+    // fold `j` holds the shuffled points from `j * n / k` up to `(j + 1) * n / k`
+    let folds: Vec<Fold> = (0..k)
+        .map(|j| {
+            let test = &order[j * n_data / k..(j + 1) * n_data / k];
+            let train = order[..j * n_data / k]
+                .iter()
+                .chain(&order[(j + 1) * n_data / k..]);
+            let (x_train, y_train): (Vec<f64>, Vec<f64>) =
+                train.map(|&i| (x_data[i], y_data[i])).unzip();
+            let mse = (x_train.len() >= N)
+                .then(|| {
+                    let p0 = cfg.initial_guess(&x_train, &y_train);
+                    solve(func, &x_train, &y_train, p0, cfg.scaling(), &cfg)
+                })
+                .filter(|solution| solution.converged())
+                .map(|solution| {
+                    let squares = test
+                        .iter()
+                        .map(|&i| (func(x_data[i], solution.params) - y_data[i]).powi(2));
+                    squares.sum::<f64>() / test.len() as f64
+                });
+            Fold {
+                n_test: test.len(),
+                mse,
+            }
+        })
+        .collect();
+
+    let errors: Vec<f64> = folds.iter().filter_map(|fold| fold.mse).collect();
+    if errors.is_empty() {
+        return Err(Error::NoConvergedFold { k });
+    }
+    let n = errors.len() as f64;
+    let mean = errors.iter().sum::<f64>() / n;
+    let squares: f64 = errors.iter().map(|e| (e - mean).powi(2)).sum();
+    Ok(CvResult {
+        folds,
+        mean,
+        std: (squares / (n - 1.0)).sqrt(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::Array;
+    use ndarray_rand::rand_distr::Normal;
+    use ndarray_rand::RandomExt;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    fn line(x: f64, p: [f64; 2]) -> f64 {
+        p[0] * x + p[1]
+    }
+
+    fn parabola(x: f64, p: [f64; 3]) -> f64 {
+        p[0] * x * x + p[1] * x + p[2]
+    }
+
+    #[test]
+    fn correct_model_has_lower_test_error() {
+        let x_data = Array::linspace(-2., 2., 60);
+        let normal = Normal::new(0.0, 0.2).unwrap();
+        let noise = Array::random_using(x_data.dim(), normal, &mut StdRng::seed_from_u64(4));
+        let y_data = x_data.map(|&x| parabola(x, [0.8, -1.0, 0.5])) + noise;
+        let (x_data, y_data) = (x_data.to_vec(), y_data.to_vec());
+        let cfg = Config {
+            seed: Some(9),
+            ..Default::default()
+        };
+
+        let quadratic = cross_validate(&parabola, &x_data, &y_data, cfg, 5).unwrap();
+        let linear = cross_validate(&line, &x_data, &y_data, cfg, 5).unwrap();
+        assert!(
+            quadratic.mean < linear.mean,
+            "{} vs {}",
+            quadratic.mean,
+            linear.mean
+        );
+        // the noise variance is the best a model can do on new points
+        assert!(
+            (quadratic.mean / 0.04 - 1.0).abs() < 0.5,
+            "{}",
+            quadratic.mean
+        );
+        assert_eq!(quadratic.folds.len(), 5);
+        assert!(quadratic.folds.iter().all(|fold| fold.n_test == 12));
+        assert!(quadratic.std.is_finite());
+        assert_eq!(
+            cross_validate(&parabola, &x_data, &y_data, cfg, 5).unwrap(),
+            quadratic
+        );
+    }
+
+    #[test]
+    fn fold_counts() {
+        let x_data = [0.0, 1.0, 2.0, 3.0, 4.0];
+        let y_data = [1.1, 2.9, 5.2, 6.8, 9.1];
+        let cfg = Config::default();
+
+        // leave-one-out, each point predicted by the line through the other four
+        let loo = cross_validate(&line, &x_data, &y_data, cfg, 5).unwrap();
+        assert!(loo.folds.iter().all(|fold| fold.n_test == 1));
+        let mut expected = 0.0;
+        for i in 0..5 {
+            let (x, y): (Vec<f64>, Vec<f64>) = (0..5)
+                .filter(|&j| j != i)
+                .map(|j| (x_data[j], y_data[j]))
+                .unzip();
+            let f = crate::CurveFit::fit(&line, &x, &y, cfg).unwrap();
+            expected += (f.curve.eval(x_data[i]) - y_data[i]).powi(2) / 5.0;
+        }
+        assert!(
+            (loo.mean - expected).abs() < 1e-9,
+            "{} vs {expected}",
+            loo.mean
+        );
+
+        for k in [0, 1, 6] {
+            assert!(matches!(
+                cross_validate(&line, &x_data, &y_data, cfg, k),
+                Err(Error::InvalidFoldCount { n_data: 5, .. })
+            ));
+        }
+        // three training points cannot determine a fourth parameter
+        let cubic = |x: f64, p: [f64; 4]| p[0] + p[1] * x + p[2] * x * x + p[3] * x.powi(3);
+        assert!(matches!(
+            cross_validate(&cubic, &x_data, &y_data, cfg, 2),
+            Err(Error::NoConvergedFold { k: 2 })
+        ));
+    }
+}
@@ -0,0 +1,138 @@
+//! Fitting data read from CSV files, behind the `csv` feature.
+
+use std::path::Path;
+
+use crate::{CurveFit, Error, FitResult};
+
+#[derive(thiserror::Error, Debug)]
+pub enum CsvError {
+    #[error("csv {0}")]
+    Csv(#[from] csv::Error),
+    #[error("record {record} has no column {column}")]
+    MissingColumn { record: usize, column: usize },
+    #[error("record {record}, column {column}: {value:?} is not a number")]
+    InvalidNumber {
+        record: usize,
+        column: usize,
+        value: String,
+    },
+    #[error(transparent)]
+    Fit(#[from] Error),
+}
+
+/// Reads columns `x_col` and `y_col`, counted from 0, of the CSV file at `path` and fits `f`
+/// to them with [`CurveFit::fit`].
+///
+/// A first record whose two columns are not numbers is taken for a header and skipped; every
+/// other record must have both columns, parsed as `f64` after trimming whitespace. `NaN` and
+/// `inf` are read as such and handled by `cfg.non_finite`. Records are numbered from 0 in
+/// errors, counting any header.
+pub fn fit_csv<const N: usize, F: CurveFit<N>>(
+    f: &F,
+    path: &Path,
+    x_col: usize,
+    y_col: usize,
+    cfg: crate::Config,
+) -> Result<FitResult<N, F>, CsvError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_path(path)?;
+    let (mut x_data, mut y_data) = (vec![], vec![]);
+    for (record_index, record) in reader.records().enumerate() {
+        let record = record?;
+        let field = |column: usize| {
+            let value = record.get(column).ok_or(CsvError::MissingColumn {
+                record: record_index,
+                column,
+            })?;
+            value
+                .trim()
+                .parse::<f64>()
+                .map_err(|_| CsvError::InvalidNumber {
+                    record: record_index,
+                    column,
+                    value: value.to_string(),
+                })
+        };
+        match (field(x_col), field(y_col)) {
+            (Ok(x), Ok(y)) => {
+                x_data.push(x);
+                y_data.push(y);
+            }
+            (Err(CsvError::InvalidNumber { .. }), Err(CsvError::InvalidNumber { .. }))
+                if record_index == 0 => {}
+            (Err(error), _) | (_, Err(error)) => return Err(error),
+        }
+    }
+    Ok(f.fit(&x_data, &y_data, cfg)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::Config;
+
+    fn line(x: f64, p: [f64; 2]) -> f64 {
+        p[0] * x + p[1]
+    }
+
+    /// Writes `contents` to a file of the temporary directory that is unique to the test.
+    fn write(name: &str, contents: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("curve_fit_{}_{name}.csv", std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn fits_columns_of_a_file() {
+        let path = write(
+            "columns",
+            "label,time,signal\n\
+             a,0.0, 1.0\n\
+             b,1.0, 3.1\n\
+             c,2.0, 4.9\n\
+             d,3.0, 7.0\n",
+        );
+        let f = fit_csv(&line, &path, 1, 2, Config::default()).unwrap();
+        let [slope, intercept] = f.curve.params();
+        assert!((slope - 1.98).abs() < 1e-9, "{slope}");
+        assert!((intercept - 1.03).abs() < 1e-9, "{intercept}");
+        assert_eq!(f.n_data, 4);
+
+        // without a header every record is data
+        let path = write("no_header", "0,1\n1,3\n2,5\n");
+        let f = fit_csv(&line, &path, 0, 1, Config::default()).unwrap();
+        assert_eq!(f.n_data, 3);
+    }
+
+    #[test]
+    fn reports_bad_records() {
+        let path = write("bad_number", "x,y\n0,1\n1,three\n2,5\n");
+        assert!(matches!(
+            fit_csv(&line, &path, 0, 1, Config::default()),
+            Err(CsvError::InvalidNumber { record: 2, column: 1, value }) if value == "three"
+        ));
+        let path = write("short_record", "0,1\n1\n2,5\n");
+        assert!(matches!(
+            fit_csv(&line, &path, 0, 1, Config::default()),
+            Err(CsvError::MissingColumn {
+                record: 1,
+                column: 1
+            })
+        ));
+        let path = write("non_finite", "0,1\n1,NaN\n2,5\n");
+        assert!(matches!(
+            fit_csv(&line, &path, 0, 1, Config::default()),
+            Err(CsvError::Fit(Error::NonFiniteData { index: 1 }))
+        ));
+        let missing = std::env::temp_dir().join("curve_fit_no_such_file.csv");
+        assert!(matches!(
+            fit_csv(&line, &missing, 0, 1, Config::default()),
+            Err(CsvError::Csv(_))
+        ));
+    }
+}
@@ -0,0 +1,238 @@
+//! Global fitting: one model fitted to several data sets at once, with some parameters
+//! shared between all of them.
+
+use crate::solver::global::{global_least_squares, GlobalProblem};
+use crate::solver::{covariance_matrix, Deadline};
+use crate::{check_input, Config, Curve, Error, FitReport, Method, TerminationReason, XScale};
+
+/// Whether a parameter of a global fit is common to all data sets or fitted to each.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sharing {
+    /// One value for every data set, fitted to all of them together.
+    Shared,
+    /// A value of its own for each data set.
+    PerDataset,
+}
+
+/// The [`Sharing`] of each of the `N` parameters of a global fit.
+pub type ParamSharing<const N: usize> = [Sharing; N];
+
+/// The curves of a global fit, one per data set, together with the statistics of the fit.
+#[derive(Clone)]
+pub struct GlobalFitResult<const N: usize, F: Fn(f64, [f64; N]) -> f64> {
+    /// The fitted curve of each data set, in their order. The shared parameters are equal in
+    /// all of them; each covariance is the block of the global covariance belonging to the
+    /// parameters of its data set.
+    pub curves: Vec<Curve<N, F>>,
+    /// Sum of squared residuals over all data sets.
+    pub ssr: f64,
+    /// Number of data points of all data sets together.
+    pub n_data: usize,
+    /// Number of fitted values, each shared parameter counted once and each per-dataset
+    /// parameter once per data set.
+    pub n_params: usize,
+    pub report: FitReport,
+    sharing: ParamSharing<N>,
+}
+
+impl<const N: usize, F: Fn(f64, [f64; N]) -> f64> GlobalFitResult<N, F> {
+    /// The value of each shared parameter, `None` for the per-dataset ones.
+    pub fn shared_params(&self) -> [Option<f64>; N] {
+        let params = self.curves[0].params();
+        std::array::from_fn(|i| (self.sharing[i] == Sharing::Shared).then_some(params[i]))
+    }
+
+    /// Standard errors of the parameters of each data set, the square roots of the
+    /// covariance diagonals.
+    pub fn std_errors(&self) -> Vec<[f64; N]> {
+        let std_errors =
+            |curve: &Curve<N, F>| std::array::from_fn(|i| curve.covariance[i][i].sqrt());
+        self.curves.iter().map(std_errors).collect()
+    }
+}
+
+/// Fits `func` to all `datasets`, pairs of `(x_data, y_data)`, at once: the parameters that
+/// `sharing` declares `Sharing::Shared` take one value for all data sets, and the
+/// `Sharing::PerDataset` ones a value for each, all minimizing the sum of squares over every
+/// point. Sharing parameters pools the information of the data sets about them, which
+/// usually makes them more precise than in separate fits.
+///
+/// Each data set is checked as by [`crate::CurveFit::fit`], except that it may have fewer
+/// points than parameters; only all points together must outnumber the fitted values,
+/// [`GlobalFitResult::n_params`]. Every parameter starts from the guess of `cfg` for the first
+/// data set when shared, and for its own otherwise.
+///
+/// The fit is a Levenberg-Marquardt iteration with forward differences, which ignores
+/// `cfg.method` and reports `Method::LM`; `cfg.regularization` and `cfg.record_history` do not
+/// apply.
+pub fn fit_global<const N: usize, F: Fn(f64, [f64; N]) -> f64 + Clone>(
+    func: &F,
+    datasets: &[(&[f64], &[f64])],
+    cfg: Config,
+    sharing: ParamSharing<N>,
+) -> Result<GlobalFitResult<N, F>, Error> {
+    let datasets = datasets
+        .iter()
+        .map(|&(x_data, y_data)| {
+            let (x_data, y_data) = check_input::<0>(x_data, y_data, &cfg)?;
+            Ok((x_data.into_owned(), y_data.into_owned()))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    let problem = GlobalProblem::new(func, &datasets, sharing.map(|s| s == Sharing::Shared))
+        .with_diff_step(cfg.diff_step);
+    let n_data = datasets.iter().map(|(x_data, _)| x_data.len()).sum();
+    let n_params = problem.n_params();
+    if datasets.is_empty() || n_data < n_params {
+        return Err(Error::InsufficientData { n_data, n_params });
+    }
+
+    let guesses: Vec<[f64; N]> = datasets
+        .iter()
+        .map(|(x_data, y_data)| cfg.initial_guess(x_data, y_data))
+        .collect();
+    let solution = global_least_squares(
+        &problem,
+        problem.stack(&guesses),
+        cfg.x_scale == XScale::Auto,
+        &cfg.lm,
+        Deadline::new(cfg.timeout),
+    );
+    if solution.jacobian.iter().all(|&j| j == 0.0) {
+        return Err(Error::ZeroJacobian {
+            params: solution.params.iter().copied().collect(),
+        });
+    }
+    if let TerminationReason::Timeout { elapsed } = solution.termination {
+        return Err(Error::Timeout {
+            elapsed,
+            iterations: solution.iterations,
+            last_params: solution.params.iter().copied().collect(),
+            last_cost: 2.0 * solution.cost,
+        });
+    }
+    if !solution.converged() {
+        return Err(Error::DidNotConverge {
+            reason: solution.termination,
+            iterations: solution.iterations,
+            last_params: solution.params.iter().copied().collect(),
+            last_cost: 2.0 * solution.cost,
+        });
+    }
+
+    let ssr = 2.0 * solution.cost;
+    let covariance = covariance_matrix(&solution.jacobian, ssr, n_params);
+    let curves = (0..datasets.len())
+        .map(|k| {
+            let index = |i| problem.index(k, i);
+            Curve {
+                func: func.clone(),
+                params: problem.params(&solution.params, k),
+                parameter_names: None,
+                derivative: None,
+                at_bounds: [false; N],
+                covariance: std::array::from_fn(|i| {
+                    std::array::from_fn(|j| covariance[(index(i), index(j))])
+                }),
+            }
+        })
+        .collect();
+    Ok(GlobalFitResult {
+        curves,
+        ssr,
+        n_data,
+        n_params,
+        report: FitReport {
+            iterations: solution.iterations,
+            n_fev: solution.n_fev,
+            method: Method::LM,
+            trust_radius: None,
+            termination: solution.termination,
+        },
+        sharing,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CurveFit;
+
+    fn decay(t: f64, p: [f64; 3]) -> f64 {
+        p[0] * (-p[1] * t).exp() + p[2]
+    }
+
+    #[test]
+    fn shared_amplitude_is_more_precise() {
+        let t_data: Vec<f64> = (0..30).map(|i| 0.1 * i as f64).collect();
+        let rates = [1.0, 2.5];
+        let y_data: Vec<Vec<f64>> = rates
+            .iter()
+            .enumerate()
+            .map(|(k, &rate)| {
+                let points = t_data.iter().enumerate();
+                let noise = |i: usize| 0.02 * ((1.7 + k as f64) * i as f64).sin();
+                points
+                    .map(|(i, &t)| decay(t, [3.0, rate, 0.5]) + noise(i))
+                    .collect()
+            })
+            .collect();
+        let datasets: Vec<(&[f64], &[f64])> =
+            y_data.iter().map(|y| (&t_data[..], &y[..])).collect();
+        let sharing = [Sharing::Shared, Sharing::PerDataset, Sharing::Shared];
+
+        let global = fit_global(&decay, &datasets, Config::default(), sharing).unwrap();
+        assert_eq!(global.curves.len(), 2);
+        assert_eq!(global.n_data, 60);
+        assert_eq!(global.n_params, 4);
+        let [amplitude, rate, offset] = global.shared_params();
+        assert!(rate.is_none());
+        assert!((amplitude.unwrap() - 3.0).abs() < 0.05, "{amplitude:?}");
+        assert!((offset.unwrap() - 0.5).abs() < 0.05, "{offset:?}");
+        for (curve, expected) in global.curves.iter().zip(rates) {
+            assert_eq!(curve.params()[0], amplitude.unwrap());
+            assert!(
+                (curve.params()[1] - expected).abs() < 0.05,
+                "{:?}",
+                curve.params()
+            );
+        }
+        let ssr: f64 = global
+            .curves
+            .iter()
+            .zip(&y_data)
+            .flat_map(|(curve, y)| {
+                t_data
+                    .iter()
+                    .zip(y)
+                    .map(|(&t, y)| (curve.eval(t) - y).powi(2))
+            })
+            .sum();
+        assert!((global.ssr - ssr).abs() < 1e-12, "{} vs {ssr}", global.ssr);
+
+        let std_errors = global.std_errors();
+        assert_eq!(std_errors[0][0], std_errors[1][0]);
+        for (&(x_data, y_data), global_errors) in datasets.iter().zip(&std_errors) {
+            let single = decay.fit(x_data, y_data, Config::default()).unwrap();
+            let single_error = single.std_errors()[0];
+            assert!(
+                global_errors[0] < single_error,
+                "{} vs {single_error}",
+                global_errors[0]
+            );
+        }
+
+        // two points cannot determine three parameters, with or without another data set
+        let short: [(&[f64], &[f64]); 1] = [(&t_data[..2], &y_data[0][..2])];
+        assert!(matches!(
+            fit_global(&decay, &short, Config::default(), sharing),
+            Err(Error::InsufficientData {
+                n_data: 2,
+                n_params: 3
+            })
+        ));
+        assert!(matches!(
+            fit_global(&decay, &[], Config::default(), sharing),
+            Err(Error::InsufficientData { n_data: 0, .. })
+        ));
+    }
+}
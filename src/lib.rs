@@ -1,26 +1,361 @@
-use ndarray::{Array, ArrayBase, Dim, OwnedRepr};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use ndarray::{Array1, ArrayBase, Data, Ix1};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 use range_checker::CheckVerbose;
-use ndarray_rand::RandomExt;
-use ndarray_rand::rand_distr::Normal;
-use rand;
 
-#[derive(Debug, Clone, Copy, range_checker::CheckVerbose)]
+mod bootstrap;
+mod cross_validation;
+#[cfg(feature = "csv")]
+mod csv_file;
+mod global_fit;
+pub mod models;
+mod multioutput;
+mod multistart;
+pub mod prelude;
+mod problem;
+mod quadrature;
+mod reject;
+#[cfg(feature = "polars")]
+mod series;
+mod solver;
+mod stats;
+mod trace;
+
+pub use bootstrap::{BootstrapResult, JackknifeResult};
+pub use cross_validation::{cross_validate, CvResult, Fold};
+#[cfg(feature = "csv")]
+pub use csv_file::{fit_csv, CsvError};
+pub use global_fit::{fit_global, GlobalFitResult, ParamSharing, Sharing};
+pub use multioutput::{MultiOutputCurveFit, MultiOutputFitResult};
+pub use multistart::{MultiStartResult, Start};
+pub use problem::{fit_problem, Problem, ProblemFitResult};
+pub use reject::{ClipScale, RejectOptions, RejectResult, RejectStatus};
+pub use solver::{TerminationReason, TraceEntry};
+
+#[derive(Debug, Clone, Copy, PartialEq, range_checker::CheckVerbose)]
 pub struct Config {
     #[filter(|p0: &f64| p0.is_normal())]
     pub p0: f64,
+    /// How the initial guess of each parameter is chosen, from `p0` or from the data.
+    pub p0_strategy: P0Strategy,
+    /// Validates the data before fitting, handling non-finite points as `non_finite` says and
+    /// rejecting data whose `y` are all equal. `false` skips the validation and passes the data
+    /// to the solver unchanged.
     pub check_finite: bool,
-    pub method: Method
+    pub non_finite: NonFinitePolicy,
+    /// Sorts the points by ascending `x` before fitting, keeping each `y` with its `x`. The
+    /// fitted parameters do not depend on the order of the points; per-point results such as
+    /// [`FitResult::x_adjusted`] follow the sorted order.
+    pub sort_input: bool,
+    /// Fails with `Error::UnsortedInput` where `x_data` decreases, to catch data shuffled by
+    /// accident. Non-finite `x` are skipped, and the data are checked as given, before
+    /// `sort_input` sorts them.
+    pub require_sorted: bool,
+    pub method: Method,
+    /// Seeds the random number generator of randomized fitting strategies, such as random
+    /// restarts, so that identical seeds give identical fits. `None` draws a fresh seed.
+    pub seed: Option<u64>,
+    /// Scaling of the parameters inside the solver. See [`CurveFit::fit_scaled`] for
+    /// scales known in advance.
+    pub x_scale: XScale,
+    /// Keeps the sum of squared residuals after every solver iteration in
+    /// [`FitResult::history`].
+    pub record_history: bool,
+    /// Keeps a [`TraceEntry`] of the parameters, sum of squares, step and damping after every
+    /// solver iteration in [`FitResult::trace`], to replay a suspicious fit. Off by default to
+    /// spare the allocations; the solvers' evaluation budgets bound its length.
+    pub record_trace: bool,
+    /// With `record_trace`, also traces the steps that LM, TRF, DogBox and L-BFGS reject,
+    /// usually the ones that explain a stalled fit.
+    pub trace_rejected: bool,
+    /// Tikhonov (L2) regularization: minimizes `ssr + lambda * |p|^2` for `Some(lambda)`,
+    /// `lambda >= 0`, which keeps near-singular problems from blowing up the parameters.
+    /// [`CurveFit::fit_regularized`] penalizes the distance to a reference instead of to zero.
+    /// The reported `ssr` refers to the data alone, while the covariance is the regularized
+    /// `ssr / (m - N) * (J^T J + lambda I)^-1`, which shrinks as `lambda` grows.
+    pub regularization: Option<f64>,
+    /// Passes over the data made by `Method::SGD`, unless the sum of squares stops changing
+    /// first. Ignored by the other methods.
+    #[filter(|n_epochs: &usize| *n_epochs > 0)]
+    pub n_epochs: usize,
+    /// Step of the finite-difference Jacobian relative to each parameter, `h = diff_step *
+    /// max(|p|, 1)`. `None` takes the square root of the machine epsilon for forward
+    /// differences and its cube root for central ones, which suits models whose output changes
+    /// on the scale of their parameters; a model much more sensitive to a small parameter
+    /// needs a smaller step, and a noisy one a larger step.
+    pub diff_step: Option<f64>,
+    /// Finite differences of the Jacobian.
+    pub jacobian: JacMethod,
+    /// Damping schedule of the Levenberg-Marquardt iterations, which `Method::LM`,
+    /// [`CurveFit::fit_odr`] and [`fit_global`] run.
+    pub lm: LmOptions,
+    /// Trust region of `Method::TRF` and `Method::DogBox`.
+    pub tr: TrustRegionOptions,
+    /// What [`CurveFit::fit_bounded`] does when `p0` lies outside the bounds of a parameter.
+    pub out_of_bounds: OutOfBoundsPolicy,
+    /// Wall-clock budget of each solver run, checked before every iteration: a solver still
+    /// iterating when it expires stops, and the fit fails with `Error::Timeout`. The iteration
+    /// under way is finished first, so a slow model may overrun the budget by one iteration.
+    /// Fits running several solvers, like [`CurveFit::fit_multistart`], give each the budget.
+    pub timeout: Option<Duration>,
 }
 
 /// Fit method enum.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Method {
     /// Levenberg-Marquardt algorithm
     LM,   
     /// Dogleg algorithm   
     DogBox,  
     /// Trust Region Reflective algorithm
-    TRF      
+    TRF,
+    /// Nelder-Mead simplex search. Needs no derivatives but many more model evaluations;
+    /// ignores `x_scale` and does not support bounds.
+    NelderMead,
+    /// Limited-memory BFGS on the sum of squares, a general quasi-Newton minimizer using only
+    /// its gradient. Usually slower than the least-squares methods above; does not support
+    /// bounds.
+    LBFGS,
+    /// Linear least squares for models linear in their parameters, like polynomials or sums
+    /// of fixed basis functions: one Jacobian, at `p0`, and steps with its pseudo-inverse,
+    /// which end at the same solution from any `p0`. A model that is not linear usually gives
+    /// `Error::DidNotConverge`; does not support bounds.
+    LinearLSQ,
+    /// Stochastic gradient descent: each step follows the gradient of the mean squared
+    /// residual over a random mini-batch of `batch_size` points, times `learning_rate`, and
+    /// each of `Config::n_epochs` epochs visits every point once. Steps cost `batch_size`
+    /// rather than all points, which pays off for data sets of many thousands of points where
+    /// a rough estimate is enough; the parameters keep fluctuating with the batches, so the
+    /// other methods are more precise. `learning_rate` has to suit the model and data, too
+    /// large diverges and too small stalls. Ignores `x_scale` and does not support bounds.
+    SGD { batch_size: usize, learning_rate: f64 },
+    /// The default, picking one of the methods above for each fit by the rules below, which
+    /// depend on the problem alone; see [`FitReport::method`] for the one that ran:
+    ///
+    /// 1. TRF when a parameter has a finite bound, since LM cannot honour bounds.
+    /// 2. Nelder-Mead when the residuals or the finite-difference Jacobian are not finite at
+    ///    the initial guess, e.g. for a model undefined just past `p0`, since the other
+    ///    methods would stop there.
+    /// 3. LM otherwise, which is the fastest on smooth unconstrained problems.
+    ///
+    /// Checking rule 2 takes one evaluation of the residuals and the Jacobian, counted in
+    /// [`FitReport::n_fev`]; set `Method::LM` to skip it.
+    Auto,
+}
+
+/// Formats as the name that `str::parse` takes back: `lm`, `dogbox`, `trf`,
+/// `nelder-mead`, `lbfgs`, `linear-lsq`, `auto`, and `sgd(batch_size, learning_rate)` such as
+/// `sgd(32, 0.01)`.
+impl std::fmt::Display for Method {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Method::LM => write!(f, "lm"),
+            Method::DogBox => write!(f, "dogbox"),
+            Method::TRF => write!(f, "trf"),
+            Method::NelderMead => write!(f, "nelder-mead"),
+            Method::LBFGS => write!(f, "lbfgs"),
+            Method::LinearLSQ => write!(f, "linear-lsq"),
+            Method::SGD {
+                batch_size,
+                learning_rate,
+            } => write!(f, "sgd({batch_size}, {learning_rate})"),
+            Method::Auto => write!(f, "auto"),
+        }
+    }
+}
+
+/// Parses the names that [`Method`] displays as, e.g. from a configuration file, ignoring
+/// case and surrounding spaces and taking `_` for `-`. The full names `levenberg-marquardt`,
+/// `trust-region-reflective` and `l-bfgs` are accepted too; anything else is
+/// `Error::UnknownMethod`.
+impl std::str::FromStr for Method {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let name = s.trim().to_ascii_lowercase().replace('_', "-");
+        let method = match name.as_str() {
+            "lm" | "levenberg-marquardt" => Method::LM,
+            "dogbox" => Method::DogBox,
+            "trf" | "trust-region-reflective" => Method::TRF,
+            "nelder-mead" => Method::NelderMead,
+            "lbfgs" | "l-bfgs" => Method::LBFGS,
+            "linear-lsq" => Method::LinearLSQ,
+            "auto" => Method::Auto,
+            _ => {
+                let sgd = name.strip_prefix("sgd(").and_then(|args| args.strip_suffix(')'));
+                let (batch_size, learning_rate) = sgd
+                    .and_then(|args| args.split_once(','))
+                    .and_then(|(b, l)| Some((b.trim().parse().ok()?, l.trim().parse().ok()?)))
+                    .ok_or_else(|| Error::UnknownMethod {
+                        name: s.to_string(),
+                    })?;
+                Method::SGD {
+                    batch_size,
+                    learning_rate,
+                }
+            }
+        };
+        Ok(method)
+    }
+}
+
+/// Initial guess of the parameters when the model gives no better one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum P0Strategy {
+    /// Every parameter starts at `Config::p0`, 1 by default.
+    Constant,
+    /// Parameters start at magnitudes of the data, cycling through `mean(|y|)`,
+    /// `1 / (max(x) - min(x))` and `median(y)` over the parameter slots, and falling back to
+    /// `Config::p0` where one of them is zero or not finite. A crude heuristic that knows
+    /// nothing of the model, but puts amplitudes, rates and offsets on the scale of the data,
+    /// which the solvers may never reach from 1. Fits from a guess of the
+    /// [`models`] module ignore it; [`CurveFit::fit_multistart`] tries this guess before the
+    /// random starts.
+    DataScaled,
+}
+
+/// What to do with data points whose `x` or `y` is NaN or infinite.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NonFinitePolicy {
+    /// Fail with `Error::NonFiniteData`.
+    Error,
+    /// Leave the point out of the fit.
+    Drop,
+    /// Fit the data as given.
+    Allow,
+}
+
+/// Finite differences approximating the Jacobian of the model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JacMethod {
+    /// `(f(p + h) - f(p)) / h`, `N` model evaluations per Jacobian with an error of order `h`.
+    Forward,
+    /// `(f(p + h) - f(p - h)) / 2h`, `2N` model evaluations per Jacobian with an error of
+    /// order `h^2`, for fits that need derivatives more accurate than the default ones. Near a
+    /// bound, a parameter falls back to a one-sided difference. `fit_odr` and `Method::SGD`
+    /// always use forward differences.
+    Central,
+}
+
+/// Damping of the Levenberg-Marquardt iterations. The damped step solves
+/// `(J^T J + lambda * I) step = -J^T r`, through the SVD of `J`: a small `lambda` gives the
+/// Gauss-Newton step, a large one a short step down the gradient. `lambda` shrinks after every
+/// step that lowers the sum of squares and grows after every one that does not.
+///
+/// The defaults suit most problems. A model that overshoots from a poor guess may start from a
+/// larger `lambda_init`, and one that crawls through many small steps may shrink `lambda`
+/// faster with a larger `lambda_down_factor`.
+#[derive(Debug, Clone, Copy, PartialEq, range_checker::CheckVerbose)]
+pub struct LmOptions {
+    /// Initial `lambda` relative to the largest diagonal entry of `J^T J`, 1e-3 by default.
+    #[filter(|lambda: &f64| *lambda > 0.0 && lambda.is_finite())]
+    pub lambda_init: f64,
+    /// Factor multiplying `lambda` after a rejected step, 10 by default.
+    #[filter(|factor: &f64| *factor > 0.0 && factor.is_finite())]
+    pub lambda_up_factor: f64,
+    /// Factor dividing `lambda` after an accepted step, 10 by default.
+    #[filter(|factor: &f64| *factor > 0.0 && factor.is_finite())]
+    pub lambda_down_factor: f64,
+    /// Largest `lambda`, relative like `lambda_init`, 1e16 by default. Past it the steps are
+    /// lost to rounding, and the fit stops with `TerminationReason::MaxDamping` instead of
+    /// rejecting ever shorter ones. `f64::INFINITY` lifts the limit.
+    #[filter(|lambda: &f64| *lambda > 0.0)]
+    pub lambda_max: f64,
+    /// Singular values of the scaled Jacobian up to `svd_threshold` times the largest one are
+    /// taken as zero, in `[0, 1)`; 1e-12 by default, which drops the directions lost to
+    /// rounding. The steps leave these directions alone, so that with little damping rounding
+    /// errors do not drive a redundant parameter of an over-parameterized model and its
+    /// partner far apart. A larger threshold also drops poorly determined directions. Global
+    /// fits and orthogonal distance regression apply it to their Jacobians the same way.
+    #[filter(|threshold: &f64| (0.0..1.0).contains(threshold))]
+    pub svd_threshold: f64,
+}
+
+impl Default for LmOptions {
+    fn default() -> Self {
+        Self {
+            lambda_init: 1e-3,
+            lambda_up_factor: 10.0,
+            lambda_down_factor: 10.0,
+            lambda_max: 1e16,
+            svd_threshold: 1e-12,
+        }
+    }
+}
+
+/// Trust region of the TRF and DogBox iterations, a ball for TRF and a box for DogBox in the
+/// scaled parameters, within which the linearized model is trusted. Each step is the best one
+/// within the radius; it is accepted when it lowers the sum of squares by at least
+/// `eta_accept` times the reduction the linearized model predicted, their ratio. The radius
+/// shrinks to `shrink_factor` times the step after a ratio below 1/4 or a rejected step, and
+/// grows by `expand_factor` after a ratio above 3/4 of a step reaching the boundary.
+///
+/// A model that rejects many first steps may start from a smaller `initial_radius`, and one
+/// whose linearization holds only nearby may limit `max_radius`.
+#[derive(Debug, Clone, Copy, PartialEq, range_checker::CheckVerbose)]
+pub struct TrustRegionOptions {
+    /// Initial radius, `None` by default for the norm of the scaled initial guess, the largest
+    /// of its entries for DogBox, or 1 when that is zero: the first step may move the
+    /// parameters by about their own size, whatever their units, and the radius then adapts
+    /// to the steps that succeed.
+    ///
+    /// Set a smaller radius when the first steps leave the region where the model is defined
+    /// or jump into another minimum, and a larger one when `p0` is all zeros but the solution
+    /// is far away.
+    pub initial_radius: Option<f64>,
+    /// Largest radius, unlimited by default.
+    #[filter(|radius: &f64| *radius > 0.0)]
+    pub max_radius: f64,
+    /// Smallest accepted ratio of the actual to the predicted reduction, in `[0, 1)`; 0 by
+    /// default, accepting every step that lowers the sum of squares.
+    #[filter(|eta: &f64| (0.0..1.0).contains(eta))]
+    pub eta_accept: f64,
+    /// Factor shrinking the radius, in `(0, 1)`, 1/4 by default.
+    #[filter(|factor: &f64| *factor > 0.0 && *factor < 1.0)]
+    pub shrink_factor: f64,
+    /// Factor growing the radius, greater than 1 and finite, 2 by default.
+    #[filter(|factor: &f64| *factor > 1.0 && factor.is_finite())]
+    pub expand_factor: f64,
+}
+
+impl Default for TrustRegionOptions {
+    fn default() -> Self {
+        Self {
+            initial_radius: None,
+            max_radius: f64::INFINITY,
+            eta_accept: 0.0,
+            shrink_factor: 0.25,
+            expand_factor: 2.0,
+        }
+    }
+}
+
+/// What to do with an initial guess outside the bounds of a parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutOfBoundsPolicy {
+    /// Start from the nearest bound, with a warning under the `tracing` feature.
+    Clamp,
+    /// Fail with `Error::GuessOutOfBounds`.
+    Error,
+}
+
+/// How the solver scales the parameters it steps in.
+///
+/// Steps are taken in the variables `p / d` for a characteristic scale `d` of each parameter,
+/// which keeps the trust region or damping meaningful when parameters differ by orders of
+/// magnitude. The fitted parameters are always reported unscaled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XScale {
+    /// Every parameter has scale 1.
+    None,
+    /// Scales are the inverse column norms of the Jacobian, updated as the fit progresses
+    /// (scipy's `x_scale='jac'`).
+    Auto,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -30,113 +365,4256 @@ pub enum Error {
         x_data_len: usize,
         y_data_len: usize,
     },
-    #[error("config {0}")]
-    ConfigCheckFailed(range_checker::Error),
+    #[error("config validation failed")]
+    ConfigCheckFailed(#[from] range_checker::Error),
+    #[error("{} config checks failed: {}", .0.len(), join_errors(.0))]
+    MultipleConfigErrors(Vec<Error>),
+    #[error("non-finite data point at index {index}")]
+    NonFiniteData { index: usize },
+    /// Only rejected by fits of the logarithm of `y`.
+    #[error("data point {index} has y = {y}, which has no logarithm")]
+    NonPositiveData { index: usize, y: f64 },
+    /// Only checked with `Config::require_sorted`.
+    #[error("x_data decreases at index {index}")]
+    UnsortedInput { index: usize },
+    #[error("{n_data} data points are too few to fit {n_params} parameters")]
+    InsufficientData { n_data: usize, n_params: usize },
+    #[error("every data point has x = {x}, which determines at most one parameter")]
+    DegenerateData { x: f64 },
+    /// Every `y` is the same, so any parameters giving a constant model fit exactly.
+    #[error("every data point has y = {y}, which has zero variance to fit")]
+    ZeroVarianceData { y: f64 },
+    #[error("invalid start range for parameter {index}: ({lower}, {upper})")]
+    InvalidStartRange { index: usize, lower: f64, upper: f64 },
+    #[error("none of the {n_starts} starts converged")]
+    NoConvergedStart { n_starts: usize },
+    #[error("invalid scale for parameter {index}: {value}")]
+    InvalidScale { index: usize, value: f64 },
+    #[error("confidence level {confidence} is not in (0, 1)")]
+    InvalidConfidence { confidence: f64 },
+    #[error("none of the {n_resamples} resampled refits converged")]
+    NoConvergedResample { n_resamples: usize },
+    #[error("{k} folds cannot split {n_data} data points, k must be in [2, {n_data}]")]
+    InvalidFoldCount { k: usize, n_data: usize },
+    #[error("the fit of none of the {k} folds converged")]
+    NoConvergedFold { k: usize },
+    #[error("invalid bounds for parameter {index}: ({lower}, {upper})")]
+    InvalidBounds { index: usize, lower: f64, upper: f64 },
+    #[error("initial guess {p0} is outside the bounds of parameter {index}: [{lower}, {upper}]")]
+    GuessOutOfBounds {
+        index: usize,
+        p0: f64,
+        lower: f64,
+        upper: f64,
+    },
+    #[error("method {method:?} does not support bounds")]
+    BoundsUnsupported { method: Method },
+    #[error(
+        "unknown method {name:?}, expected one of lm, dogbox, trf, nelder-mead, lbfgs, \
+         linear-lsq, sgd(batch_size, learning_rate) or auto"
+    )]
+    UnknownMethod { name: String },
+    #[error("unmatched sigma length. data: {data_len} != sigma: {sigma_len}")]
+    UnmatchedSigmaLength { data_len: usize, sigma_len: usize },
+    #[error("unmatched mask length. data: {data_len} != mask: {mask_len}")]
+    UnmatchedMaskLength { data_len: usize, mask_len: usize },
+    #[error("invalid standard deviation of point {index}: {value}")]
+    InvalidSigma { index: usize, value: f64 },
+    #[error("invalid weight of point {index}: {value}")]
+    InvalidWeight { index: usize, value: f64 },
+    #[error("the curve does not cross the target between {lower} and {upper}")]
+    RootNotBracketed { lower: f64, upper: f64 },
+    #[error("no root within tolerance after {max_iterations} iterations")]
+    RootNotConverged { max_iterations: usize },
+    /// Every column of the Jacobian was zero where the fit ended, which stops the derivative
+    /// based solvers right at the initial guess; typically the model ignores its parameters.
+    #[error("the model does not depend on its parameters at {params:?}")]
+    ZeroJacobian { params: Vec<f64> },
+    /// The solver stopped without converging; the last point it reached is kept for
+    /// inspection, not for use as a fit.
+    #[error("the solver did not converge ({reason:?} after {iterations} iterations)")]
+    DidNotConverge {
+        reason: TerminationReason,
+        iterations: usize,
+        last_params: Vec<f64>,
+        /// Sum of squared residuals at `last_params`.
+        last_cost: f64,
+    },
+    /// The solver ran out of `Config::timeout`; `last_params` are the best found until then.
+    #[error("the fit timed out after {elapsed:?} and {iterations} iterations")]
+    Timeout {
+        elapsed: Duration,
+        iterations: usize,
+        last_params: Vec<f64>,
+        /// Sum of squared residuals at `last_params`.
+        last_cost: f64,
+    },
+    /// A `polars` series could not be read as numbers.
+    #[cfg(feature = "polars")]
+    #[error("polars {0}")]
+    PolarsError(#[from] polars::error::PolarsError),
+}
+
+fn join_errors(errors: &[Error]) -> String {
+    let message = |error: &Error| match std::error::Error::source(error) {
+        Some(source) => format!("{error}: {source}"),
+        None => error.to_string(),
+    };
+    errors.iter().map(message).collect::<Vec<_>>().join("; ")
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             p0: 1.0,
+            p0_strategy: P0Strategy::Constant,
             check_finite: true,
-            method: Method::LM
+            non_finite: NonFinitePolicy::Error,
+            sort_input: false,
+            require_sorted: false,
+            method: Method::Auto,
+            seed: None,
+            x_scale: XScale::None,
+            record_history: false,
+            record_trace: false,
+            trace_rejected: false,
+            regularization: None,
+            n_epochs: 100,
+            diff_step: None,
+            jacobian: JacMethod::Forward,
+            lm: LmOptions::default(),
+            tr: TrustRegionOptions::default(),
+            out_of_bounds: OutOfBoundsPolicy::Clamp,
+            timeout: None,
+        }
+    }
+}
+
+impl Config {
+    /// Checks every field, returning all failures as `Error::ConfigCheckFailed` rather than
+    /// stopping at the first.
+    pub fn validate_all(&self) -> Result<(), Vec<Error>> {
+        let mut errors: Vec<Error> = match self.check() {
+            Ok(()) => vec![],
+            Err(errors) => errors.into_iter().map(Error::from).collect(),
+        };
+        if let Err(lm_errors) = self.lm.check() {
+            errors.extend(lm_errors.into_iter().map(Error::from));
+        }
+        if let Err(tr_errors) = self.tr.check() {
+            errors.extend(tr_errors.into_iter().map(Error::from));
+        }
+        if let Some(radius) = self.tr.initial_radius {
+            if !(radius > 0.0 && radius.is_finite()) {
+                errors.push(
+                    range_checker::Error::CheckFailed {
+                        ident: "initial_radius".to_string(),
+                        value: radius.to_string(),
+                        check_statement: "radius > 0.0 && radius.is_finite()".to_string(),
+                    }
+                    .into(),
+                );
+            }
+        }
+        if let Some(lambda) = self.regularization {
+            if !(lambda >= 0.0 && lambda.is_finite()) {
+                errors.push(
+                    range_checker::Error::CheckFailed {
+                        ident: "regularization".to_string(),
+                        value: lambda.to_string(),
+                        check_statement: "lambda >= 0.0 && lambda.is_finite()".to_string(),
+                    }
+                    .into(),
+                );
+            }
+        }
+        if let Some(diff_step) = self.diff_step {
+            if !(diff_step > 0.0 && diff_step.is_finite()) {
+                errors.push(
+                    range_checker::Error::CheckFailed {
+                        ident: "diff_step".to_string(),
+                        value: diff_step.to_string(),
+                        check_statement: "diff_step > 0.0 && diff_step.is_finite()".to_string(),
+                    }
+                    .into(),
+                );
+            }
+        }
+        if let Method::SGD {
+            batch_size,
+            learning_rate,
+        } = self.method
+        {
+            let check = |value: String, check_statement: &str| {
+                Error::from(range_checker::Error::CheckFailed {
+                    ident: "method".to_string(),
+                    value,
+                    check_statement: check_statement.to_string(),
+                })
+            };
+            if batch_size == 0 {
+                errors.push(check(batch_size.to_string(), "batch_size > 0"));
+            }
+            if !(learning_rate > 0.0 && learning_rate.is_finite()) {
+                errors.push(check(
+                    learning_rate.to_string(),
+                    "learning_rate > 0.0 && learning_rate.is_finite()",
+                ));
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Initial guess of every parameter for the data to fit, as `p0_strategy` says.
+    pub(crate) fn initial_guess<const N: usize>(
+        &self,
+        x_data: &[f64],
+        y_data: &[f64],
+    ) -> [f64; N] {
+        let candidates = match self.p0_strategy {
+            P0Strategy::DataScaled if !y_data.is_empty() => {
+                let m = y_data.len() as f64;
+                let mut sorted = y_data.to_vec();
+                sorted.sort_by(f64::total_cmp);
+                let (min, max) = x_data
+                    .iter()
+                    .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &x| {
+                        (min.min(x), max.max(x))
+                    });
+                [
+                    y_data.iter().map(|y| y.abs()).sum::<f64>() / m,
+                    1.0 / (max - min),
+                    stats::quantile(&sorted, 0.5),
+                ]
+            }
+            _ => return [self.p0; N],
+        };
+        std::array::from_fn(|i| {
+            let candidate = candidates[i % candidates.len()];
+            if candidate.is_normal() {
+                candidate
+            } else {
+                self.p0
+            }
+        })
+    }
+
+    /// Generator for randomized strategies, seeded from `seed` when it is set.
+    pub(crate) fn rng(&self) -> StdRng {
+        match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_os_rng(),
+        }
+    }
+
+    fn scaling<const N: usize>(&self) -> solver::Scaling<N> {
+        match self.x_scale {
+            XScale::None => solver::Scaling::Fixed([1.0; N]),
+            XScale::Auto => solver::Scaling::Jacobian,
         }
     }
 }
 
+/// A model function with fitted parameters.
+///
+/// A curve, like the results holding one, is `Send` and `Sync` whenever its function is, as
+/// plain `fn` items and closures over such data are, so it can be fitted on a worker thread
+/// and sent back.
+#[derive(Clone)]
 pub struct Curve<const N: usize, F: Fn(f64, [f64; N]) -> f64> {
     func: F,
     params: [f64; N],
+    parameter_names: Option<[&'static str; N]>,
+    derivative: Option<fn(f64, [f64; N]) -> f64>,
+    at_bounds: [bool; N],
+    /// Covariance of the parameters estimated by the fit, as in [`FitResult::covariance`].
+    covariance: [[f64; N]; N],
 }
 
 impl<const N: usize, F: Fn(f64, [f64; N]) -> f64> Curve<N, F> {
+    /// The curve of `func` at known parameters, e.g. from a paper or an earlier fit, to
+    /// evaluate, differentiate or integrate without data. The parameters are taken as exact:
+    /// their covariance is zero, so [`Curve::predict_with_errors`] gives zero errors, and none
+    /// is at a bound.
+    pub fn from_params(func: F, params: [f64; N]) -> Self {
+        Self {
+            func,
+            params,
+            parameter_names: None,
+            derivative: None,
+            at_bounds: [false; N],
+            covariance: [[0.0; N]; N],
+        }
+    }
+
     pub fn eval(&self, x: f64) -> f64 {
         (self.func)(x, self.params)
     }
-}
 
-pub trait CurveFit<const N: usize>
-where
-    Self: std::marker::Sized + Fn(f64, [f64; N]) -> f64,
-{
-    fn fit(&self, x_data: &[f64], y_data: &[f64], cfg: Config) -> Result<Curve<N, Self>, Error>;
-}
+    pub fn params(&self) -> [f64; N] {
+        self.params
+    }
 
-impl<T, const N: usize> CurveFit<N> for T
-where
-    T: Fn(f64, [f64; N]) -> f64 + Clone + Copy,
-{
-    fn fit(&self, x_data: &[f64], y_data: &[f64], cfg: Config) -> Result<Curve<N, Self>, Error> {
-        // data length check
+    /// [`Curve::eval`] at each element of a one-dimensional array or view.
+    pub fn eval_array<S: Data<Elem = f64>>(&self, x: &ArrayBase<S, Ix1>) -> Array1<f64> {
+        x.mapv(|x| self.eval(x))
+    }
+
+    /// The curve as a plain function of `x`, borrowing it, for code that takes a closure
+    /// rather than a curve, such as integration or root-finding routines of other crates.
+    pub fn as_fn(&self) -> impl Fn(f64) -> f64 + '_ {
+        |x| self.eval(x)
+    }
+
+    /// Names the parameters, in the order of the model's parameter array, for display.
+    pub fn with_names(mut self, names: [&'static str; N]) -> Self {
+        self.parameter_names = Some(names);
+        self
+    }
+
+    pub fn parameter_names(&self) -> Option<[&'static str; N]> {
+        self.parameter_names
+    }
+
+    /// Which parameters the fit left on, or within `1e-8` relative of, one of the bounds of
+    /// [`CurveFit::fit_bounded`]. A parameter pinned at a bound often points at a problem with
+    /// the model or the bounds. Always `false` for unbounded fits.
+    pub fn at_bounds(&self) -> [bool; N] {
+        self.at_bounds
+    }
+
+    /// Slope `df/dx` of the curve at `x`.
+    ///
+    /// Uses the analytic derivative set by [`Curve::with_derivative`] if any, and otherwise the
+    /// five-point central difference `(8(f(x+h) - f(x-h)) - (f(x+2h) - f(x-2h))) / 12h`, which
+    /// is exact for polynomials up to degree four. The step is relative,
+    /// `h = EPSILON^(1/5) * max(|x|, 1)`, so it never vanishes against a large `x`, and a curve
+    /// that is flat at `x` gives exactly zero.
+    pub fn derivative(&self, x: f64) -> f64 {
+        if let Some(derivative) = self.derivative {
+            return derivative(x, self.params);
+        }
+        let h = f64::EPSILON.powf(0.2) * x.abs().max(1.0);
+        // use the step that is actually representable
+        let h = (x + h) - x;
+        let f = |x| (self.func)(x, self.params);
+        (8.0 * (f(x + h) - f(x - h)) - (f(x + 2.0 * h) - f(x - 2.0 * h))) / (12.0 * h)
+    }
+
+    /// [`Curve::derivative`] at each of `x`.
+    pub fn derivative_slice(&self, x: &[f64]) -> Vec<f64> {
+        x.iter().map(|&x| self.derivative(x)).collect()
+    }
+
+    /// Fitted value and its standard error `(y, sigma_y)` at each of `xs`, e.g. to plot a
+    /// one-sigma band around the curve. `sigma_y^2 = g C g^T` propagates the covariance `C` of
+    /// the fit through the gradient `g` of the model with respect to the parameters, so the
+    /// band reflects the uncertainty of the curve, not the scatter of the data about it.
+    pub fn predict_with_errors(&self, xs: &[f64]) -> Vec<(f64, f64)> {
+        xs.iter()
+            .map(|&x| (self.eval(x), self.mean_variance(x).sqrt()))
+            .collect()
+    }
+
+    /// Cost `0.5 * sum((f(x_i) - y_i)^2)` of the curve on the points, the objective the solvers
+    /// minimize and half the sum of squared residuals, e.g. to compare parameters from
+    /// elsewhere with a fit. The data are not checked for non-finite values.
+    pub fn cost(&self, x_data: &[f64], y_data: &[f64]) -> Result<f64, Error> {
+        if x_data.len() != y_data.len() {
+            return Err(Error::UnmatchedLength {
+                x_data_len: x_data.len(),
+                y_data_len: y_data.len(),
+            });
+        }
+        let squares = x_data.iter().zip(y_data).map(|(&x, y)| (self.eval(x) - y).powi(2));
+        Ok(0.5 * squares.sum::<f64>())
+    }
+
+    /// p-value of the chi-squared goodness-of-fit test of the curve on points with standard
+    /// deviations `sigma`: the probability that `chi2 = sum(((f(x_i) - y_i) / sigma_i)^2)`
+    /// reaches its value by chance, under the chi-squared distribution with `m - N` degrees of
+    /// freedom, if the model and `sigma` are right. A small p-value, e.g. below 0.05, rejects
+    /// the model or the `sigma`; one close to 1 suggests overestimated `sigma`.
+    ///
+    /// `sigma` must be positive and of the data's length, and there must be more points than
+    /// parameters; all `N` parameters count as fitted.
+    pub fn chi2_pvalue(&self, x_data: &[f64], y_data: &[f64], sigma: &[f64]) -> Result<f64, Error> {
         if x_data.len() != y_data.len() {
             return Err(Error::UnmatchedLength {
                 x_data_len: x_data.len(),
                 y_data_len: y_data.len(),
             });
         }
+        if sigma.len() != x_data.len() {
+            return Err(Error::UnmatchedSigmaLength {
+                data_len: x_data.len(),
+                sigma_len: sigma.len(),
+            });
+        }
+        if let Some(index) = sigma.iter().position(|s| !(*s > 0.0 && s.is_finite())) {
+            return Err(Error::InvalidSigma { index, value: sigma[index] });
+        }
+        if x_data.len() <= N {
+            return Err(Error::InsufficientData { n_data: x_data.len(), n_params: N });
+        }
+        let points = x_data.iter().zip(y_data).zip(sigma);
+        let chi2: f64 = points.map(|((&x, y), s)| ((self.eval(x) - y) / s).powi(2)).sum();
+        Ok(stats::chi_squared_sf(chi2, (x_data.len() - N) as f64))
+    }
+
+    /// Variance `g C g^T` of the fitted value at `x`.
+    fn mean_variance(&self, x: f64) -> f64 {
+        let g = self.parameter_gradient(x);
+        g.iter()
+            .zip(&self.covariance)
+            .map(|(g_i, row)| g_i * row.iter().zip(&g).map(|(c, g_j)| c * g_j).sum::<f64>())
+            .sum()
+    }
+
+    /// Derivatives `df/dp_i` of the model at `x` with respect to the parameters, by central
+    /// differences with steps `EPSILON^(1/3) * max(|p_i|, 1)`.
+    fn parameter_gradient(&self, x: f64) -> [f64; N] {
+        std::array::from_fn(|i| {
+            let h = f64::EPSILON.cbrt() * self.params[i].abs().max(1.0);
+            let (mut forward, mut backward) = (self.params, self.params);
+            forward[i] += h;
+            backward[i] -= h;
+            ((self.func)(x, forward) - (self.func)(x, backward)) / (forward[i] - backward[i])
+        })
+    }
+
+    /// Central difference `(f(x + eps) - f(x - eps)) / 2 eps` of the curve at `x`, with a step
+    /// chosen by the caller. See [`Curve::derivative`] for an automatic step.
+    pub fn gradient(&self, x: f64, eps: f64) -> f64 {
+        (self.eval(x + eps) - self.eval(x - eps)) / (2.0 * eps)
+    }
+
+    /// Location of the maximum of the curve on `[x_min, x_max]`: the best of `n_grid` equally
+    /// spaced points, refined by a golden-section search between its neighbours to a relative
+    /// precision of `sqrt(EPSILON)`, which is as close as values near a maximum can tell.
+    /// Narrow peaks between grid points can be missed, so `n_grid` should resolve them.
+    ///
+    /// # Panics
+    ///
+    /// If `n_grid < 2` or `x_min < x_max` does not hold.
+    pub fn find_peak(&self, x_min: f64, x_max: f64, n_grid: usize) -> f64 {
+        assert!(n_grid >= 2, "a grid needs at least 2 points, not {n_grid}");
+        assert!(x_min < x_max, "empty range [{x_min}, {x_max}]");
+        let step = (x_max - x_min) / (n_grid - 1) as f64;
+        let grid = |i: usize| x_min + i as f64 * step;
+        let best = (0..n_grid)
+            .max_by(|&i, &j| self.eval(grid(i)).total_cmp(&self.eval(grid(j))))
+            .expect("the grid is not empty");
+
+        let inverse_golden = 0.5 * (5f64.sqrt() - 1.0);
+        let (mut a, mut b) = (grid(best.saturating_sub(1)), grid((best + 1).min(n_grid - 1)));
+        let mut c = b - inverse_golden * (b - a);
+        let mut d = a + inverse_golden * (b - a);
+        let (mut f_c, mut f_d) = (self.eval(c), self.eval(d));
+        while b - a > f64::EPSILON.sqrt() * c.abs().max(1.0) {
+            if f_c > f_d {
+                (b, d, f_d) = (d, c, f_c);
+                c = b - inverse_golden * (b - a);
+                f_c = self.eval(c);
+            } else {
+                (a, c, f_c) = (c, d, f_d);
+                d = a + inverse_golden * (b - a);
+                f_d = self.eval(d);
+            }
+        }
+        0.5 * (a + b)
+    }
+
+    /// Area under the curve between `a` and `b`, e.g. of a fitted peak, to a relative precision
+    /// of about `1e-10`. See [`Curve::integrate_with_error`].
+    pub fn integrate(&self, a: f64, b: f64) -> f64 {
+        self.integrate_with_error(a, b, 1e-10, 50).0
+    }
+
+    /// Integral of the curve from `a` to `b` together with an estimate of its absolute error.
+    ///
+    /// Adaptive 7-15 point Gauss-Kronrod quadrature keeps bisecting the subinterval with the
+    /// largest error until the total error is below `tolerance` times the integral of `|f|`,
+    /// or after `max_subdivisions` bisections. The rule is exact for polynomials up to degree
+    /// 22, so those need no bisection. The integral is negated for `a > b` and exactly zero
+    /// for `a == b`.
+    pub fn integrate_with_error(
+        &self,
+        a: f64,
+        b: f64,
+        tolerance: f64,
+        max_subdivisions: usize,
+    ) -> (f64, f64) {
+        quadrature::integrate(|x| self.eval(x), a, b, tolerance, max_subdivisions)
+    }
+
+    /// Integral of the curve from `a` to `b` by the composite Simpson rule on `n_intervals`
+    /// equal subintervals, rounded up to an even number of at least 2, which costs exactly
+    /// `n_intervals + 1` evaluations of the curve. Exact for cubics; for other curves
+    /// [`Curve::integrate_with_error`] adapts to a tolerance instead. Negated for `a > b`.
+    pub fn integrate_simpson(&self, a: f64, b: f64, n_intervals: usize) -> f64 {
+        quadrature::simpson(|x| self.eval(x), a, b, n_intervals)
+    }
+
+    /// The `x` within `bracket` at which the curve takes the value `y`, e.g. to read a
+    /// calibration curve backwards, to within `2e-12 + 4 EPSILON |x|`. See
+    /// [`Curve::solve_for_x_with`].
+    pub fn solve_for_x(&self, y: f64, bracket: (f64, f64)) -> Result<f64, Error> {
+        self.solve_for_x_with(y, bracket, 2e-12, 100)
+    }
+
+    /// [`Curve::solve_for_x`] to within `tolerance + 4 EPSILON |x|`, giving up with
+    /// `Error::RootNotConverged` after `max_iterations` evaluations of the curve.
+    ///
+    /// Brent's method, which combines bisection with secant and inverse quadratic steps.
+    /// `f(x) - y` must have opposite signs at the two ends of `bracket`, in either order, or
+    /// be zero at one of them; otherwise, including when the curve crosses `y` an even number
+    /// of times in between, the result is `Error::RootNotBracketed`. Of an odd number of
+    /// crossings one is returned, not necessarily the first.
+    pub fn solve_for_x_with(
+        &self,
+        y: f64,
+        bracket: (f64, f64),
+        tolerance: f64,
+        max_iterations: usize,
+    ) -> Result<f64, Error> {
+        let f = |x| self.eval(x) - y;
+        let (mut a, mut b) = bracket;
+        let (mut fa, mut fb) = (f(a), f(b));
+        if fa == 0.0 {
+            return Ok(a);
+        }
+        if fb == 0.0 {
+            return Ok(b);
+        }
+        if fa.is_nan() || fb.is_nan() || fa.signum() == fb.signum() {
+            return Err(Error::RootNotBracketed { lower: a, upper: b });
+        }
+
+        // `b` is the best estimate and `c` the end of the bracket opposite to it, `a` is the
+        // previous `b`; `d` is the last step and `e` the one before
+        let (mut c, mut fc) = (b, fb);
+        let (mut d, mut e) = (b - a, b - a);
+        for _ in 0..max_iterations {
+            if fb.signum() == fc.signum() {
+                (c, fc) = (a, fa);
+                (d, e) = (b - a, b - a);
+            }
+            if fc.abs() < fb.abs() {
+                (a, fa) = (b, fb);
+                (b, fb) = (c, fc);
+                (c, fc) = (a, fa);
+            }
+            let tol = 2.0 * f64::EPSILON * b.abs() + 0.5 * tolerance;
+            let middle = 0.5 * (c - b);
+            if middle.abs() <= tol || fb == 0.0 {
+                return Ok(b);
+            }
+            if e.abs() >= tol && fa.abs() > fb.abs() {
+                // secant step if only two points are known, else inverse quadratic
+                let s = fb / fa;
+                let (p, q) = if a == c {
+                    (2.0 * middle * s, 1.0 - s)
+                } else {
+                    let (q, r) = (fa / fc, fb / fc);
+                    (
+                        s * (2.0 * middle * q * (q - r) - (b - a) * (r - 1.0)),
+                        (q - 1.0) * (r - 1.0) * (s - 1.0),
+                    )
+                };
+                let (p, q) = if p > 0.0 { (p, -q) } else { (-p, q) };
+                // interpolate only if the step stays inside the bracket and shrinks fast enough
+                if 2.0 * p < (3.0 * middle * q - (tol * q).abs()).min((e * q).abs()) {
+                    (e, d) = (d, p / q);
+                } else {
+                    (d, e) = (middle, middle);
+                }
+            } else {
+                (d, e) = (middle, middle);
+            }
+            (a, fa) = (b, fb);
+            b += if d.abs() > tol { d } else { tol.copysign(middle) };
+            fb = f(b);
+        }
+        Err(Error::RootNotConverged { max_iterations })
+    }
 
-        // config check
-        if let Err(e) = cfg.check() {
-            if let Some(e) = e.into_iter().next() {
-                return Err(Error::ConfigCheckFailed(e));
+    /// Least-squares polynomial of the given `degree` through the curve sampled at `n_points`
+    /// equally spaced points of `[x_min, x_max]`, as coefficients `c` in ascending powers,
+    /// `c[0] + c[1] x + ... + c[degree] x^degree`. Solved by a QR decomposition, which is
+    /// accurate enough for moderate degrees over ranges not far from the origin.
+    ///
+    /// # Panics
+    ///
+    /// If `n_points <= degree` or `x_min < x_max` does not hold.
+    pub fn to_polynomial_approx(
+        &self,
+        x_min: f64,
+        x_max: f64,
+        degree: usize,
+        n_points: usize,
+    ) -> Vec<f64> {
+        assert!(
+            n_points > degree,
+            "{n_points} points cannot determine a degree {degree} polynomial"
+        );
+        assert!(x_min < x_max, "empty range [{x_min}, {x_max}]");
+        let step = (x_max - x_min) / (n_points - 1) as f64;
+        let x = nalgebra::DVector::from_fn(n_points, |i, _| x_min + i as f64 * step);
+        let vandermonde =
+            nalgebra::DMatrix::from_fn(n_points, degree + 1, |i, j| x[i].powi(j as i32));
+        let y = x.map(|x| self.eval(x));
+        let qr = vandermonde.qr();
+        // R only has a zero on its diagonal if the powers of x are exactly dependent
+        qr.r()
+            .solve_upper_triangular(&qr.q().tr_mul(&y))
+            .map_or_else(|| vec![f64::NAN; degree + 1], |c| c.as_slice().to_vec())
+    }
+
+    /// Replaces the numerical [`Curve::derivative`] by the analytic `df/dx` of the model,
+    /// called with the fitted parameters.
+    pub fn with_derivative(mut self, derivative: fn(f64, [f64; N]) -> f64) -> Self {
+        self.derivative = Some(derivative);
+        self
+    }
+
+    /// Fits new data starting from the current parameters instead of `cfg.p0`, e.g. to
+    /// follow parameters that drift slowly between frames of a series. Parameter names and
+    /// the analytic derivative carry over to the new curve; this one stays usable.
+    ///
+    /// For points that arrive one batch at a time, refit all points so far, or a window of
+    /// the latest ones, with the curve of the previous batch: it is usually close to the new
+    /// solution, which then takes a few iterations instead of a fit from scratch.
+    pub fn refit(&self, x_data: &[f64], y_data: &[f64], cfg: Config) -> Result<FitResult<N, F>, Error>
+    where
+        F: Clone,
+    {
+        let (x_data, y_data) = check_input::<N>(x_data, y_data, &cfg)?;
+
+        let solution = solve(&self.func, &x_data, &y_data, self.params, cfg.scaling(), &cfg);
+        let mut fit = FitResult::converged(self.func.clone(), solution, &y_data)?;
+        fit.curve.parameter_names = self.parameter_names;
+        fit.curve.derivative = self.derivative;
+        Ok(fit)
+    }
+
+    /// Percentile bootstrap interval `(lower, upper)` of each parameter at the `confidence`
+    /// level, e.g. `0.95`, without assuming Gaussian errors.
+    ///
+    /// Resamples the residuals of this curve on `x_data, y_data` with replacement and refits
+    /// each of the `n_resamples` synthetic data sets from the current parameters using `cfg`;
+    /// set `cfg.seed` for reproducible intervals. Refits that do not converge are left out.
+    pub fn bootstrap_ci(
+        &self,
+        x_data: &[f64],
+        y_data: &[f64],
+        n_resamples: usize,
+        confidence: f64,
+        cfg: Config,
+    ) -> Result<[(f64, f64); N], Error> {
+        let (x_data, y_data) = check_input::<N>(x_data, y_data, &cfg)?;
+        bootstrap::check_confidence(confidence)?;
+
+        let samples = bootstrap::resample_residuals(self, &x_data, &y_data, n_resamples, &cfg);
+        let result = bootstrap::summarize(&samples, n_resamples, confidence, false)?;
+        Ok(result.intervals)
+    }
+}
+
+/// Formats as `a = 2.5, b = 1.3`, falling back to `params[i]` for unnamed parameters.
+/// A precision such as `{:.2}` applies to every value.
+impl<const N: usize, F: Fn(f64, [f64; N]) -> f64> std::fmt::Display for Curve<N, F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, value) in self.params.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            match self.parameter_names {
+                Some(names) => write!(f, "{} = ", names[i])?,
+                None => write!(f, "params[{i}] = ")?,
             }
+            match f.precision() {
+                Some(precision) => write!(f, "{value:.precision$}")?,
+                None => write!(f, "{value}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `curve[i]` is parameter `i`, panicking for `i >= N`.
+impl<const N: usize, F: Fn(f64, [f64; N]) -> f64> std::ops::Index<usize> for Curve<N, F> {
+    type Output = f64;
+
+    fn index(&self, i: usize) -> &f64 {
+        &self.params[i]
+    }
+}
+
+/// Iterates over the parameter values in order.
+impl<const N: usize, F: Fn(f64, [f64; N]) -> f64> IntoIterator for &Curve<N, F> {
+    type Item = f64;
+    type IntoIter = std::array::IntoIter<f64, N>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.params.into_iter()
+    }
+}
+
+/// A fitted curve together with the statistics of the fit.
+#[derive(Clone)]
+pub struct FitResult<const N: usize, F: Fn(f64, [f64; N]) -> f64> {
+    pub curve: Curve<N, F>,
+    /// Sum of squared residuals at the fitted parameters.
+    pub ssr: f64,
+    /// Estimated covariance of the parameters, `ssr / (m - N) * (J^T J)^-1`, zero for the
+    /// parameters held fixed by [`CurveFit::fit_partial`], which also leaves them out of `N`;
+    /// `ssr / (m - N) * (J^T J + lambda I)^-1` with [`Config::regularization`]. Infinite when
+    /// there are no more data points than parameters.
+    pub covariance: [[f64; N]; N],
+    /// Number of data points `m` the curve was fitted to.
+    pub n_data: usize,
+    pub report: FitReport,
+    /// Sum of squared residuals at the initial guess and after each solver iteration, ending
+    /// at `ssr`; with [`Config::regularization`] it includes the penalty. Only recorded when
+    /// [`Config::record_history`] is set.
+    pub history: Option<Vec<f64>>,
+    /// The solver iterations, and with [`Config::trace_rejected`] the rejected steps, in the
+    /// order they happened. Only recorded when [`Config::record_trace`] is set, and not by
+    /// [`CurveFit::fit_odr`].
+    pub trace: Option<Vec<TraceEntry<N>>>,
+    /// The `x` of each data point moved onto the curve by [`CurveFit::fit_odr`],
+    /// `x_i + delta_i`, for the points that were fitted; `None` for the other fits.
+    pub x_adjusted: Option<Vec<f64>>,
+    /// Number of fitted parameters, `N` less any held fixed.
+    n_free: usize,
+    /// Total sum of squares `sum((y_i - mean(y))^2)` of the points fitted.
+    tss: f64,
+}
+
+/// Diagnostics of the solver run behind a fit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FitReport {
+    /// Number of solver iterations, each ending with an accepted step or a termination.
+    pub iterations: usize,
+    /// Number of times the model was evaluated over the data set, counting the `N` extra
+    /// evaluations of every finite-difference Jacobian, `2N` for `JacMethod::Central`; the
+    /// model function itself was called `n_fev * n_data` times.
+    pub n_fev: usize,
+    /// The method that ran, which [`Method::Auto`] resolves to one of the others.
+    pub method: Method,
+    /// Trust radius at the end of a TRF or DogBox run, in the scaled parameters, for tuning
+    /// [`Config::tr`]; `None` for the other methods.
+    pub trust_radius: Option<f64>,
+    /// Why the solver stopped, one of the convergence criteria for a fit that succeeded.
+    pub termination: TerminationReason,
+}
+
+impl FitReport {
+    /// The report of the solver run that found `solution`.
+    fn of<const N: usize>(solution: &solver::Solution<N>) -> Self {
+        Self {
+            iterations: solution.iterations,
+            n_fev: solution.n_fev,
+            method: solution.method,
+            trust_radius: solution.trust_radius,
+            termination: solution.termination,
+        }
+    }
+}
+
+/// The error for a `solution` that is not a fit: one that did not converge, ran out of time,
+/// or started where the Jacobian is zero.
+fn check_converged<const N: usize>(solution: &solver::Solution<N>) -> Result<(), Error> {
+    // the solvers would report the start as converged, with a zero covariance
+    if solution.n_free > 0 && solution.jacobian.iter().all(|&j| j == 0.0) {
+        return Err(Error::ZeroJacobian {
+            params: solution.params.to_vec(),
+        });
+    }
+    if let TerminationReason::Timeout { elapsed } = solution.termination {
+        return Err(Error::Timeout {
+            elapsed,
+            iterations: solution.iterations,
+            last_params: solution.params.to_vec(),
+            last_cost: 2.0 * solution.cost,
+        });
+    }
+    if !solution.converged() {
+        return Err(Error::DidNotConverge {
+            reason: solution.termination,
+            iterations: solution.iterations,
+            last_params: solution.params.to_vec(),
+            last_cost: 2.0 * solution.cost,
+        });
+    }
+    Ok(())
+}
+
+impl<const N: usize, F: Fn(f64, [f64; N]) -> f64> FitResult<N, F> {
+    /// Degrees of freedom `m - N` of the residuals, not counting fixed parameters in `N`.
+    pub fn dof(&self) -> usize {
+        self.n_data.saturating_sub(self.n_free)
+    }
+
+    /// Standard error of each parameter, the square root of the covariance diagonal.
+    pub fn std_errors(&self) -> [f64; N] {
+        std::array::from_fn(|i| self.covariance[i][i].sqrt())
+    }
+
+    /// Correlation of each pair of parameters, `covariance[i][j] / sqrt(covariance[i][i] *
+    /// covariance[j][j])`, between -1 and 1 with ones on the diagonal. The row and column of a
+    /// parameter without a positive finite variance, such as one held fixed, are NaN.
+    pub fn parameter_correlation_matrix(&self) -> [[f64; N]; N] {
+        let c = &self.covariance;
+        let defined = |i: usize| c[i][i] > 0.0 && c[i][i].is_finite();
+        std::array::from_fn(|i| {
+            std::array::from_fn(|j| {
+                if defined(i) && defined(j) {
+                    c[i][j] / (c[i][i] * c[j][j]).sqrt()
+                } else {
+                    f64::NAN
+                }
+            })
+        })
+    }
+
+    /// Coefficient of determination `1 - ssr / sum((y_i - mean(y))^2)` over the points fitted,
+    /// the fraction of the variance of `y` that the curve explains: 1 for a curve through every
+    /// point, and below 0 for one worse than the mean. Both sums are those of the fit, of
+    /// `ln(y)` for [`CurveFit::fit_log_space`] and weighted for [`CurveFit::fit_weight_fn`].
+    /// NaN when all `y` are equal.
+    pub fn r_squared(&self) -> f64 {
+        if self.tss > 0.0 {
+            1.0 - self.ssr / self.tss
+        } else {
+            f64::NAN
         }
+    }
+
+    /// Akaike information criterion `n ln(ssr / n) + 2k` under Gaussian errors, where `k`
+    /// counts the fitted parameters, `N` less any fixed ones, but not the noise variance. Only
+    /// differences between fits to the same data are meaningful; the lower value is preferred.
+    pub fn aic(&self) -> f64 {
+        self.gaussian_log_term() + 2.0 * self.n_free as f64
+    }
+
+    /// Bayesian information criterion `n ln(ssr / n) + k ln(n)`, with `k` as in
+    /// [`FitResult::aic`]. It penalizes extra parameters more strongly than the AIC once
+    /// `n > 7`.
+    pub fn bic(&self) -> f64 {
+        let n = self.n_data as f64;
+        self.gaussian_log_term() + self.n_free as f64 * n.ln()
+    }
 
-        let p_bar = [0.0; N];
+    fn gaussian_log_term(&self) -> f64 {
+        let n = self.n_data as f64;
+        n * (self.ssr / n).ln()
+    }
 
-        Ok(Curve {
-            func: *self,
-            params: p_bar,
+    /// Two-sided `1 - alpha` confidence interval `[lower, upper]` of each parameter,
+    /// `param ± t(1 - alpha / 2, m - N) * std_err` with `t` the Student-t quantile.
+    /// `alpha = 0.05` gives 95% intervals; an `alpha` outside `(0, 1)` gives NaN bounds.
+    pub fn confidence_intervals(&self, alpha: f64) -> [[f64; 2]; N] {
+        let t = stats::student_t_quantile(1.0 - alpha / 2.0, self.dof() as f64);
+        let std_errors = self.std_errors();
+        std::array::from_fn(|i| {
+            let param = self.curve.params[i];
+            [param - t * std_errors[i], param + t * std_errors[i]]
         })
     }
+
+    /// Two-sided `1 - alpha` prediction interval `(lower, upper)` for a new observation at `x`,
+    /// `f(x) ± t(1 - alpha / 2, m - N) * sqrt(g C g^T + sigma_residual^2)`, where `g` is the
+    /// gradient of the model with respect to the parameters and `C` the covariance.
+    ///
+    /// Unlike an interval of the mean response, it also covers the scatter `sigma_residual` of
+    /// a single observation about the curve, typically `sqrt(ssr / dof())`. An `alpha` outside
+    /// `(0, 1)` gives NaN bounds.
+    pub fn predict_interval(&self, x: f64, sigma_residual: f64, alpha: f64) -> (f64, f64) {
+        let t = stats::student_t_quantile(1.0 - alpha / 2.0, self.dof() as f64);
+        let variance = self.curve.mean_variance(x) + sigma_residual * sigma_residual;
+        let y = self.curve.eval(x);
+        let half_width = t * variance.sqrt();
+        (y - half_width, y + half_width)
+    }
+
+    /// Prediction interval for a new observation at each of `xs` at the `confidence` level,
+    /// e.g. 0.95, by [`FitResult::predict_interval`] with the scatter `sqrt(ssr / dof())`
+    /// estimated from the fit. A `confidence` outside `(0, 1)` gives
+    /// `Error::InvalidConfidence`; without degrees of freedom the bounds are NaN.
+    pub fn prediction_intervals(
+        &self,
+        xs: &[f64],
+        confidence: f64,
+    ) -> Result<Vec<(f64, f64)>, Error> {
+        bootstrap::check_confidence(confidence)?;
+        let sigma_residual = (self.ssr / self.dof() as f64).sqrt();
+        let alpha = 1.0 - confidence;
+        Ok(xs.iter().map(|&x| self.predict_interval(x, sigma_residual, alpha)).collect())
+    }
+
+    /// A plain-text table of the fit for terminals of 80 columns: each parameter, by name if
+    /// the curve has them, with its value, standard error and 95% confidence interval, followed
+    /// by the sum of squares, the coefficient of determination, the information criteria and
+    /// the solver's effort. The values have 6 digits after the point; `format!("{fit:.3}")`
+    /// gives the same table with 3.
+    ///
+    /// ```text
+    /// parameter                 value      std error      95% lower      95% upper
+    /// ----------------------------------------------------------------------------
+    /// slope                1.497031e0    8.204957e-3     1.478110e0     1.515952e0
+    /// intercept          -4.863836e-1    4.380249e-2   -5.873924e-1   -3.853749e-1
+    /// ----------------------------------------------------------------------------
+    /// ssr = 4.443207e-2, r_squared = 0.999760, n_data = 10, dof = 8
+    /// aic = -50.1638, bic = -49.5586
+    /// iterations = 4, n_fev = 15, method = LM, termination = Ftol
+    /// ```
+    pub fn summary(&self) -> String {
+        self.to_string()
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Formats as [`FitResult::summary`], a precision such as `{:.3}` setting the digits after the
+/// point of the values in the table.
+impl<const N: usize, F: Fn(f64, [f64; N]) -> f64> std::fmt::Display for FitResult<N, F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let precision = f.precision().unwrap_or(6);
+        let row = |f: &mut std::fmt::Formatter<'_>, name: &str, columns: [String; 4]| {
+            let [value, error, lower, upper] = columns;
+            writeln!(f, "{name:<16}{value:>15}{error:>15}{lower:>15}{upper:>15}")
+        };
+        let headers = ["value", "std error", "95% lower", "95% upper"].map(String::from);
+        row(f, "parameter", headers)?;
+        writeln!(f, "{}", "-".repeat(76))?;
+        let std_errors = self.std_errors();
+        let intervals = self.confidence_intervals(0.05);
+        for (i, value) in self.curve.params.iter().enumerate() {
+            let name = match self.curve.parameter_names {
+                Some(names) => names[i].to_string(),
+                None => format!("params[{i}]"),
+            };
+            let [lower, upper] = intervals[i];
+            let columns = [*value, std_errors[i], lower, upper];
+            let columns = columns.map(|v| format!("{v:.precision$e}"));
+            row(f, &name, columns)?;
+        }
+        writeln!(f, "{}", "-".repeat(76))?;
+        let (ssr, r_squared, n_data, dof) = (self.ssr, self.r_squared(), self.n_data, self.dof());
+        writeln!(f, "ssr = {ssr:.6e}, r_squared = {r_squared:.6}, n_data = {n_data}, dof = {dof}")?;
+        writeln!(f, "aic = {:.4}, bic = {:.4}", self.aic(), self.bic())?;
+        writeln!(
+            f,
+            "iterations = {}, n_fev = {}, method = {:?}, termination = {:?}",
+            self.report.iterations, self.report.n_fev, self.report.method, self.report.termination
+        )
+    }
+}
 
-    fn target_func(x: f64, p: [f64; 2]) -> f64 {
-        p[0] * x + p[1]
+/// Fits compare by `ssr` alone, so the best of several fits to the same data, e.g. of
+/// competing models, is their minimum. A NaN `ssr` orders after every other value.
+impl<const N: usize, F: Fn(f64, [f64; N]) -> f64> Ord for FitResult<N, F> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.ssr
+            .is_nan()
+            .cmp(&other.ssr.is_nan())
+            .then(self.ssr.total_cmp(&other.ssr))
     }
+}
 
-    #[test]
-    fn it_works() {
-        let lamda_func = |x: f64, p: [f64; 2]| p[0] * x + p[1];
-        lamda_func
-            .fit(
-                &[1.0, 2.0, 3.0],
-                &[1.0, 2.0, 3.0],
-                Config {
-                    check_finite: false,
-                    ..Default::default()
-                },
-            )
-            .unwrap();
+impl<const N: usize, F: Fn(f64, [f64; N]) -> f64> PartialOrd for FitResult<N, F> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
 
-        let f = target_func
-            .fit(
-                &[1.0, 2.0, 3.0],
-                &[1.0, 2.0, 3.0],
-                Config {
-                    check_finite: false,
-                    ..Default::default()
-                },
-            )
-            .unwrap();
+impl<const N: usize, F: Fn(f64, [f64; N]) -> f64> PartialEq for FitResult<N, F> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl<const N: usize, F: Fn(f64, [f64; N]) -> f64> Eq for FitResult<N, F> {}
+
+/// `result[i]` is parameter `i` of the fitted curve, panicking for `i >= N`.
+impl<const N: usize, F: Fn(f64, [f64; N]) -> f64> std::ops::Index<usize> for FitResult<N, F> {
+    type Output = f64;
 
-        f.eval(1.0);
+    fn index(&self, i: usize) -> &f64 {
+        &self.curve[i]
     }
+}
 
-    #[test]
-    fn simple_test() {
-        let xdata = Array::linspace(0., 4., 50);
-        let y = xdata.map(|&x| target_func(x, [2.5, 1.3]));
-        let normal = Normal::new(0.0, 1.0).unwrap();
-        let y_noise = Array::random_using(xdata.shape(), normal, &mut rand::thread_rng());
-        let y_data = y + y_noise; 
-        println!("y data:\n{:?}", y_data);
-    
+/// Iterates over the parameter values of the fitted curve in order.
+impl<const N: usize, F: Fn(f64, [f64; N]) -> f64> IntoIterator for &FitResult<N, F> {
+    type Item = f64;
+    type IntoIter = std::array::IntoIter<f64, N>;
 
-        // dbg!(xdata, ydata);
+    fn into_iter(self) -> Self::IntoIter {
+        self.curve.into_iter()
+    }
+}
+
+/// Akaike weights of competing models fitted to the same data, from their [`FitResult::aic`]:
+/// `exp(-0.5 * d_i) / sum(exp(-0.5 * d_j))` with `d_i = aic_i - min(aic)`, the probability of
+/// each model being the best of them in the sense of the AIC. The weights sum to 1; a NaN AIC,
+/// e.g. standing in for a failed fit, gets weight 0. BIC values give the analogous weights.
+///
+/// An AIC of `-inf`, from a fit that goes through every point, beats any finite one: the
+/// models with it share the whole weight equally. Without such a model and without any finite
+/// AIC there is nothing to weigh, and every weight is NaN.
+pub fn model_weights(aics: &[f64]) -> Vec<f64> {
+    let n_exact = aics.iter().filter(|&&aic| aic == f64::NEG_INFINITY).count();
+    if n_exact > 0 {
+        let weight = |aic: &f64| if *aic == f64::NEG_INFINITY { 1.0 / n_exact as f64 } else { 0.0 };
+        return aics.iter().map(weight).collect();
+    }
+    if !aics.iter().any(|aic| aic.is_finite()) {
+        return vec![f64::NAN; aics.len()];
+    }
+    let min = aics.iter().copied().fold(f64::INFINITY, f64::min);
+    let likelihoods: Vec<f64> = aics
+        .iter()
+        .map(|aic| match (-0.5 * (aic - min)).exp() {
+            likelihood if likelihood.is_nan() => 0.0,
+            likelihood => likelihood,
+        })
+        .collect();
+    let total: f64 = likelihoods.iter().sum();
+    likelihoods.iter().map(|likelihood| likelihood / total).collect()
+}
+
+impl<const N: usize, F: Fn(f64, [f64; N]) -> f64> FitResult<N, F> {
+    /// The fit found by `solution`, or `Error::DidNotConverge` if it stopped early or on
+    /// non-finite values, `Error::Timeout` if it ran out of time and `Error::ZeroJacobian` if
+    /// the model did not depend on the parameters.
+    fn converged(func: F, solution: solver::Solution<N>, y_data: &[f64]) -> Result<Self, Error> {
+        check_converged(&solution)?;
+        Ok(Self::new(func, solution, y_data))
+    }
+
+    /// The fit found by `solution` to the points with values `y_data`.
+    fn new(func: F, solution: solver::Solution<N>, y_data: &[f64]) -> Self {
+        let ssr = 2.0 * solution.cost;
+        let mean = y_data.iter().sum::<f64>() / y_data.len() as f64;
+        let covariance =
+            solver::covariance(&solution.jacobian, ssr, solution.n_free, solution.regularization);
+        Self {
+            curve: Curve {
+                func,
+                params: solution.params,
+                parameter_names: None,
+                derivative: None,
+                at_bounds: solution.at_bounds,
+                covariance,
+            },
+            ssr,
+            covariance,
+            n_data: y_data.len(),
+            report: FitReport::of(&solution),
+            history: solution.history,
+            trace: solution.trace,
+            x_adjusted: None,
+            n_free: solution.n_free,
+            tss: y_data.iter().map(|y| (y - mean).powi(2)).sum(),
+        }
+    }
+}
+
+pub trait CurveFit<const N: usize>
+where
+    Self: std::marker::Sized + Fn(f64, [f64; N]) -> f64,
+{
+    /// Fits the model to the data starting from `cfg.p0` for every parameter, or from the data
+    /// scales for `P0Strategy::DataScaled`. A solver that stops without converging gives
+    /// `Error::DidNotConverge`, as do the other fitting methods.
+    ///
+    /// The points may come in any order, and repeated `x` values are replicate measurements.
+    /// Fewer points than parameters, or none at all, give `Error::InsufficientData`, after
+    /// dropping non-finite ones. Only a single distinct `x` for more than one parameter is
+    /// rejected, as
+    /// `Error::DegenerateData`, and, with `cfg.check_finite`, a single distinct `y`, as
+    /// `Error::ZeroVarianceData`.
+    ///
+    /// For data in ndarray arrays or views, e.g. from `Array1::linspace`, use
+    /// [`CurveFit::fit_array`].
+    fn fit(&self, x_data: &[f64], y_data: &[f64], cfg: Config) -> Result<FitResult<N, Self>, Error>;
+
+    /// Estimates the parameter uncertainty by a pairs bootstrap: refits `n_resamples` copies of
+    /// the data with `(x, y)` pairs drawn with replacement, each starting from the fit to the
+    /// full data, and summarizes the parameters over the refits with percentile intervals at
+    /// the `confidence` level. Set `cfg.seed` for reproducible results.
+    ///
+    /// Resamples whose refit does not converge are dropped and counted in
+    /// [`BootstrapResult::n_failed`]. With `keep_samples` the parameters of every converged
+    /// refit are returned as well.
+    fn bootstrap_fit(
+        &self,
+        x_data: &[f64],
+        y_data: &[f64],
+        cfg: Config,
+        n_resamples: usize,
+        confidence: f64,
+        keep_samples: bool,
+    ) -> Result<BootstrapResult<N>, Error> {
+        let (x_data, y_data) = check_input::<N>(x_data, y_data, &cfg)?;
+        bootstrap::check_confidence(confidence)?;
+        let fit = self.fit(&x_data, &y_data, cfg)?;
+        let samples = bootstrap::resample_pairs(&fit.curve, &x_data, &y_data, n_resamples, &cfg);
+        bootstrap::summarize(&samples, n_resamples, confidence, keep_samples)
+    }
+
+    /// Estimates the parameter uncertainty by the jackknife: refits the data once without each
+    /// of its `m` points, each starting from the fit to the full data, and takes the spread of
+    /// the `m` leave-one-out parameters. Unlike [`CurveFit::bootstrap_fit`] it involves no
+    /// randomness, and it makes no assumption of Gaussian errors like the covariance does.
+    ///
+    /// It costs `m` fits on top of the one to all points, so O(m) times a single fit, which
+    /// adds up for large data sets. Refits that do not converge are dropped and counted in
+    /// [`JackknifeResult::n_failed`]; only when all of them fail is
+    /// `Error::NoConvergedResample` returned. There must be more points than parameters.
+    fn resample_jackknife(
+        &self,
+        x_data: &[f64],
+        y_data: &[f64],
+        cfg: Config,
+    ) -> Result<JackknifeResult<N>, Error> {
+        let (x_data, y_data) = check_input::<N>(x_data, y_data, &cfg)?;
+        if x_data.len() <= N {
+            return Err(Error::InsufficientData {
+                n_data: x_data.len().saturating_sub(1),
+                n_params: N,
+            });
+        }
+        let fit = self.fit(&x_data, &y_data, cfg)?;
+        bootstrap::leave_one_out(&fit.curve, &x_data, &y_data, &cfg)
+    }
+
+    /// Fits the model to each `(x_data, y_data)` of `datasets` with [`CurveFit::fit`],
+    /// returning one result per data set in their order. A data set that fails, e.g. by
+    /// unmatched lengths or a fit that does not converge, gives its error without affecting
+    /// the others.
+    fn fit_batch(
+        &self,
+        datasets: &[(&[f64], &[f64])],
+        cfg: Config,
+    ) -> Vec<Result<FitResult<N, Self>, Error>> {
+        datasets
+            .iter()
+            .map(|&(x_data, y_data)| self.fit(x_data, y_data, cfg))
+            .collect()
+    }
+
+    /// [`CurveFit::fit_batch`] with the data sets fitted in parallel on the `rayon` thread
+    /// pool, behind the `rayon` feature.
+    #[cfg(feature = "rayon")]
+    fn par_fit_batch(
+        &self,
+        datasets: &[(&[f64], &[f64])],
+        cfg: Config,
+    ) -> Vec<Result<FitResult<N, Self>, Error>>
+    where
+        Self: Sync + Send,
+    {
+        use rayon::prelude::*;
+        datasets
+            .par_iter()
+            .map(|&(x_data, y_data)| self.fit(x_data, y_data, cfg))
+            .collect()
+    }
+
+    /// Like [`CurveFit::fit`], for data held in ndarray arrays or views. Contiguous data is
+    /// fitted in place; strided views are copied first.
+    fn fit_array<S: Data<Elem = f64>, T: Data<Elem = f64>>(
+        &self,
+        x_data: &ArrayBase<S, Ix1>,
+        y_data: &ArrayBase<T, Ix1>,
+        cfg: Config,
+    ) -> Result<FitResult<N, Self>, Error> {
+        self.fit(&contiguous(x_data), &contiguous(y_data), cfg)
+    }
+
+    /// Like [`CurveFit::fit`], for data given as `(x, y)` pairs, e.g. mapped from a slice of
+    /// measurement structs. An empty iterator gives `Error::InsufficientData`.
+    fn fit_pairs<I: IntoIterator<Item = (f64, f64)>>(
+        &self,
+        data: I,
+        cfg: Config,
+    ) -> Result<FitResult<N, Self>, Error> {
+        let (x_data, y_data): (Vec<f64>, Vec<f64>) = data.into_iter().unzip();
+        self.fit(&x_data, &y_data, cfg)
+    }
+
+    /// Like [`CurveFit::fit`], with a fixed characteristic scale for each parameter in place
+    /// of `cfg.x_scale`, typically its expected order of magnitude. Scales must be positive
+    /// and finite.
+    fn fit_scaled(
+        &self,
+        x_data: &[f64],
+        y_data: &[f64],
+        cfg: Config,
+        scale: [f64; N],
+    ) -> Result<FitResult<N, Self>, Error>;
+
+    /// Like [`CurveFit::fit`], keeping each parameter within `lower[i] <= p[i] <= upper[i]`;
+    /// infinite bounds leave a side open. A `cfg.p0` outside the bounds of a parameter is
+    /// clipped into them, or an error, as `cfg.out_of_bounds` says.
+    ///
+    /// Bounds need `Method::TRF` or `Method::DogBox`, or `Method::Auto`, which picks TRF;
+    /// other methods give `Error::BoundsUnsupported`.
+    fn fit_bounded(
+        &self,
+        x_data: &[f64],
+        y_data: &[f64],
+        cfg: Config,
+        lower: [f64; N],
+        upper: [f64; N],
+    ) -> Result<FitResult<N, Self>, Error>;
+
+    /// Like [`CurveFit::fit`], calling `callback(iteration, params, ssr)` at the initial guess,
+    /// as iteration 0, and after every solver iteration, with the parameters reached and the
+    /// sum of squares that [`FitResult::history`] records there. The callback may print, log
+    /// or send to a channel, and collect into a `RefCell`; it sees the iterations even of a
+    /// fit that ends in an error.
+    fn fit_with_callback(
+        &self,
+        x_data: &[f64],
+        y_data: &[f64],
+        cfg: Config,
+        callback: &dyn Fn(usize, [f64; N], f64),
+    ) -> Result<FitResult<N, Self>, Error>;
+
+    /// Like [`CurveFit::fit`] on the points whose `mask` entry is `true`, leaving the others,
+    /// e.g. gated regions of a spectrum, out of the residuals and the Jacobian while the
+    /// arrays stay aligned with other data. Excluded points are not checked, so they may be
+    /// NaN; indices in errors still count all points. A mask excluding too many points gives
+    /// `Error::InsufficientData`.
+    fn fit_masked(
+        &self,
+        x_data: &[f64],
+        y_data: &[f64],
+        cfg: Config,
+        mask: &[bool],
+    ) -> Result<FitResult<N, Self>, Error>;
+
+    /// Fits the points that are not outliers by iterative sigma clipping: a fit to all points,
+    /// then fits to the points whose residual lies within `reject.k` spreads of the center of
+    /// the inlier residuals, until the points rejected are those left out or after
+    /// `reject.max_rounds` fits, for data with gross errors, e.g. cosmic-ray hits, that must be
+    /// excluded rather than down-weighted. A rejected point is measured against every later
+    /// fit, and comes back if it is close to it.
+    ///
+    /// The fits are those of [`CurveFit::fit_masked`], with their errors. When a round would
+    /// leave no more points than parameters, the fit of the round before is returned with
+    /// `RejectStatus::Underdetermined`.
+    fn fit_robust_reject(
+        &self,
+        x_data: &[f64],
+        y_data: &[f64],
+        cfg: Config,
+        reject: RejectOptions,
+    ) -> Result<RejectResult<N, Self>, Error>;
+
+    /// Like [`CurveFit::fit`], minimizing the residuals `ln(f(x_i)) - ln(y_i)` rather than
+    /// `f(x_i) - y_i`, for data spanning decades with errors proportional to `y`, such as
+    /// exponential decays or power laws, where the largest values would otherwise dominate the
+    /// fit. Every `y` must be positive, or `Error::NonPositiveData` is returned; parameters at
+    /// which the model is not positive stop the solver as non-finite residuals would.
+    ///
+    /// The curve evaluates the model itself, while `ssr` and the covariance refer to the
+    /// logarithms.
+    fn fit_log_space(
+        &self,
+        x_data: &[f64],
+        y_data: &[f64],
+        cfg: Config,
+    ) -> Result<FitResult<N, Self>, Error>;
+
+    /// Like [`CurveFit::fit`], minimizing `sum(w(x_i) * (f(x_i) - y_i)^2)` for a weight known
+    /// as a function of `x`, e.g. `w(x) = 1 / x` for counts with Poisson statistics, or
+    /// `1 / sigma(x)^2` for any known spread of the errors. Each residual and row of the
+    /// Jacobian is multiplied by `sqrt(w(x_i))`; a zero weight leaves a point out of the fit
+    /// while still counting it among the data.
+    ///
+    /// With `cfg.check_finite`, a weight that is negative or not finite at a point to fit gives
+    /// `Error::InvalidWeight`, the index counting all points. `ssr` and the covariance refer to
+    /// the weighted residuals.
+    fn fit_weight_fn<W: Fn(f64) -> f64>(
+        &self,
+        x_data: &[f64],
+        y_data: &[f64],
+        cfg: Config,
+        weight: W,
+    ) -> Result<FitResult<N, Self>, Error>;
+
+    /// Like [`CurveFit::fit`], holding each parameter given as `Some(value)` in `fixed` at that
+    /// value and fitting only the others from the initial guess. The model is never
+    /// differentiated with respect to the fixed parameters, which appear at their values among
+    /// the fitted ones with zero variance and do not count towards [`FitResult::dof`].
+    fn fit_partial(
+        &self,
+        x_data: &[f64],
+        y_data: &[f64],
+        cfg: Config,
+        fixed: [Option<f64>; N],
+    ) -> Result<FitResult<N, Self>, Error>;
+
+    /// Like [`CurveFit::fit`], with the `cfg.regularization` penalty `lambda * |p - p_ref|^2`
+    /// pulling the parameters towards `p_ref`, e.g. estimates from earlier data, rather than
+    /// towards zero. Without `cfg.regularization` this is an ordinary fit.
+    fn fit_regularized(
+        &self,
+        x_data: &[f64],
+        y_data: &[f64],
+        cfg: Config,
+        p_ref: [f64; N],
+    ) -> Result<FitResult<N, Self>, Error>;
+
+    /// Orthogonal distance regression: like [`CurveFit::fit`], for data whose `x` is measured
+    /// with errors as well. Minimizes the weighted distances of the points to the curve,
+    /// `sum(((f(x_i + delta_i) - y_i) / y_sigma_i)^2 + (delta_i / x_sigma_i)^2)`, over the
+    /// parameters and a correction `delta_i` of every `x_i`, where ordinary least squares
+    /// would bias the parameters, e.g. a slope towards zero.
+    ///
+    /// `x_sigma` and `y_sigma` are the standard deviations of the points, positive and of the
+    /// data's length; `None` weighs every point by 1. Only their ratio matters to the fit.
+    /// The reported `ssr` is the minimized weighted sum, the orthogonal cost, and the covariance
+    /// is that of ODRPACK (scipy's `odr`), scaled by `ssr / (m - N)` like the other fits. The
+    /// corrected `x` are in [`FitResult::x_adjusted`].
+    ///
+    /// The corrections are eliminated inside a Levenberg-Marquardt iteration, which ignores
+    /// `cfg.method` and reports `Method::LM`.
+    fn fit_odr(
+        &self,
+        x_data: &[f64],
+        y_data: &[f64],
+        cfg: Config,
+        x_sigma: Option<&[f64]>,
+        y_sigma: Option<&[f64]>,
+    ) -> Result<FitResult<N, Self>, Error>;
+
+    /// Runs the configured solver from `n_starts` initial guesses drawn uniformly from
+    /// `p0_ranges` (one `(lower, upper)` range per parameter, seeded by `cfg.seed`) and returns
+    /// the fit with the lowest `ssr` together with the outcome of every start.
+    ///
+    /// With `P0Strategy::DataScaled` the guess from the data is the first start, ahead of the
+    /// `n_starts` random ones; `cfg.p0` is not used otherwise.
+    ///
+    /// Starts that do not converge or end on non-finite values are recorded and skipped; only
+    /// when all of them fail is `Error::NoConvergedStart` returned.
+    fn fit_multistart(
+        &self,
+        x_data: &[f64],
+        y_data: &[f64],
+        cfg: Config,
+        n_starts: usize,
+        p0_ranges: [(f64, f64); N],
+    ) -> Result<MultiStartResult<N, Self>, Error>;
+
+    /// [`CurveFit::fit`] on the values of two `polars` series, behind the `polars` feature.
+    /// Integer series are converted to `f64` and nulls read as NaN, handled by
+    /// `cfg.non_finite`; series of other types give `Error::PolarsError`.
+    #[cfg(feature = "polars")]
+    fn fit_series(
+        &self,
+        x_data: &polars::prelude::Series,
+        y_data: &polars::prelude::Series,
+        cfg: Config,
+    ) -> Result<FitResult<N, Self>, Error>;
+}
+
+impl<T, const N: usize> CurveFit<N> for T
+where
+    T: Fn(f64, [f64; N]) -> f64 + Clone,
+{
+    fn fit(&self, x_data: &[f64], y_data: &[f64], cfg: Config) -> Result<FitResult<N, Self>, Error> {
+        let (x_data, y_data) = check_input::<N>(x_data, y_data, &cfg)?;
+
+        let p0 = cfg.initial_guess(&x_data, &y_data);
+        let solution = solve(self, &x_data, &y_data, p0, cfg.scaling(), &cfg);
+        FitResult::converged(self.clone(), solution, &y_data)
+    }
+
+    fn fit_scaled(
+        &self,
+        x_data: &[f64],
+        y_data: &[f64],
+        cfg: Config,
+        scale: [f64; N],
+    ) -> Result<FitResult<N, Self>, Error> {
+        let (x_data, y_data) = check_input::<N>(x_data, y_data, &cfg)?;
+        for (index, &value) in scale.iter().enumerate() {
+            if !(value.is_finite() && value > 0.0) {
+                return Err(Error::InvalidScale { index, value });
+            }
+        }
+
+        let scaling = solver::Scaling::Fixed(scale);
+        let p0 = cfg.initial_guess(&x_data, &y_data);
+        let solution = solve(self, &x_data, &y_data, p0, scaling, &cfg);
+        FitResult::converged(self.clone(), solution, &y_data)
+    }
+
+    fn fit_bounded(
+        &self,
+        x_data: &[f64],
+        y_data: &[f64],
+        cfg: Config,
+        lower: [f64; N],
+        upper: [f64; N],
+    ) -> Result<FitResult<N, Self>, Error> {
+        let (x_data, y_data) = check_input::<N>(x_data, y_data, &cfg)?;
+        for index in 0..N {
+            if lower[index].is_nan() || upper[index].is_nan() || lower[index] >= upper[index] {
+                return Err(Error::InvalidBounds {
+                    index,
+                    lower: lower[index],
+                    upper: upper[index],
+                });
+            }
+        }
+        if matches!(
+            cfg.method,
+            Method::LM
+                | Method::NelderMead
+                | Method::LBFGS
+                | Method::LinearLSQ
+                | Method::SGD { .. }
+        ) {
+            return Err(Error::BoundsUnsupported { method: cfg.method });
+        }
+
+        let bounds = solver::Bounds { lower, upper };
+        let p0: [f64; N] = cfg.initial_guess(&x_data, &y_data);
+        for index in 0..N {
+            if lower[index] <= p0[index] && p0[index] <= upper[index] {
+                continue;
+            }
+            match cfg.out_of_bounds {
+                OutOfBoundsPolicy::Clamp => {
+                    trace::clamped_guess(index, p0[index], lower[index], upper[index])
+                }
+                OutOfBoundsPolicy::Error => {
+                    return Err(Error::GuessOutOfBounds {
+                        index,
+                        p0: p0[index],
+                        lower: lower[index],
+                        upper: upper[index],
+                    })
+                }
+            }
+        }
+        let p0 = std::array::from_fn(|i| p0[i].clamp(lower[i], upper[i]));
+        let scaling = cfg.scaling();
+        let solution = solve_within(self, &x_data, &y_data, p0, scaling, bounds, [0.0; N], &cfg);
+        FitResult::converged(self.clone(), solution, &y_data)
+    }
+
+    fn fit_with_callback(
+        &self,
+        x_data: &[f64],
+        y_data: &[f64],
+        cfg: Config,
+        callback: &dyn Fn(usize, [f64; N], f64),
+    ) -> Result<FitResult<N, Self>, Error> {
+        let (x_data, y_data) = check_input::<N>(x_data, y_data, &cfg)?;
+
+        let p0 = cfg.initial_guess(&x_data, &y_data);
+        let bounds = solver::Bounds::UNBOUNDED;
+        let problem = (self, &x_data[..], &y_data[..]);
+        let setup = solver::Setup::default();
+        let solution =
+            run_solver(&problem, p0, cfg.scaling(), bounds, &cfg, setup, Some(callback));
+        FitResult::converged(self.clone(), solution, &y_data)
+    }
+
+    fn fit_masked(
+        &self,
+        x_data: &[f64],
+        y_data: &[f64],
+        cfg: Config,
+        mask: &[bool],
+    ) -> Result<FitResult<N, Self>, Error> {
+        if x_data.len() != y_data.len() {
+            return Err(Error::UnmatchedLength {
+                x_data_len: x_data.len(),
+                y_data_len: y_data.len(),
+            });
+        }
+        if mask.len() != x_data.len() {
+            return Err(Error::UnmatchedMaskLength {
+                data_len: x_data.len(),
+                mask_len: mask.len(),
+            });
+        }
+
+        let kept: Vec<usize> = (0..mask.len()).filter(|&i| mask[i]).collect();
+        let x_kept: Vec<f64> = kept.iter().map(|&i| x_data[i]).collect();
+        let y_kept: Vec<f64> = kept.iter().map(|&i| y_data[i]).collect();
+        self.fit(&x_kept, &y_kept, cfg).map_err(|error| match error {
+            Error::NonFiniteData { index } => Error::NonFiniteData { index: kept[index] },
+            Error::NonPositiveData { index, y } => Error::NonPositiveData { index: kept[index], y },
+            Error::UnsortedInput { index } => Error::UnsortedInput { index: kept[index] },
+            error => error,
+        })
+    }
+
+    fn fit_robust_reject(
+        &self,
+        x_data: &[f64],
+        y_data: &[f64],
+        cfg: Config,
+        reject: RejectOptions,
+    ) -> Result<RejectResult<N, Self>, Error> {
+        reject::fit_rejecting(self, x_data, y_data, cfg, reject)
+    }
+
+    fn fit_log_space(
+        &self,
+        x_data: &[f64],
+        y_data: &[f64],
+        cfg: Config,
+    ) -> Result<FitResult<N, Self>, Error> {
+        let (x_checked, y_checked) = check_input::<N>(x_data, y_data, &cfg)?;
+        // the non-finite points were handled above; the index counts all points
+        if let Some(index) = y_data.iter().position(|&y| y.is_finite() && y <= 0.0) {
+            return Err(Error::NonPositiveData {
+                index,
+                y: y_data[index],
+            });
+        }
+
+        let ln_y: Vec<f64> = y_checked.iter().map(|y| y.ln()).collect();
+        let ln_model = |x: f64, p: [f64; N]| self(x, p).ln();
+        let p0 = cfg.initial_guess(&x_checked, &y_checked);
+        let solution = solve(&ln_model, &x_checked, &ln_y, p0, cfg.scaling(), &cfg);
+        FitResult::converged(self.clone(), solution, &ln_y)
+    }
+
+    fn fit_weight_fn<W: Fn(f64) -> f64>(
+        &self,
+        x_data: &[f64],
+        y_data: &[f64],
+        cfg: Config,
+        weight: W,
+    ) -> Result<FitResult<N, Self>, Error> {
+        let (x_checked, y_checked) = check_input::<N>(x_data, y_data, &cfg)?;
+        if cfg.check_finite {
+            // only the points kept above are weighted; the index counts all points
+            let is_invalid = |i: &usize| {
+                let w = weight(x_data[*i]);
+                x_data[*i].is_finite() && y_data[*i].is_finite() && !(w >= 0.0 && w.is_finite())
+            };
+            if let Some(index) = (0..x_data.len()).find(is_invalid) {
+                return Err(Error::InvalidWeight {
+                    index,
+                    value: weight(x_data[index]),
+                });
+            }
+        }
+
+        let sqrt_w: Vec<f64> = x_checked.iter().map(|&x| weight(x).sqrt()).collect();
+        let weighted_y: Vec<f64> = y_checked.iter().zip(&sqrt_w).map(|(y, s)| s * y).collect();
+        let weighted_model = |x: f64, p: [f64; N]| weight(x).sqrt() * self(x, p);
+        let p0 = cfg.initial_guess(&x_checked, &y_checked);
+        let solution = solve(&weighted_model, &x_checked, &weighted_y, p0, cfg.scaling(), &cfg);
+        let mut fit = FitResult::converged(self.clone(), solution, &y_checked)?;
+        // the total sum of squares is weighted like the residuals
+        let points = sqrt_w.iter().map(|s| s * s).zip(y_checked.iter());
+        let total_weight: f64 = sqrt_w.iter().map(|s| s * s).sum();
+        let mean = points.clone().map(|(w, y)| w * y).sum::<f64>() / total_weight;
+        fit.tss = points.map(|(w, y)| w * (y - mean).powi(2)).sum();
+        Ok(fit)
+    }
+
+    fn fit_partial(
+        &self,
+        x_data: &[f64],
+        y_data: &[f64],
+        cfg: Config,
+        fixed: [Option<f64>; N],
+    ) -> Result<FitResult<N, Self>, Error> {
+        let (x_data, y_data) = check_input::<N>(x_data, y_data, &cfg)?;
+
+        let guess: [f64; N] = cfg.initial_guess(&x_data, &y_data);
+        let p0 = std::array::from_fn(|i| fixed[i].unwrap_or(guess[i]));
+        let bounds = solver::Bounds::UNBOUNDED;
+        let problem = (self, &x_data[..], &y_data[..]);
+        let setup = solver::Setup { fixed, ..Default::default() };
+        let solution = run_solver(&problem, p0, cfg.scaling(), bounds, &cfg, setup, None);
+        FitResult::converged(self.clone(), solution, &y_data)
+    }
+
+    fn fit_regularized(
+        &self,
+        x_data: &[f64],
+        y_data: &[f64],
+        cfg: Config,
+        p_ref: [f64; N],
+    ) -> Result<FitResult<N, Self>, Error> {
+        let (x_data, y_data) = check_input::<N>(x_data, y_data, &cfg)?;
+
+        let bounds = solver::Bounds::UNBOUNDED;
+        let (p0, scaling) = (cfg.initial_guess(&x_data, &y_data), cfg.scaling());
+        let solution = solve_within(self, &x_data, &y_data, p0, scaling, bounds, p_ref, &cfg);
+        FitResult::converged(self.clone(), solution, &y_data)
+    }
+
+    fn fit_odr(
+        &self,
+        x_data: &[f64],
+        y_data: &[f64],
+        cfg: Config,
+        x_sigma: Option<&[f64]>,
+        y_sigma: Option<&[f64]>,
+    ) -> Result<FitResult<N, Self>, Error> {
+        let lengths = [Some(y_data), x_sigma, y_sigma].map(|data| data.map(<[f64]>::len));
+        if let Some(order) = sorting_order(x_data, &cfg)
+            .filter(|_| lengths.iter().flatten().all(|&len| len == x_data.len()))
+        {
+            // the standard deviations are sorted along with their points
+            check_order(x_data, &cfg)?;
+            let sorted = |data: &[f64]| -> Vec<f64> { order.iter().map(|&i| data[i]).collect() };
+            let (x_sigma, y_sigma) = (x_sigma.map(sorted), y_sigma.map(sorted));
+            let fit = self.fit_odr(
+                &sorted(x_data),
+                &sorted(y_data),
+                cfg,
+                x_sigma.as_deref(),
+                y_sigma.as_deref(),
+            );
+            return fit.map_err(|error| match error {
+                Error::NonFiniteData { index } => Error::NonFiniteData { index: order[index] },
+                Error::InvalidSigma { index, value } => Error::InvalidSigma {
+                    index: order[index],
+                    value,
+                },
+                error => error,
+            });
+        }
+        let (x_checked, y_checked) = check_input::<N>(x_data, y_data, &cfg)?;
+        let n_kept = x_checked.len();
+        let x_sigma = solver::odr::checked_sigma(x_sigma, x_data, y_data, n_kept)?;
+        let y_sigma = solver::odr::checked_sigma(y_sigma, x_data, y_data, n_kept)?;
+
+        let problem = solver::odr::OdrProblem::new(
+            self,
+            (&x_checked, &y_checked),
+            (&x_sigma, &y_sigma),
+            cfg.regularization,
+        )
+        .with_diff_step(cfg.diff_step);
+        let (solution, x_adjusted) = solver::odr::orthogonal_distance_regression(
+            &problem,
+            cfg.initial_guess(&x_checked, &y_checked),
+            &cfg.scaling(),
+            cfg.record_history,
+            &cfg.lm,
+            solver::Deadline::new(cfg.timeout),
+        );
+        let mut result = FitResult::converged(self.clone(), solution, &y_checked)?;
+        result.x_adjusted = Some(x_adjusted);
+        Ok(result)
+    }
+
+    fn fit_multistart(
+        &self,
+        x_data: &[f64],
+        y_data: &[f64],
+        cfg: Config,
+        n_starts: usize,
+        p0_ranges: [(f64, f64); N],
+    ) -> Result<MultiStartResult<N, Self>, Error> {
+        let (x_data, y_data) = check_input::<N>(x_data, y_data, &cfg)?;
+
+        let mut starts = multistart::sample_starts(&p0_ranges, n_starts, &mut cfg.rng())?;
+        if cfg.p0_strategy == P0Strategy::DataScaled {
+            starts.insert(0, cfg.initial_guess(&x_data, &y_data));
+        }
+        let scaling = cfg.scaling();
+        let solutions = starts
+            .iter()
+            .map(|&p0| solve(self, &x_data, &y_data, p0, scaling, &cfg));
+        multistart::select_best(self.clone(), starts.iter().copied().zip(solutions), &y_data)
+    }
+
+    #[cfg(feature = "polars")]
+    fn fit_series(
+        &self,
+        x_data: &polars::prelude::Series,
+        y_data: &polars::prelude::Series,
+        cfg: Config,
+    ) -> Result<FitResult<N, Self>, Error> {
+        self.fit(&series::values(x_data)?, &series::values(y_data)?, cfg)
+    }
+}
+
+/// Like [`CurveFit::fit`], for a model with state of its own, e.g. one that drives an
+/// external simulator through a `&mut` handle. The model is moved into the returned curve,
+/// which keeps calling it in [`Curve::eval`].
+///
+/// Every pass of the solver over the data, `report.n_fev` of them, calls the model once per
+/// point in index order, skipping points dropped by `cfg.non_finite`; passes never overlap,
+/// so the model can keep a cache from one call to the next. The curve shares the model
+/// through an `Rc`, so it cannot leave the thread it was fitted on.
+pub fn fit_mut<const N: usize, F: FnMut(f64, [f64; N]) -> f64>(
+    model: F,
+    x_data: &[f64],
+    y_data: &[f64],
+    cfg: Config,
+) -> Result<FitResult<N, impl Fn(f64, [f64; N]) -> f64 + Clone>, Error> {
+    let model = Rc::new(RefCell::new(model));
+    // a model call never re-enters the model, so the borrow cannot fail
+    let shared = move |x: f64, p: [f64; N]| (model.borrow_mut())(x, p);
+    shared.fit(x_data, y_data, cfg)
+}
+
+fn contiguous<S: Data<Elem = f64>>(data: &ArrayBase<S, Ix1>) -> Cow<'_, [f64]> {
+    match data.as_slice() {
+        Some(slice) => Cow::Borrowed(slice),
+        None => Cow::Owned(data.to_vec()),
+    }
+}
+
+/// `x_data, y_data` as they are passed to the solver.
+type CheckedData<'a> = (Cow<'a, [f64]>, Cow<'a, [f64]>);
+
+/// Checks the data and the config before any solver work, returning the data to fit:
+/// the input itself, or a copy without its non-finite points under `NonFinitePolicy::Drop` or
+/// sorted under `Config::sort_input`.
+fn check_input<'a, const N: usize>(
+    x_data: &'a [f64],
+    y_data: &'a [f64],
+    cfg: &Config,
+) -> Result<CheckedData<'a>, Error> {
+    // data length check
+    if x_data.len() != y_data.len() {
+        return Err(Error::UnmatchedLength {
+            x_data_len: x_data.len(),
+            y_data_len: y_data.len(),
+        });
+    }
+
+    check_config(cfg)?;
+    check_order(x_data, cfg)?;
+
+    // non-finite data check
+    let is_finite = |i: &usize| x_data[*i].is_finite() && y_data[*i].is_finite();
+    let (x_data, y_data) = match cfg.non_finite {
+        NonFinitePolicy::Error if cfg.check_finite => {
+            if let Some(index) = (0..x_data.len()).find(|i| !is_finite(i)) {
+                return Err(Error::NonFiniteData { index });
+            }
+            (Cow::Borrowed(x_data), Cow::Borrowed(y_data))
+        }
+        NonFinitePolicy::Drop if cfg.check_finite && !(0..x_data.len()).all(|i| is_finite(&i)) => {
+            let finite: Vec<usize> = (0..x_data.len()).filter(is_finite).collect();
+            (
+                Cow::Owned(finite.iter().map(|&i| x_data[i]).collect()),
+                Cow::Owned(finite.iter().map(|&i| y_data[i]).collect()),
+            )
+        }
+        _ => (Cow::Borrowed(x_data), Cow::Borrowed(y_data)),
+    };
+    let (x_data, y_data) = match sorting_order(&x_data, cfg) {
+        Some(order) => (
+            Cow::Owned(order.iter().map(|&i| x_data[i]).collect()),
+            Cow::Owned(order.iter().map(|&i| y_data[i]).collect()),
+        ),
+        None => (x_data, y_data),
+    };
+
+    // data count check, after dropping points; no points are too few even for no parameters
+    if x_data.len() < N.max(1) {
+        return Err(Error::InsufficientData {
+            n_data: x_data.len(),
+            n_params: N,
+        });
+    }
+    // the Jacobian rows of points at one x are equal, so its rank is at most 1
+    if N > 1 && x_data.iter().all(|&x| x == x_data[0]) {
+        return Err(Error::DegenerateData { x: x_data[0] });
+    }
+    // the total sum of squares is zero, part of the data validation of `check_finite`
+    if cfg.check_finite && y_data.iter().all(|&y| y == y_data[0]) {
+        return Err(Error::ZeroVarianceData { y: y_data[0] });
+    }
+
+    Ok((x_data, y_data))
+}
+
+/// `Error::UnsortedInput` at the first finite `x` below an earlier one, under
+/// `Config::require_sorted`.
+fn check_order(x_data: &[f64], cfg: &Config) -> Result<(), Error> {
+    if !cfg.require_sorted {
+        return Ok(());
+    }
+    let mut finite = x_data.iter().enumerate().filter(|(_, x)| x.is_finite());
+    let Some((_, mut previous)) = finite.next() else {
+        return Ok(());
+    };
+    for (index, x) in finite {
+        if x < previous {
+            return Err(Error::UnsortedInput { index });
+        }
+        previous = x;
+    }
+    Ok(())
+}
+
+/// Indices that sort `x_data` in ascending order under `Config::sort_input`, `None` when the
+/// data stay as they are. Equal `x` keep their order.
+fn sorting_order(x_data: &[f64], cfg: &Config) -> Option<Vec<usize>> {
+    if !cfg.sort_input || x_data.is_sorted_by(|a, b| a.total_cmp(b).is_le()) {
+        return None;
+    }
+    let mut order: Vec<usize> = (0..x_data.len()).collect();
+    order.sort_by(|&i, &j| x_data[i].total_cmp(&x_data[j]));
+    Some(order)
+}
+
+/// All failed checks of `cfg`, a single one unwrapped.
+fn check_config(cfg: &Config) -> Result<(), Error> {
+    cfg.validate_all().map_err(|mut errors| {
+        if errors.len() == 1 {
+            errors.remove(0)
+        } else {
+            Error::MultipleConfigErrors(errors)
+        }
+    })
+}
+
+/// Runs the solver selected by `cfg.method` from the initial guess `p0`.
+fn solve<const N: usize, F: Fn(f64, [f64; N]) -> f64>(
+    func: &F,
+    x_data: &[f64],
+    y_data: &[f64],
+    p0: [f64; N],
+    scaling: solver::Scaling<N>,
+    cfg: &Config,
+) -> solver::Solution<N> {
+    let bounds = solver::Bounds::UNBOUNDED;
+    solve_within(func, x_data, y_data, p0, scaling, bounds, [0.0; N], cfg)
+}
+
+/// [`solve`] within `bounds`, which only TRF and DogBox honour, regularizing towards `p_ref`.
+#[allow(clippy::too_many_arguments)]
+fn solve_within<const N: usize, F: Fn(f64, [f64; N]) -> f64>(
+    func: &F,
+    x_data: &[f64],
+    y_data: &[f64],
+    p0: [f64; N],
+    scaling: solver::Scaling<N>,
+    bounds: solver::Bounds<N>,
+    p_ref: [f64; N],
+    cfg: &Config,
+) -> solver::Solution<N> {
+    let setup = solver::Setup { reference: p_ref, ..Default::default() };
+    run_solver(&(func, x_data, y_data), p0, scaling, bounds, cfg, setup, None)
+}
+
+/// Runs the solver selected by `cfg.method` on the residuals of `problem`, resolving
+/// `Method::Auto`.
+fn run_solver<const N: usize>(
+    problem: &dyn Problem<N>,
+    p0: [f64; N],
+    scaling: solver::Scaling<N>,
+    bounds: solver::Bounds<N>,
+    cfg: &Config,
+    setup: solver::Setup<N>,
+    callback: Option<&dyn Fn(usize, [f64; N], f64)>,
+) -> solver::Solution<N> {
+    let problem = solver::Problem::new(problem, cfg.regularization, setup)
+        .with_differences(cfg.diff_step, cfg.jacobian);
+    let options = solver::Options {
+        scaling,
+        record_history: cfg.record_history,
+        lm: cfg.lm,
+        tr: cfg.tr,
+        deadline: solver::Deadline::new(cfg.timeout),
+        bounds,
+        callback,
+        tracer: cfg.record_trace.then(|| solver::Tracer::new(cfg.trace_rejected)),
+    };
+    // the rules documented on `Method::Auto`; the probe's evaluations count towards `n_fev`
+    let method = match cfg.method {
+        Method::Auto if bounds.is_bounded() => Method::TRF,
+        Method::Auto if !problem.jacobian_is_finite(p0, &bounds) => Method::NelderMead,
+        Method::Auto => Method::LM,
+        method => method,
+    };
+    let mut solution = match method {
+        Method::LM => solver::levenberg_marquardt(&problem, p0, &options),
+        Method::DogBox => solver::dogbox(&problem, p0, &options),
+        Method::TRF => solver::trust_region_reflective(&problem, p0, &options),
+        Method::NelderMead => solver::nelder_mead(&problem, p0, &options),
+        Method::LBFGS => solver::lbfgs(&problem, p0, &options),
+        Method::LinearLSQ => solver::linear_least_squares(&problem, p0, &options),
+        Method::SGD {
+            batch_size,
+            learning_rate,
+        } => solver::stochastic_gradient_descent(
+            &problem,
+            p0,
+            &options,
+            batch_size,
+            learning_rate,
+            cfg.n_epochs,
+            &mut cfg.rng(),
+        ),
+        Method::Auto => unreachable!("resolved above"),
+    };
+    solution.trace = options.tracer.map(solver::Tracer::into_entries);
+    solution
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::Array;
+    use ndarray_rand::rand_distr::Normal;
+    use ndarray_rand::RandomExt;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn target_func(x: f64, p: [f64; 2]) -> f64 {
+        p[0] * x + p[1]
+    }
+
+    #[test]
+    fn it_works() {
+        let lamda_func = |x: f64, p: [f64; 2]| p[0] * x + p[1];
+        lamda_func
+            .fit(
+                &[1.0, 2.0, 3.0],
+                &[1.0, 2.0, 3.0],
+                Config {
+                    check_finite: false,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        let f = target_func
+            .fit(
+                &[1.0, 2.0, 3.0],
+                &[1.0, 2.0, 3.0],
+                Config {
+                    check_finite: false,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        f.curve.eval(1.0);
+    }
+
+    #[test]
+    fn non_finite_points() {
+        let x_data = [0.0, 1.0, 2.0, f64::NAN, 4.0, 5.0, 6.0];
+        let y_data = [1.1, 2.9, 5.2, 7.0, f64::INFINITY, 10.8, 13.1];
+        let cfg = |non_finite| Config {
+            non_finite,
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            target_func.fit(&x_data, &y_data, cfg(NonFinitePolicy::Error)),
+            Err(Error::NonFiniteData { index: 3 })
+        ));
+
+        let dropped = target_func
+            .fit(&x_data, &y_data, cfg(NonFinitePolicy::Drop))
+            .unwrap();
+        let removed = target_func
+            .fit(
+                &[0.0, 1.0, 2.0, 5.0, 6.0],
+                &[1.1, 2.9, 5.2, 10.8, 13.1],
+                Config::default(),
+            )
+            .unwrap();
+        assert_eq!(dropped.curve.params(), removed.curve.params());
+        assert_eq!(dropped.n_data, 5);
+
+        assert!(matches!(
+            target_func.fit(&[1.0, f64::NAN], &[2.0, 3.0], cfg(NonFinitePolicy::Drop)),
+            Err(Error::InsufficientData {
+                n_data: 1,
+                n_params: 2
+            })
+        ));
+        // NaN residuals stop the solver at once
+        assert!(matches!(
+            target_func.fit(&x_data, &y_data, cfg(NonFinitePolicy::Allow)),
+            Err(Error::DidNotConverge {
+                reason: TerminationReason::NonFinite,
+                iterations: 0,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn empty_and_short_data() {
+        let line = |x: f64, p: [f64; 2]| p[0] * x + p[1];
+        let cases: [(&[f64], &[f64]); 4] = [
+            (&[], &[1.0]),
+            (&[1.0], &[]),
+            (&[], &[]),
+            (&[1.0], &[2.0]),
+        ];
+        let errors = cases.map(|(x_data, y_data)| line.fit(x_data, y_data, Config::default()));
+        assert!(matches!(
+            errors[0],
+            Err(Error::UnmatchedLength {
+                x_data_len: 0,
+                y_data_len: 1
+            })
+        ));
+        assert!(matches!(
+            errors[1],
+            Err(Error::UnmatchedLength {
+                x_data_len: 1,
+                y_data_len: 0
+            })
+        ));
+        for error in &errors[2..] {
+            assert!(matches!(
+                error,
+                Err(Error::InsufficientData { n_params: 2, .. })
+            ));
+        }
+        assert!(matches!(
+            errors[2],
+            Err(Error::InsufficientData { n_data: 0, .. })
+        ));
+        assert!(matches!(
+            errors[3],
+            Err(Error::InsufficientData { n_data: 1, .. })
+        ));
+
+        // the checks hold without the data validation, and for a model without parameters
+        let unchecked = Config { check_finite: false, ..Default::default() };
+        assert!(matches!(
+            line.fit(&[], &[], unchecked),
+            Err(Error::InsufficientData { n_data: 0, .. })
+        ));
+        let constant = |_: f64, _: [f64; 0]| 1.0;
+        assert!(matches!(
+            constant.fit(&[], &[], Config::default()),
+            Err(Error::InsufficientData {
+                n_data: 0,
+                n_params: 0
+            })
+        ));
+        assert!(line.fit_batch(&[], Config::default()).is_empty());
+    }
+
+    #[test]
+    fn non_convergence_is_an_error() {
+        let growth = |x: f64, p: [f64; 1]| (p[0] * x).exp();
+        let x_data: Vec<f64> = (0..=80).map(|i| 10.0 * i as f64).collect();
+        // growth cannot change sign, and exp(800) overflows at the initial guess
+        let y_data: Vec<f64> = x_data.iter().map(|&x| 400.0 - x).collect();
+
+        for method in [Method::LM, Method::TRF, Method::DogBox] {
+            let cfg = Config { method, ..Default::default() };
+            match growth.fit(&x_data, &y_data, cfg) {
+                Err(Error::DidNotConverge {
+                    reason,
+                    last_params,
+                    last_cost,
+                    ..
+                }) => {
+                    assert_eq!(reason, TerminationReason::NonFinite, "{method:?}");
+                    assert_eq!(last_params, [1.0]);
+                    assert!(!last_cost.is_finite());
+                }
+                Err(e) => panic!("{method:?}: {e}"),
+                Ok(f) => panic!("{method:?}: converged to {:?}", f.curve.params()),
+            }
+        }
+    }
+
+    #[test]
+    fn parameter_independent_model_is_an_error() {
+        let x_data = [0.0, 1.0, 2.0, 3.0];
+        let y_data = [0.0, 0.8, 0.9, 0.1];
+        let ignores_params = |x: f64, _: [f64; 2]| x.sin();
+        for method in [Method::LM, Method::TRF, Method::DogBox, Method::NelderMead] {
+            let cfg = Config { method, ..Default::default() };
+            match ignores_params.fit(&x_data, &y_data, cfg) {
+                Err(Error::ZeroJacobian { params }) => assert_eq!(params, [1.0, 1.0]),
+                other => panic!("{method:?}: {:?}", other.map(|f| f.curve.params())),
+            }
+        }
+        // a stationary starting point of a model that does depend on its parameters
+        let saddle = |x: f64, p: [f64; 2]| (p[0] - 1.0) * (p[1] - 1.0) * x;
+        assert!(matches!(
+            saddle.fit(&x_data, &y_data, Config::default()),
+            Err(Error::ZeroJacobian { .. })
+        ));
+    }
+
+    #[test]
+    fn point_order_and_replicates() {
+        let decay = |x: f64, p: [f64; 3]| p[0] * (-p[1] * x).exp() + p[2];
+        // three replicates at each of ten setpoints
+        let mut points: Vec<(f64, f64)> = (0..30)
+            .map(|i| {
+                let x = 0.5 * (i % 10) as f64;
+                (x, decay(x, [4.0, 1.5, 0.5]) + 0.05 * (1.3 * i as f64).sin())
+            })
+            .collect();
+        let fit = |points: &[(f64, f64)]| {
+            let (x_data, y_data): (Vec<f64>, Vec<f64>) = points.iter().copied().unzip();
+            decay.fit(&x_data, &y_data, Config::default()).unwrap()
+        };
+
+        let ordered = fit(&points);
+        points.reverse();
+        points.swap(3, 17);
+        points.swap(8, 25);
+        let shuffled = fit(&points);
+        // the order of summation only changes rounding, within the solver tolerance
+        for (a, b) in ordered.curve.params().iter().zip(shuffled.curve.params()) {
+            assert!((a - b).abs() < 1e-8 * a.abs(), "{a} vs {b}");
+        }
+        assert!((ordered.ssr - shuffled.ssr).abs() < 1e-8 * ordered.ssr);
+
+        let x_data = [2.0; 5];
+        let y_data = [1.0, 1.2, 0.9, 1.1, 1.0];
+        assert!(matches!(
+            target_func.fit(&x_data, &y_data, Config::default()),
+            Err(Error::DegenerateData { x }) if x == 2.0
+        ));
+        // a single parameter is determined by replicates alone
+        let constant = |_: f64, p: [f64; 1]| p[0];
+        let mean = constant.fit(&x_data, &y_data, Config::default()).unwrap();
+        assert!((mean.curve.params()[0] - 1.04).abs() < 1e-9);
+
+        let x_data = [0.0, 1.0, 2.0, 3.0];
+        let y_data = [1.5; 4];
+        assert!(matches!(
+            target_func.fit(&x_data, &y_data, Config::default()),
+            Err(Error::ZeroVarianceData { y }) if y == 1.5
+        ));
+        let cfg = Config { check_finite: false, ..Default::default() };
+        let flat = target_func.fit(&x_data, &y_data, cfg).unwrap();
+        assert!(flat.curve.params()[0].abs() < 1e-9);
+    }
+
+    #[test]
+    fn methods_converge() {
+        let decay = |x: f64, p: [f64; 3]| p[0] * (-p[1] * x).exp() + p[2];
+        let x_data: Vec<f64> = (0..30).map(|i| 0.2 * i as f64).collect();
+        let y_data: Vec<f64> = x_data.iter().map(|&x| decay(x, [4.0, 1.5, 0.5])).collect();
+
+        for method in [Method::LM, Method::DogBox, Method::TRF, Method::LBFGS] {
+            let f = decay
+                .fit(&x_data, &y_data, Config { method, ..Default::default() })
+                .unwrap();
+            let params = f.curve.params();
+            for (p, expected) in params.iter().zip([4.0, 1.5, 0.5]) {
+                assert!((p - expected).abs() < 1e-6, "{method:?}: {params:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn lbfgs_solves_rosenbrock() {
+        // the residuals 10 (p1 - p0^2) and 1 - p0 at x = 0 and 1, whose sum of squares is the
+        // Rosenbrock function with its curved valley and minimum at (1, 1)
+        let rosenbrock = |x: f64, p: [f64; 2]| {
+            if x == 0.0 {
+                10.0 * (p[1] - p[0] * p[0])
+            } else {
+                1.0 - p[0]
+            }
+        };
+        // the zero targets are not data with zero variance
+        let start = Config { p0: -1.2, check_finite: false, ..Default::default() };
+        let mut reports = vec![];
+        for method in [Method::LM, Method::LBFGS] {
+            let f = rosenbrock
+                .fit(&[0.0, 1.0], &[0.0, 0.0], Config { method, ..start })
+                .unwrap();
+            let params = f.curve.params();
+            for p in params {
+                assert!((p - 1.0).abs() < 1e-5, "{method:?}: {params:?}");
+            }
+            assert_eq!(f.report.method, method);
+            reports.push(f.report);
+        }
+        // Gauss-Newton steps exploit the least-squares structure that L-BFGS ignores
+        assert!(reports[0].n_fev < reports[1].n_fev, "{reports:?}");
+
+        let f = rosenbrock.fit_bounded(
+            &[0.0, 1.0],
+            &[0.0, 0.0],
+            Config { method: Method::LBFGS, ..start },
+            [0.0; 2],
+            [2.0; 2],
+        );
+        assert!(matches!(f, Err(Error::BoundsUnsupported { method: Method::LBFGS })));
+    }
+
+    #[test]
+    fn linear_models_are_solved_directly() {
+        let calls = std::cell::Cell::new(0);
+        let cubic = |x: f64, p: [f64; 4]| {
+            calls.set(calls.get() + 1);
+            p[0] + p[1] * x + p[2] * x * x + p[3] * x.powi(3)
+        };
+        let x_data: Vec<f64> = (0..25).map(|i| -1.0 + 0.125 * i as f64).collect();
+        let y_data: Vec<f64> = x_data
+            .iter()
+            .enumerate()
+            .map(|(i, &x)| 0.5 - 2.0 * x + 0.3 * x.powi(3) + 0.05 * (2.1 * i as f64).sin())
+            .collect();
+
+        // TRF steps reach the solution to rounding, where LM stops within its tolerances
+        let trf = Config { method: Method::TRF, ..Default::default() };
+        let reference = cubic.fit(&x_data, &y_data, trf).unwrap();
+        for p0 in [1.0, -40.0, 1e4] {
+            calls.set(0);
+            let cfg = Config { method: Method::LinearLSQ, p0, ..Default::default() };
+            let f = cubic.fit(&x_data, &y_data, cfg).unwrap();
+            for (p, expected) in f.curve.params().iter().zip(reference.curve.params()) {
+                assert!((p - expected).abs() < 1e-12, "{p0}: {p} vs {expected}");
+            }
+            assert!((f.ssr - reference.ssr).abs() < 1e-12 * reference.ssr);
+            // the initial residuals, a single Jacobian and one pass per step
+            assert_eq!(f.report.n_fev, 1 + 4 + f.report.iterations, "{:?}", f.report);
+            assert_eq!(calls.get(), f.report.n_fev * x_data.len());
+            assert_eq!(f.report.method, Method::LinearLSQ);
+        }
+    }
+
+    #[test]
+    fn stochastic_gradient_descent_approaches_lm() {
+        let decay = |x: f64, p: [f64; 2]| p[0] * (-p[1] * x).exp();
+        let mut rng = StdRng::seed_from_u64(11);
+        let x_data: Vec<f64> = (0..100_000).map(|i| 2.0 * i as f64 / 100_000.0).collect();
+        let noise = Array::random_using(x_data.len(), Normal::new(0.0, 0.05).unwrap(), &mut rng);
+        let y_data: Vec<f64> =
+            x_data.iter().zip(&noise).map(|(&x, e)| decay(x, [2.0, 1.5]) + e).collect();
+
+        let lm = decay.fit(&x_data, &y_data, Config::default()).unwrap();
+        let sgd = Method::SGD { batch_size: 64, learning_rate: 0.2 };
+        let cfg = Config { method: sgd, n_epochs: 3, seed: Some(5), ..Default::default() };
+        let f = decay.fit(&x_data, &y_data, cfg).unwrap();
+        // three passes over the data land next to the minimum LM finds
+        for (p, expected) in f.curve.params().iter().zip(lm.curve.params()) {
+            assert!((p - expected).abs() < 0.01, "{p} vs {expected}");
+        }
+        assert!(f.ssr < 1.001 * lm.ssr, "{} vs {}", f.ssr, lm.ssr);
+        assert_eq!(f.report.iterations, 3);
+        assert_eq!(f.report.method, sgd);
+        // the initial pass, three epochs of batch gradients and their costs, and the Jacobian
+        assert_eq!(f.report.n_fev, 1 + 3 * 3 + 3 + 2);
+        let again = decay.fit(&x_data, &y_data, cfg).unwrap();
+        assert_eq!(again.curve.params().map(f64::to_bits), f.curve.params().map(f64::to_bits));
+
+        let bounded = decay.fit_bounded(&x_data, &y_data, cfg, [0.0; 2], [5.0; 2]);
+        assert!(matches!(bounded, Err(Error::BoundsUnsupported { method }) if method == sgd));
+    }
+
+    #[test]
+    fn finite_difference_steps_follow_diff_step() {
+        // a microsecond decay time, for which the default step is over 1% of the parameter
+        let decay = |t: f64, p: [f64; 2]| p[0] * (-t / p[1]).exp();
+        let t_data: Vec<f64> = (0..50).map(|i| 1e-7 * i as f64).collect();
+        let y_data: Vec<f64> = t_data
+            .iter()
+            .enumerate()
+            .map(|(i, &t)| decay(t, [2.0, 1e-6]) + 0.01 * (1.7 * i as f64).sin())
+            .collect();
+        let fit = |diff_step| {
+            let cfg = Config { diff_step, ..Default::default() };
+            decay.fit(&t_data, &y_data, cfg).unwrap()
+        };
+
+        let default = fit(None);
+        let tuned = fit(Some(1e-10));
+        let coarse = fit(Some(0.1));
+        assert!(tuned.ssr < default.ssr, "{} vs {}", tuned.ssr, default.ssr);
+        assert!(coarse.ssr > 1000.0 * tuned.ssr, "{} vs {}", coarse.ssr, tuned.ssr);
+        let cfg = Config { diff_step: Some(0.0), ..Default::default() };
+        assert!(matches!(
+            decay.fit(&t_data, &y_data, cfg),
+            Err(Error::ConfigCheckFailed(range_checker::Error::CheckFailed { ident, .. }))
+                if ident == "diff_step"
+        ));
+    }
+
+    #[test]
+    fn central_differences_are_more_accurate() {
+        let decay = |t: f64, p: [f64; 2]| p[0] * (-p[1] * t).exp();
+        let t_data: Vec<f64> = (0..40).map(|i| 0.1 * i as f64).collect();
+        let y_data: Vec<f64> = t_data
+            .iter()
+            .enumerate()
+            .map(|(i, &t)| decay(t, [3.0, 1.5]) + 0.01 * (1.7 * i as f64).sin())
+            .collect();
+        let fit = |jacobian| {
+            let cfg = Config { diff_step: Some(1e-2), jacobian, ..Default::default() };
+            decay.fit(&t_data, &y_data, cfg).unwrap()
+        };
+
+        // with a coarse step, forward differences stop short of the minimum
+        let exact = decay.fit(&t_data, &y_data, Config::default()).unwrap();
+        let forward = fit(JacMethod::Forward);
+        let central = fit(JacMethod::Central);
+        let (forward_excess, central_excess) = (forward.ssr - exact.ssr, central.ssr - exact.ssr);
+        assert!(central_excess < 0.01 * forward_excess, "{central_excess} vs {forward_excess}");
+        let params = forward.curve.params().into_iter().zip(central.curve.params());
+        for ((f, c), e) in params.zip(exact.curve.params()) {
+            assert!((c - e).abs() < 0.1 * (f - e).abs(), "{c} vs {f}");
+        }
+        assert!(
+            central.report.n_fev > forward.report.n_fev,
+            "{} vs {}",
+            central.report.n_fev,
+            forward.report.n_fev
+        );
+    }
+
+    #[test]
+    fn data_scaled_guesses_reach_large_amplitudes() {
+        let decay = |t: f64, p: [f64; 2]| p[0] * (-p[1] * t).exp();
+        let t_data: Vec<f64> = (0..40).map(|i| 0.025 * i as f64).collect();
+        let y_data: Vec<f64> = t_data
+            .iter()
+            .enumerate()
+            .map(|(i, &t)| decay(t, [1e5, 5.0]) + 100.0 * (1.7 * i as f64).sin())
+            .collect();
+
+        // from ones, the rate runs off until only the first point is fitted
+        let ones = decay.fit(&t_data, &y_data, Config::default());
+        assert!(
+            ones.as_ref().map_or(true, |f| f.curve.params()[1] > 100.0),
+            "{:?}",
+            ones.map(|f| f.curve.params())
+        );
+
+        let cfg = Config { p0_strategy: P0Strategy::DataScaled, ..Default::default() };
+        let guess: [f64; 3] = cfg.initial_guess(&t_data, &y_data);
+        let mean_abs = y_data.iter().map(|y| y.abs()).sum::<f64>() / 40.0;
+        assert_eq!(guess[..2], [mean_abs, 1.0 / t_data[39]]);
+        let f = decay.fit(&t_data, &y_data, cfg).unwrap();
+        let [a, k] = f.curve.params();
+        assert!((a / 1e5 - 1.0).abs() < 1e-2 && (k - 5.0).abs() < 0.05, "{a}, {k}");
+
+        // the guess leads the random starts
+        let multi = decay.fit_multistart(&t_data, &y_data, cfg, 3, [(0.5, 2.0); 2]).unwrap();
+        assert_eq!(multi.starts.len(), 4);
+        assert_eq!(multi.starts[0].p0, guess[..2]);
+        assert_eq!(multi.best.curve.params(), [a, k]);
+    }
+
+    #[test]
+    fn function_evaluations_are_counted() {
+        let calls = std::cell::Cell::new(0);
+        let decay = |x: f64, p: [f64; 3]| {
+            calls.set(calls.get() + 1);
+            p[0] * (-p[1] * x).exp() + p[2]
+        };
+        let x_data: Vec<f64> = (0..30).map(|i| 0.2 * i as f64).collect();
+        let y_data: Vec<f64> = x_data.iter().map(|&x| 4.0 * (-1.5 * x).exp() + 0.5).collect();
+
+        for method in [Method::LM, Method::DogBox, Method::TRF] {
+            calls.set(0);
+            let f = decay
+                .fit(&x_data, &y_data, Config { method, ..Default::default() })
+                .unwrap();
+            let (iterations, n_fev) = (f.report.iterations, f.report.n_fev);
+            assert_eq!(calls.get(), n_fev * x_data.len(), "{method:?}");
+            // one trial step and a Jacobian of N evaluations per iteration
+            assert!(
+                4 * iterations <= n_fev && n_fev <= 2 * 4 * (iterations + 1),
+                "{method:?}: {:?}",
+                f.report
+            );
+        }
+    }
+
+    #[test]
+    fn stateful_models_count_their_calls() {
+        let x_data: Vec<f64> = (0..30).map(|i| 0.2 * i as f64).collect();
+        let y_data: Vec<f64> = x_data.iter().map(|&x| 4.0 * (-1.5 * x).exp() + 0.5).collect();
+        let mut calls = 0;
+        let mut points = vec![];
+        let decay = |x: f64, p: [f64; 3]| {
+            calls += 1;
+            points.push(x);
+            p[0] * (-p[1] * x).exp() + p[2]
+        };
+
+        let f = fit_mut(decay, &x_data, &y_data, Config::default()).unwrap();
+        for (p, expected) in f.curve.params().iter().zip([4.0, 1.5, 0.5]) {
+            assert!((p - expected).abs() < 1e-6, "{p}");
+        }
+        let n_fev = f.report.n_fev;
+        let y = f.curve.eval(1.0);
+        assert!((y - (4.0 * (-1.5_f64).exp() + 0.5)).abs() < 1e-6);
+        drop(f);
+        assert_eq!(calls, n_fev * x_data.len() + 1);
+        // whole passes over the data in index order, then the evaluation of the curve
+        let (passes, last) = points.split_at(n_fev * x_data.len());
+        for pass in passes.chunks(x_data.len()) {
+            assert_eq!(pass, x_data);
+        }
+        assert_eq!(last, [1.0]);
+    }
+
+    #[test]
+    fn display_uses_parameter_names() {
+        let gaussian =
+            |x: f64, p: [f64; 3]| p[0] * (-(x - p[1]).powi(2) / (2.0 * p[2].powi(2))).exp();
+        let x_data: Vec<f64> = (0..40).map(|i| -2.0 + 0.1 * i as f64).collect();
+        let y_data: Vec<f64> = x_data.iter().map(|&x| gaussian(x, [2.5, 0.13, 0.8])).collect();
+
+        let f = gaussian.fit(&x_data, &y_data, Config::default()).unwrap().curve;
+        assert_eq!(f.parameter_names(), None);
+        assert_eq!(
+            format!("{f:.2}"),
+            "params[0] = 2.50, params[1] = 0.13, params[2] = 0.80"
+        );
+
+        let f = f.with_names(["amplitude", "center", "sigma"]);
+        assert_eq!(f.parameter_names(), Some(["amplitude", "center", "sigma"]));
+        assert_eq!(
+            format!("{f:.2}"),
+            "amplitude = 2.50, center = 0.13, sigma = 0.80"
+        );
+    }
+
+    #[test]
+    fn method_names_round_trip() {
+        let methods = [
+            Method::LM,
+            Method::DogBox,
+            Method::TRF,
+            Method::NelderMead,
+            Method::LBFGS,
+            Method::LinearLSQ,
+            Method::SGD {
+                batch_size: 32,
+                learning_rate: 0.01,
+            },
+            Method::Auto,
+        ];
+        for method in methods {
+            assert_eq!(method.to_string().parse::<Method>().unwrap(), method);
+        }
+        assert_eq!(Method::NelderMead.to_string(), "nelder-mead");
+        for (name, method) in [
+            ("LM", Method::LM),
+            (" Levenberg_Marquardt ", Method::LM),
+            ("Trust-Region-Reflective", Method::TRF),
+            ("L-BFGS", Method::LBFGS),
+            ("SGD(8,1e-3)", Method::SGD { batch_size: 8, learning_rate: 1e-3 }),
+        ] {
+            assert_eq!(name.parse::<Method>().unwrap(), method, "{name}");
+        }
+
+        for name in ["dogbx", "", "sgd", "sgd(8)", "sgd(-1, 0.1)"] {
+            let error = name.parse::<Method>().unwrap_err();
+            assert!(matches!(&error, Error::UnknownMethod { name: n } if n == name));
+        }
+        assert_eq!(
+            "dogbx".parse::<Method>().unwrap_err().to_string(),
+            "unknown method \"dogbx\", expected one of lm, dogbox, trf, nelder-mead, lbfgs, \
+             linear-lsq, sgd(batch_size, learning_rate) or auto"
+        );
+    }
+
+    #[test]
+    fn summary_table() {
+        let x_data: Vec<f64> = (0..10).map(f64::from).collect();
+        let y_data: Vec<f64> = x_data
+            .iter()
+            .map(|&x| 1.5 * x - 0.5 + 0.1 * (2.1 * x).sin())
+            .collect();
+        let cfg = Config {
+            method: Method::LM,
+            ..Default::default()
+        };
+        let mut f = target_func.fit(&x_data, &y_data, cfg).unwrap();
+        let summary = f.summary();
+        let lines: Vec<&str> = summary.lines().collect();
+        assert_eq!(lines.len(), 8);
+        assert!(lines.iter().all(|line| line.is_ascii() && line.len() <= 80));
+        assert!(lines[0].starts_with("parameter") && lines[0].ends_with("95% upper"));
+        assert_eq!(lines[1], "-".repeat(76));
+        let [slope, _] = f.curve.params();
+        let [[lower, upper], _] = f.confidence_intervals(0.05);
+        let columns: Vec<&str> = lines[2].split_whitespace().collect();
+        assert_eq!(columns[0], "params[0]");
+        for (column, value) in columns[1..].iter().zip([slope, f.std_errors()[0], lower, upper]) {
+            assert_eq!(column.parse::<f64>().unwrap(), format!("{value:.6e}").parse().unwrap());
+        }
+        assert!(lines[3].starts_with("params[1] "));
+        assert!(lines[5].contains("n_data = 10, dof = 8"), "{}", lines[5]);
+        let r_squared = format!("r_squared = {:.6},", f.r_squared());
+        assert!(lines[5].contains(&r_squared), "{}", lines[5]);
+        let mean = y_data.iter().sum::<f64>() / 10.0;
+        let tss: f64 = y_data.iter().map(|y| (y - mean).powi(2)).sum();
+        assert!((f.r_squared() - (1.0 - f.ssr / tss)).abs() < 1e-12);
+        assert!(f.r_squared() > 0.99 && f.r_squared() < 1.0, "{}", f.r_squared());
+        assert!(lines[6].starts_with("aic = "), "{}", lines[6]);
+        assert!(lines[7].contains("method = LM"), "{}", lines[7]);
+        let termination = format!("termination = {:?}", f.report.termination);
+        assert!(lines[7].ends_with(&termination), "{}", lines[7]);
+
+        f.curve = f.curve.with_names(["slope", "intercept"]);
+        let summary = f.summary();
+        assert!(summary.lines().nth(2).unwrap().starts_with("slope "));
+        assert!(summary.lines().nth(3).unwrap().starts_with("intercept "));
+        assert_eq!(format!("{f}"), summary);
+        let short = format!("{f:.3}");
+        assert!(short.contains(&format!(" {slope:.3e} ")), "{short}");
+        assert!(short.lines().skip(4).eq(summary.lines().skip(4)));
+
+        // a line through two points leaves no degrees of freedom for the errors
+        let f = target_func.fit(&[0.0, 1.0], &[1.0, 3.0], cfg).unwrap();
+        assert!(f.std_errors().iter().all(|e| e.is_infinite()));
+        let summary = f.summary();
+        for value in f.curve.params() {
+            assert!(summary.contains(&format!("{value:.6e}")), "{summary}");
+        }
+        assert!(summary.lines().nth(2).unwrap().contains("inf"), "{summary}");
+    }
+
+    #[test]
+    fn bounded_fits_stop_at_active_bounds() {
+        let decay = |x: f64, p: [f64; 3]| p[0] * (-p[1] * x).exp() + p[2];
+        let x_data: Vec<f64> = (0..30).map(|i| 0.2 * i as f64).collect();
+        let y_data: Vec<f64> = x_data.iter().map(|&x| decay(x, [4.0, 1.5, 0.5])).collect();
+        let inactive = ([0.0, 0.0, f64::NEG_INFINITY], [10.0, 5.0, 2.0]);
+        // the offset is held below its true value
+        let active = ([0.0, 0.0, f64::NEG_INFINITY], [10.0, 5.0, 0.3]);
+
+        let unbounded = decay.fit(&x_data, &y_data, Config::default()).unwrap();
+        let mut at_bound = vec![];
+        for method in [Method::TRF, Method::DogBox] {
+            let cfg = Config { method, ..Default::default() };
+            let (lower, upper) = inactive;
+            let f = decay.fit_bounded(&x_data, &y_data, cfg, lower, upper).unwrap();
+            for (p, expected) in f.curve.params().iter().zip(unbounded.curve.params()) {
+                assert!((p - expected).abs() < 1e-6, "{method:?}: {:?}", f.curve.params());
+            }
+            assert_eq!(f.curve.at_bounds(), [false; 3]);
+
+            let (lower, upper) = active;
+            let f = decay.fit_bounded(&x_data, &y_data, cfg, lower, upper).unwrap();
+            assert_eq!(f.curve.at_bounds(), [false, false, true], "{method:?}");
+            let params = f.curve.params();
+            for (p, (l, u)) in params.iter().zip(lower.iter().zip(upper)) {
+                assert!(*l <= *p && *p <= u, "{method:?}: {params:?}");
+            }
+            assert!((params[2] - 0.3).abs() < 1e-6, "{method:?}: {params:?}");
+            at_bound.push(f);
+        }
+        let (trf, dogbox) = (&at_bound[0], &at_bound[1]);
+        assert!((trf.ssr - dogbox.ssr).abs() < 1e-6 * trf.ssr, "{} vs {}", trf.ssr, dogbox.ssr);
+
+        let (lower, upper) = active;
+        let cfg = Config { method: Method::LM, ..Default::default() };
+        assert!(matches!(
+            decay.fit_bounded(&x_data, &y_data, cfg, lower, upper),
+            Err(Error::BoundsUnsupported { method: Method::LM })
+        ));
+        let cfg = Config { method: Method::TRF, ..Default::default() };
+        assert!(matches!(
+            decay.fit_bounded(&x_data, &y_data, cfg, [0.0, 1.0, 0.0], [1.0, 1.0, 1.0]),
+            Err(Error::InvalidBounds { index: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn guesses_outside_the_bounds_are_clamped() {
+        let decay = |x: f64, p: [f64; 2]| p[0] * (-p[1] * x).exp();
+        let x_data: Vec<f64> = (0..30).map(|i| 0.2 * i as f64).collect();
+        let y_data: Vec<f64> = x_data.iter().map(|&x| decay(x, [4.0, 1.5])).collect();
+        let (lower, upper) = ([0.0, 0.0], [10.0, 3.0]);
+
+        // a rate of 20 would leave almost nothing to fit beyond the first point
+        for method in [Method::TRF, Method::DogBox] {
+            let cfg = Config { method, p0: 20.0, ..Default::default() };
+            let f = decay.fit_bounded(&x_data, &y_data, cfg, lower, upper).unwrap();
+            let params = f.curve.params();
+            for (p, expected) in params.iter().zip([4.0, 1.5]) {
+                assert!((p - expected).abs() < 1e-6, "{method:?}: {params:?}");
+            }
+
+            let cfg = Config { out_of_bounds: OutOfBoundsPolicy::Error, ..cfg };
+            assert!(matches!(
+                decay.fit_bounded(&x_data, &y_data, cfg, lower, upper),
+                Err(Error::GuessOutOfBounds { index: 0, p0, .. }) if p0 == 20.0
+            ));
+        }
+        // a guess on a bound is inside
+        let cfg = Config {
+            method: Method::TRF,
+            p0: 3.0,
+            out_of_bounds: OutOfBoundsPolicy::Error,
+            ..Default::default()
+        };
+        assert!(decay.fit_bounded(&x_data, &y_data, cfg, [0.0, 0.0], [10.0, 3.0]).is_ok());
+    }
+
+    #[test]
+    fn parameters_at_lower_bound_are_flagged() {
+        let decay = |x: f64, p: [f64; 3]| p[0] * (-p[1] * x).exp() + p[2];
+        let x_data: Vec<f64> = (0..30).map(|i| 0.2 * i as f64).collect();
+        let y_data: Vec<f64> = x_data.iter().map(|&x| decay(x, [4.0, 1.5, 0.5])).collect();
+        // the rate may not fall to its true value
+        let lower = [0.0, 2.0, f64::NEG_INFINITY];
+        let upper = [f64::INFINITY; 3];
+
+        for method in [Method::TRF, Method::DogBox] {
+            let cfg = Config { method, ..Default::default() };
+            let f = decay.fit_bounded(&x_data, &y_data, cfg, lower, upper).unwrap();
+            assert_eq!(f.curve.at_bounds(), [false, true, false], "{method:?}");
+            assert!((f.curve.params()[1] - 2.0).abs() < 1e-6);
+        }
+        let unbounded = decay.fit(&x_data, &y_data, Config::default()).unwrap();
+        assert_eq!(unbounded.curve.at_bounds(), [false; 3]);
+    }
+
+    #[test]
+    fn half_open_bounds() {
+        let x_data: Vec<f64> = (0..20).map(|i| 0.25 * i as f64).collect();
+        let lower = [0.0, f64::NEG_INFINITY];
+        let upper = [f64::INFINITY; 2];
+        for method in [Method::TRF, Method::DogBox] {
+            let cfg = Config { method, ..Default::default() };
+            // a rising line is inside the bounds
+            let y_data: Vec<f64> = x_data.iter().map(|&x| target_func(x, [1.5, -3.0])).collect();
+            let f = target_func.fit_bounded(&x_data, &y_data, cfg, lower, upper).unwrap();
+            for (p, expected) in f.curve.params().iter().zip([1.5, -3.0]) {
+                assert!((p - expected).abs() < 1e-6, "{method:?}: {p}");
+            }
+            assert_eq!(f.curve.at_bounds(), [false; 2]);
+
+            // a falling one is best matched by a flat line through the mean
+            let y_data: Vec<f64> = x_data.iter().map(|&x| target_func(x, [-0.5, 2.0])).collect();
+            let f = target_func.fit_bounded(&x_data, &y_data, cfg, lower, upper).unwrap();
+            let [slope, intercept] = f.curve.params();
+            let mean = y_data.iter().sum::<f64>() / y_data.len() as f64;
+            assert!((0.0..1e-6).contains(&slope), "{method:?}: {slope}");
+            assert!((intercept - mean).abs() < 1e-5, "{method:?}: {intercept} vs {mean}");
+            assert_eq!(f.curve.at_bounds(), [true, false], "{method:?}");
+        }
+    }
+
+    #[test]
+    fn lm_damping_options() {
+        let decay = |x: f64, p: [f64; 3]| p[0] * (-p[1] * x).exp() + p[2];
+        let x_data: Vec<f64> = (0..20).map(|i| 0.2 * i as f64).collect();
+        let y_data: Vec<f64> = x_data.iter().map(|&x| decay(x, [2.0, 0.7, 0.3])).collect();
+        let with = |lm: LmOptions| Config {
+            method: Method::LM,
+            lm,
+            ..Default::default()
+        };
+
+        let defaults = decay.fit(&x_data, &y_data, with(LmOptions::default())).unwrap();
+        let aggressive = LmOptions {
+            lambda_up_factor: 1e4,
+            lambda_down_factor: 100.0,
+            ..Default::default()
+        };
+        let f = decay.fit(&x_data, &y_data, with(aggressive)).unwrap();
+        for (p, expected) in f.curve.params().iter().zip(defaults.curve.params()) {
+            assert!((p - expected).abs() < 1e-6, "{p} vs {expected}");
+        }
+
+        // a damping beyond the limit from the start gives up at once
+        let stiff = LmOptions {
+            lambda_init: 1e30,
+            ..Default::default()
+        };
+        assert!(matches!(
+            decay.fit(&x_data, &y_data, with(stiff)),
+            Err(Error::DidNotConverge {
+                reason: TerminationReason::MaxDamping,
+                iterations: 0,
+                ..
+            })
+        ));
+
+        let invalid = [
+            ("lambda_init", LmOptions { lambda_init: 0.0, ..Default::default() }),
+            ("lambda_up_factor", LmOptions { lambda_up_factor: f64::NAN, ..Default::default() }),
+            ("lambda_max", LmOptions { lambda_max: -1.0, ..Default::default() }),
+            ("svd_threshold", LmOptions { svd_threshold: 1.0, ..Default::default() }),
+        ];
+        for (field, lm) in invalid {
+            match decay.fit(&x_data, &y_data, with(lm)) {
+                Err(Error::ConfigCheckFailed(range_checker::Error::CheckFailed {
+                    ident, ..
+                })) => assert_eq!(ident, field),
+                _ => panic!("{field} passed"),
+            }
+        }
+    }
+
+    #[test]
+    fn lm_svd_threshold() {
+        // only the sum of the two rates is determined, so the Jacobian has rank 2
+        let redundant = |x: f64, p: [f64; 3]| p[0] * (-(p[1] + p[2]) * x).exp();
+        let x_data: Vec<f64> = (0..20).map(|i| 0.25 * i as f64).collect();
+        let y_data: Vec<f64> = x_data
+            .iter()
+            .enumerate()
+            .map(|(i, &x)| redundant(x, [2.0, 0.5, 1.0]) + 0.01 * (1.7 * i as f64).sin())
+            .collect();
+        let with = |svd_threshold: f64| Config {
+            method: Method::LM,
+            lm: LmOptions {
+                lambda_init: 1e-16,
+                svd_threshold,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        // the undetermined direction is left alone, so the rates stay equal as they start
+        let f = redundant.fit(&x_data, &y_data, with(1e-12)).unwrap();
+        let [amplitude, a, b] = f.curve.params();
+        assert!((a - b).abs() < 1e-6, "{a} vs {b}");
+        assert!((a + b - 1.5).abs() < 0.01, "{a} + {b}");
+        assert!((amplitude - 2.0).abs() < 0.02, "{amplitude}");
+
+        // with every direction kept, rounding errors pull them apart
+        let f = redundant.fit(&x_data, &y_data, with(0.0)).unwrap();
+        let [_, a, b] = f.curve.params();
+        assert!((a - b).abs() > 1.0, "{a} vs {b}");
+
+        // orthogonal distance regression and global fits take their steps the same way
+        let f = redundant.fit_odr(&x_data, &y_data, with(1e-12), None, None).unwrap();
+        let [_, a, b] = f.curve.params();
+        assert!((a - b).abs() < 1e-6, "{a} vs {b}");
+        let datasets = [(&x_data[..], &y_data[..])];
+        let f = fit_global(&redundant, &datasets, with(1e-12), [Sharing::Shared; 3]).unwrap();
+        let [_, a, b] = f.curves[0].params();
+        assert!((a - b).abs() < 1e-6, "{a} vs {b}");
+    }
+
+    #[test]
+    fn trust_region_options() {
+        let decay = |x: f64, p: [f64; 3]| p[0] * (-p[1] * x).exp() + p[2];
+        let x_data: Vec<f64> = (0..20).map(|i| 0.2 * i as f64).collect();
+        let y_data: Vec<f64> = x_data.iter().map(|&x| decay(x, [2.0, 0.7, 0.3])).collect();
+        let with = |method: Method, tr: TrustRegionOptions| Config {
+            method,
+            tr,
+            ..Default::default()
+        };
+
+        for method in [Method::TRF, Method::DogBox] {
+            let defaults = decay.fit(&x_data, &y_data, with(method, Default::default())).unwrap();
+            assert!(defaults.report.trust_radius.unwrap() > 0.0);
+
+            // without parameter scaling the steps are bounded by the radius itself
+            let tr = TrustRegionOptions {
+                initial_radius: Some(1e-3),
+                max_radius: 0.05,
+                ..Default::default()
+            };
+            let previous = RefCell::new(None);
+            let norm = |step: [f64; 3]| match method {
+                Method::DogBox => step.iter().fold(0.0, |max: f64, s| max.max(s.abs())),
+                _ => step.iter().map(|s| s * s).sum::<f64>().sqrt(),
+            };
+            let callback = |_: usize, p: [f64; 3], _: f64| {
+                if let Some(q) = previous.replace(Some(p)) {
+                    let step: [f64; 3] = std::array::from_fn(|i| p[i] - q[i]);
+                    assert!(norm(step) <= 0.05 * (1.0 + 1e-12), "{method:?}: {step:?}");
+                }
+            };
+            let limited = decay
+                .fit_with_callback(&x_data, &y_data, with(method, tr), &callback)
+                .unwrap();
+            assert!(limited.report.trust_radius.unwrap() <= 0.05);
+            assert!(limited.report.iterations > defaults.report.iterations);
+            for (p, expected) in limited.curve.params().iter().zip([2.0, 0.7, 0.3]) {
+                assert!((p - expected).abs() < 1e-6, "{method:?}: {p}");
+            }
+        }
+
+        // far from `p0 = 1`, the size of the scaled guess beats a small start that has to grow
+        let y_large: Vec<f64> = x_data.iter().map(|&x| decay(x, [500.0, 0.7, 30.0])).collect();
+        let auto = decay.fit(&x_data, &y_large, with(Method::TRF, Default::default())).unwrap();
+        let small = TrustRegionOptions {
+            initial_radius: Some(1e-3),
+            ..Default::default()
+        };
+        let small = decay.fit(&x_data, &y_large, with(Method::TRF, small)).unwrap();
+        assert!(
+            auto.report.iterations < small.report.iterations,
+            "{:?} vs {:?}",
+            auto.report,
+            small.report
+        );
+        for (p, expected) in auto.curve.params().iter().zip([500.0, 0.7, 30.0]) {
+            assert!((p / expected - 1.0).abs() < 1e-6, "{p}");
+        }
+        let lm = decay.fit(&x_data, &y_data, with(Method::LM, Default::default()));
+        assert_eq!(lm.unwrap().report.trust_radius, None);
+
+        let default = TrustRegionOptions::default();
+        let invalid = [
+            ("initial_radius", TrustRegionOptions { initial_radius: Some(0.0), ..default }),
+            ("eta_accept", TrustRegionOptions { eta_accept: 1.0, ..default }),
+            ("shrink_factor", TrustRegionOptions { shrink_factor: 1.5, ..default }),
+            ("expand_factor", TrustRegionOptions { expand_factor: 0.5, ..default }),
+        ];
+        for (field, tr) in invalid {
+            match decay.fit(&x_data, &y_data, with(Method::TRF, tr)) {
+                Err(Error::ConfigCheckFailed(range_checker::Error::CheckFailed {
+                    ident, ..
+                })) => assert_eq!(ident, field),
+                _ => panic!("{field} passed"),
+            }
+        }
+    }
+
+    #[test]
+    fn slow_fits_time_out() {
+        let x_data: Vec<f64> = (0..10).map(f64::from).collect();
+        let y_data: Vec<f64> = x_data.iter().map(|&x| target_func(x, [0.5, 2.0])).collect();
+        let slow = |x: f64, p: [f64; 2]| {
+            std::thread::sleep(Duration::from_millis(1));
+            target_func(x, p)
+        };
+
+        let cfg = Config {
+            timeout: Some(Duration::from_millis(5)),
+            ..Default::default()
+        };
+        match slow.fit(&x_data, &y_data, cfg) {
+            Err(Error::Timeout {
+                elapsed,
+                iterations,
+                last_params,
+                last_cost,
+            }) => {
+                assert!(elapsed > Duration::from_millis(5), "{elapsed:?}");
+                assert!(iterations < 5, "{iterations}");
+                assert_eq!(last_params.len(), 2);
+                assert!(last_cost.is_finite());
+            }
+            _ => panic!("the fit did not time out"),
+        }
+
+        let cfg = Config {
+            timeout: Some(Duration::from_secs(60)),
+            ..Default::default()
+        };
+        let f = target_func.fit(&x_data, &y_data, cfg).unwrap();
+        assert!((f.curve.params()[0] - 0.5).abs() < 1e-8);
+    }
+
+    #[test]
+    fn auto_method_choices() {
+        let decay = |x: f64, p: [f64; 3]| p[0] * (-p[1] * x).exp() + p[2];
+        let x_data: Vec<f64> = (0..30).map(|i| 0.2 * i as f64).collect();
+        let y_data: Vec<f64> = x_data.iter().map(|&x| decay(x, [4.0, 1.5, 0.5])).collect();
+        let cfg = Config::default();
+        assert_eq!(cfg.method, Method::Auto);
+
+        let plain = decay.fit(&x_data, &y_data, cfg).unwrap();
+        assert_eq!(plain.report.method, Method::LM);
+        let lower = [0.0, 0.0, 0.0];
+        let upper = [10.0, 5.0, f64::INFINITY];
+        let bounded = decay.fit_bounded(&x_data, &y_data, cfg, lower, upper).unwrap();
+        assert_eq!(bounded.report.method, Method::TRF);
+        for f in [&plain, &bounded] {
+            let params = f.curve.params();
+            for (p, expected) in params.iter().zip([4.0, 1.5, 0.5]) {
+                assert!((p - expected).abs() < 1e-6, "{:?}: {params:?}", f.report.method);
+            }
+        }
+
+        // undefined for any step beyond the initial offset of 1
+        let kinked = |x: f64, p: [f64; 2]| p[0] * x + (1.0 - p[1]).sqrt();
+        let y_data: Vec<f64> = x_data.iter().map(|&x| kinked(x, [2.0, 0.75])).collect();
+        let f = kinked.fit(&x_data, &y_data, cfg).unwrap();
+        assert_eq!(f.report.method, Method::NelderMead);
+        let [slope, offset] = f.curve.params();
+        assert!((slope - 2.0).abs() < 1e-6 && (offset - 0.75).abs() < 1e-6, "{slope}, {offset}");
+    }
+
+    #[test]
+    fn derivative_of_fitted_cubic() {
+        let cubic = |x: f64, p: [f64; 4]| ((p[0] * x + p[1]) * x + p[2]) * x + p[3];
+        let slope = |x: f64, p: [f64; 4]| (3.0 * p[0] * x + 2.0 * p[1]) * x + p[2];
+        let x_data: Vec<f64> = (0..30).map(|i| -3.0 + 0.2 * i as f64).collect();
+        let y_data: Vec<f64> = x_data
+            .iter()
+            .map(|&x| cubic(x, [0.5, -1.2, 2.0, 0.3]))
+            .collect();
+
+        let f = cubic.fit(&x_data, &y_data, Config::default()).unwrap().curve;
+        let x = [-2.5, -0.1, 0.0, 0.7, 3.0, 1e3, -4e6];
+        for (x, derivative) in x.iter().zip(f.derivative_slice(&x)) {
+            let expected = slope(*x, f.params());
+            assert!(
+                (derivative - expected).abs() <= 1e-6 * expected.abs(),
+                "f'({x}) = {derivative} != {expected}"
+            );
+        }
+
+        let f = f.with_derivative(slope);
+        assert_eq!(f.derivative(1.5), slope(1.5, f.params()));
+
+        let flat = |_: f64, p: [f64; 1]| p[0];
+        let f = flat.fit(&x_data, &y_data, Config::default()).unwrap().curve;
+        assert_eq!(f.derivative_slice(&[0.0, 1e300]), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn peak_of_fitted_gaussian() {
+        let gaussian =
+            |x: f64, p: [f64; 3]| p[0] * (-(x - p[1]).powi(2) / (2.0 * p[2].powi(2))).exp();
+        let x_data: Vec<f64> = (0..40).map(|i| -2.0 + 0.1 * i as f64).collect();
+        let y_data: Vec<f64> = x_data.iter().map(|&x| gaussian(x, [2.5, 0.13, 0.8])).collect();
+        let f = gaussian.fit(&x_data, &y_data, Config::default()).unwrap().curve;
+
+        let center = f.params()[1];
+        let peak = f.find_peak(-2.0, 2.0, 9);
+        assert!((peak - center).abs() < 1e-6, "{peak} vs {center}");
+        assert!(f.gradient(center, 1e-5).abs() < 1e-9);
+        // the slope of a Gaussian is steepest at one sigma from the center
+        let sigma = f.params()[2];
+        let expected = -f.eval(center + sigma) / sigma;
+        assert!((f.gradient(center + sigma, 1e-5) - expected).abs() < 1e-8);
+        // a maximum on the edge of the range
+        assert!((f.find_peak(0.5, 1.5, 5) - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn inverts_fitted_logistic() {
+        let x_data: Vec<f64> = (0..50).map(|i| 0.2 * i as f64).collect();
+        let y_data: Vec<f64> = x_data
+            .iter()
+            .enumerate()
+            .map(|(i, &x)| models::logistic(x, [8.0, 1.5, 4.0]) + 0.02 * (1.3 * i as f64).sin())
+            .collect();
+        let cfg = Config { p0: 4.0, ..Default::default() };
+        let f = models::logistic.fit(&x_data, &y_data, cfg).unwrap().curve;
+
+        for y in [0.5, 2.0, 4.0, 6.5, 7.9] {
+            let x = f.solve_for_x(y, (0.0, 10.0)).unwrap();
+            assert!((f.eval(x) - y).abs() < 1e-9, "f({x}) = {} vs {y}", f.eval(x));
+            // the bracket may be given in either order
+            assert_eq!(f.solve_for_x(y, (10.0, 0.0)).unwrap(), x);
+        }
+        assert_eq!(f.solve_for_x(f.eval(2.0), (2.0, 3.0)).unwrap(), 2.0);
+
+        // above the plateau, and crossed twice by a peak
+        assert!(matches!(
+            f.solve_for_x(9.0, (0.0, 10.0)),
+            Err(Error::RootNotBracketed { lower, upper }) if lower == 0.0 && upper == 10.0
+        ));
+        let bump = |x: f64, p: [f64; 1]| p[0] * (-x * x).exp();
+        let peak = bump.fit(&[-1.0, 0.0, 1.0], &[1.0, 2.7, 1.0], Config::default()).unwrap().curve;
+        assert!(matches!(
+            peak.solve_for_x(1.0, (-3.0, 3.0)),
+            Err(Error::RootNotBracketed { .. })
+        ));
+        assert!(matches!(
+            f.solve_for_x_with(4.0, (0.0, 10.0), 1e-12, 3),
+            Err(Error::RootNotConverged { max_iterations: 3 })
+        ));
+    }
+
+    #[test]
+    fn fits_batches_of_datasets() {
+        let line = |x: f64, p: [f64; 2]| p[0] * x + p[1];
+        let x_data: Vec<f64> = (0..10).map(|i| i as f64).collect();
+        let params = [[2.0, 1.0], [-0.5, 3.0], [10.0, -4.0]];
+        let y_data: Vec<Vec<f64>> = params
+            .iter()
+            .map(|&p| {
+                let noisy = x_data.iter().enumerate();
+                noisy.map(|(i, &x)| line(x, p) + 0.01 * (1.3 * i as f64).sin()).collect()
+            })
+            .collect();
+        let mut datasets: Vec<(&[f64], &[f64])> =
+            y_data.iter().map(|y| (&x_data[..], &y[..])).collect();
+        // a broken data set in between leaves the others alone
+        datasets.insert(1, (&x_data[..9], &y_data[0]));
+
+        let fits = line.fit_batch(&datasets, Config::default());
+        assert_eq!(fits.len(), 4);
+        assert!(matches!(
+            fits[1],
+            Err(Error::UnmatchedLength {
+                x_data_len: 9,
+                y_data_len: 10
+            })
+        ));
+        for (fit, expected) in [&fits[0], &fits[2], &fits[3]].into_iter().zip(params) {
+            let fit = fit.as_ref().unwrap();
+            for (p, e) in fit.curve.params().iter().zip(expected) {
+                assert!((p - e).abs() < 0.02, "{:?} vs {expected:?}", fit.curve.params());
+            }
+        }
+        #[cfg(feature = "rayon")]
+        {
+            let parallel = line.par_fit_batch(&datasets, Config::default());
+            assert!(parallel[1].is_err());
+            for i in [0, 2, 3] {
+                assert!(parallel[i].as_ref().unwrap() == fits[i].as_ref().unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn sorting_keeps_points_paired() {
+        let line = |x: f64, p: [f64; 2]| p[0] * x + p[1];
+        let x_sorted: Vec<f64> = (0..12).map(|i| 0.5 * i as f64).collect();
+        let y_sorted: Vec<f64> = x_sorted
+            .iter()
+            .enumerate()
+            .map(|(i, &x)| 2.0 * x - 1.0 + 0.1 * (2.3 * i as f64).sin())
+            .collect();
+        let sigma_sorted: Vec<f64> = (0..12).map(|i| 0.1 + 0.05 * i as f64).collect();
+        // the same points, shuffled
+        let order = [7, 2, 11, 0, 5, 9, 1, 10, 3, 8, 6, 4];
+        let shuffle = |data: &[f64]| -> Vec<f64> { order.iter().map(|&i| data[i]).collect() };
+        let (x_data, y_data) = (shuffle(&x_sorted), shuffle(&y_sorted));
+        let sigma = shuffle(&sigma_sorted);
+
+        let sorting = Config { sort_input: true, ..Default::default() };
+        let cfg = Config::default();
+        let sorted = line.fit_odr(&x_sorted, &y_sorted, cfg, Some(&sigma_sorted), None);
+        let shuffled = line.fit_odr(&x_data, &y_data, sorting, Some(&sigma), None);
+        let (shuffled, sorted) = (shuffled.unwrap(), sorted.unwrap());
+        assert_eq!(shuffled.curve.params(), sorted.curve.params());
+        assert_eq!(shuffled.x_adjusted, sorted.x_adjusted);
+        let unsorted = line.fit_odr(&x_data, &y_data, cfg, Some(&sigma), None).unwrap();
+        assert!(unsorted.x_adjusted.unwrap().windows(2).any(|pair| pair[0] > pair[1]));
+
+        // non-finite points and sigmas are reported at their index as given
+        let mut sigma = sigma;
+        sigma[4] = 0.0;
+        assert!(matches!(
+            line.fit_odr(&x_data, &y_data, sorting, Some(&sigma), None),
+            Err(Error::InvalidSigma { index: 4, .. })
+        ));
+        let mut y_data = y_data;
+        y_data[2] = f64::NAN;
+        assert!(matches!(
+            line.fit_odr(&x_data, &y_data, sorting, None, None),
+            Err(Error::NonFiniteData { index: 2 })
+        ));
+
+        let checking = Config { require_sorted: true, ..Default::default() };
+        assert!(line.fit(&x_sorted, &y_sorted, checking).is_ok());
+        assert!(matches!(
+            line.fit(&x_data, &y_data, checking),
+            Err(Error::UnsortedInput { index: 1 })
+        ));
+        // a non-finite x in between is skipped
+        let mut x_gap = x_sorted.clone();
+        x_gap[5] = f64::NAN;
+        let dropping = Config { non_finite: NonFinitePolicy::Drop, ..checking };
+        assert_eq!(line.fit(&x_gap, &y_sorted, dropping).unwrap().n_data, 11);
+    }
+
+    #[test]
+    fn log_space_fits_balance_the_decades() {
+        let growth = |x: f64, p: [f64; 2]| p[0] * (p[1] * x).exp();
+        // three decades, with errors of a few percent of each value
+        let x_data: Vec<f64> = (0..36).map(|i| 0.2 * i as f64).collect();
+        let y_data: Vec<f64> = x_data
+            .iter()
+            .enumerate()
+            .map(|(i, &x)| growth(x, [0.01, 1.0]) * (1.0 + 0.05 * (2.1 * i as f64).sin()))
+            .collect();
+        let relative_error = |f: &FitResult<2, _>| -> f64 {
+            let small = x_data.iter().zip(&y_data).take(10);
+            small.map(|(&x, y)| (f.curve.eval(x) / y - 1.0).abs()).sum::<f64>() / 10.0
+        };
+
+        let cfg = Config { p0_strategy: P0Strategy::DataScaled, ..Default::default() };
+        let linear = growth.fit(&x_data, &y_data, cfg).unwrap();
+        let log = growth.fit_log_space(&x_data, &y_data, cfg).unwrap();
+        let (linear_error, log_error) = (relative_error(&linear), relative_error(&log));
+        assert!(log_error < 0.4 * linear_error, "{log_error} vs {linear_error}");
+        for (p, expected) in log.curve.params().iter().zip([0.01, 1.0]) {
+            assert!((p / expected - 1.0).abs() < 0.05, "{:?}", log.curve.params());
+        }
+        // the residuals are those of the logarithms
+        let ssr: f64 = x_data
+            .iter()
+            .zip(&y_data)
+            .map(|(&x, y)| (log.curve.eval(x).ln() - y.ln()).powi(2))
+            .sum();
+        assert!((log.ssr - ssr).abs() < 1e-12, "{} vs {ssr}", log.ssr);
+
+        let mut y_data = y_data;
+        y_data[4] = 0.0;
+        assert!(matches!(
+            growth.fit_log_space(&x_data, &y_data, cfg),
+            Err(Error::NonPositiveData { index: 4, y }) if y == 0.0
+        ));
+    }
+
+    #[test]
+    fn weight_functions_scale_the_residuals() {
+        let x_data: Vec<f64> = (1..=12).map(f64::from).collect();
+        let mut y_data: Vec<f64> = x_data
+            .iter()
+            .map(|&x| 1.5 * x + 4.0 + 0.3 * (1.7 * x).sin())
+            .collect();
+        let cfg = Config::default();
+        let plain = target_func.fit(&x_data, &y_data, cfg).unwrap();
+
+        // a constant weight scales the sum of squares but not the estimate
+        let doubled = target_func.fit_weight_fn(&x_data, &y_data, cfg, |_| 2.0).unwrap();
+        for (a, b) in doubled.curve.params().iter().zip(plain.curve.params()) {
+            assert!((a - b).abs() < 1e-6, "{a} vs {b}");
+        }
+        assert!((doubled.ssr - 2.0 * plain.ssr).abs() < 1e-9 * plain.ssr);
+
+        // a zero weight leaves the outlier out
+        y_data[11] = 100.0;
+        let weight = |x: f64| if x > 11.5 { 0.0 } else { 1.0 / x };
+        let weighted = target_func.fit_weight_fn(&x_data, &y_data, cfg, weight).unwrap();
+        let ssr: f64 = x_data
+            .iter()
+            .zip(&y_data)
+            .map(|(&x, y)| weight(x) * (weighted.curve.eval(x) - y).powi(2))
+            .sum();
+        assert!((weighted.ssr - ssr).abs() < 1e-9, "{} vs {ssr}", weighted.ssr);
+        let [slope, intercept] = weighted.curve.params();
+        assert!((slope - 1.5).abs() < 0.1, "{slope}");
+        assert!((intercept - 4.0).abs() < 0.5, "{intercept}");
+
+        assert!(matches!(
+            target_func.fit_weight_fn(&x_data, &y_data, cfg, |x| 5.0 - x),
+            Err(Error::InvalidWeight { index: 5, value }) if value == -1.0
+        ));
+    }
+
+    #[test]
+    fn masked_points_are_left_out() {
+        let decay = |x: f64, p: [f64; 3]| p[0] * (-p[1] * x).exp() + p[2];
+        let x_data: Vec<f64> = (0..40).map(|i| 0.1 * i as f64).collect();
+        let mut y_data: Vec<f64> = x_data
+            .iter()
+            .enumerate()
+            .map(|(i, &x)| decay(x, [4.0, 1.5, 0.5]) + 0.02 * (1.9 * i as f64).sin())
+            .collect();
+        // a spike on points 10..15, and a dead channel
+        for y in &mut y_data[10..15] {
+            *y += 3.0;
+        }
+        y_data[30] = f64::NAN;
+        let mask: Vec<bool> = (0..40).map(|i| !(10..15).contains(&i) && i != 30).collect();
+
+        let masked = decay.fit_masked(&x_data, &y_data, Config::default(), &mask).unwrap();
+        let (x_trimmed, y_trimmed): (Vec<f64>, Vec<f64>) =
+            (0..40).filter(|&i| mask[i]).map(|i| (x_data[i], y_data[i])).unzip();
+        let trimmed = decay.fit(&x_trimmed, &y_trimmed, Config::default()).unwrap();
+        assert_eq!(masked.curve.params(), trimmed.curve.params());
+        assert_eq!(masked.covariance, trimmed.covariance);
+        assert_eq!(masked.n_data, 34);
+        for (p, expected) in masked.curve.params().iter().zip([4.0, 1.5, 0.5]) {
+            assert!((p - expected).abs() < 0.05, "{:?}", masked.curve.params());
+        }
+
+        // a non-finite point is reported at its index among all points
+        let mut mask = mask;
+        mask[30] = true;
+        assert!(matches!(
+            decay.fit_masked(&x_data, &y_data, Config::default(), &mask),
+            Err(Error::NonFiniteData { index: 30 })
+        ));
+        // so is a point out of order, while the order of excluded points does not matter
+        let mut x_shuffled = x_data.clone();
+        x_shuffled[12] = -1.0;
+        x_shuffled[20] = 0.05;
+        let sorted = Config { require_sorted: true, ..Default::default() };
+        assert!(matches!(
+            decay.fit_masked(&x_shuffled, &y_data, sorted, &mask),
+            Err(Error::UnsortedInput { index: 20 })
+        ));
+        assert!(matches!(
+            decay.fit_masked(&x_data, &y_data, Config::default(), &mask[..39]),
+            Err(Error::UnmatchedMaskLength {
+                data_len: 40,
+                mask_len: 39
+            })
+        ));
+        assert!(matches!(
+            decay.fit_masked(&x_data, &y_data, Config::default(), &[false; 40]),
+            Err(Error::InsufficientData { n_data: 0, n_params: 3 })
+        ));
+    }
+
+    #[test]
+    fn partial_fit_holds_fixed_parameters() {
+        let decay = |x: f64, p: [f64; 3]| p[0] * (-p[1] * x).exp() + p[2];
+        let x_data: Vec<f64> = (0..30).map(|i| 0.2 * i as f64).collect();
+        let y_data: Vec<f64> = x_data
+            .iter()
+            .enumerate()
+            .map(|(i, &x)| decay(x, [4.0, 1.5, 0.5]) + 0.03 * (1.3 * i as f64).sin())
+            .collect();
+        // the same model with the offset written in
+        let reduced = |x: f64, p: [f64; 2]| decay(x, [p[0], p[1], 0.4]);
+
+        for method in [Method::LM, Method::TRF, Method::DogBox, Method::NelderMead] {
+            let cfg = Config { method, ..Default::default() };
+            let expected = reduced.fit(&x_data, &y_data, cfg).unwrap();
+            let f = decay.fit_partial(&x_data, &y_data, cfg, [None, None, Some(0.4)]).unwrap();
+            let [amplitude, rate, offset] = f.curve.params();
+            assert_eq!(offset, 0.4, "{method:?}");
+            for (p, expected) in [amplitude, rate].iter().zip(expected.curve.params()) {
+                assert!((p - expected).abs() < 1e-6, "{method:?}: {p} vs {expected}");
+            }
+            assert_eq!(f.dof(), expected.dof());
+            assert_eq!(f.std_errors()[2], 0.0);
+            for (s, expected) in f.std_errors().iter().zip(expected.std_errors()) {
+                assert!((s - expected).abs() < 1e-4 * expected, "{method:?}: {s} vs {expected}");
+            }
+        }
+
+        // only the free parameter is differentiated
+        let calls = std::cell::Cell::new(0);
+        let counted = |x: f64, p: [f64; 3]| {
+            calls.set(calls.get() + 1);
+            decay(x, p)
+        };
+        let cfg = Config { method: Method::LM, ..Default::default() };
+        let f = counted.fit_partial(&x_data, &y_data, cfg, [Some(4.0), None, Some(0.5)]).unwrap();
+        assert_eq!(f.curve.params()[0], 4.0);
+        assert!((f.curve.params()[1] - 1.5).abs() < 0.01);
+        let (iterations, n_fev) = (f.report.iterations, f.report.n_fev);
+        assert!(n_fev <= 2 * (iterations + 1), "{:?}", f.report);
+        assert_eq!(calls.get(), n_fev * x_data.len());
+    }
+
+    #[test]
+    fn integrates_fitted_curves() {
+        let x_data: Vec<f64> = (0..60).map(|i| -3.0 + 0.1 * i as f64).collect();
+        let y_data: Vec<f64> = x_data
+            .iter()
+            .map(|&x| models::gaussian(x, [2.5, 0.13, 0.8]))
+            .collect();
+        let f = models::gaussian.fit(&x_data, &y_data, Config::default()).unwrap().curve;
+        let [amplitude, center, sigma] = f.params();
+        let area = amplitude * sigma * (2.0 * std::f64::consts::PI).sqrt();
+        // the tails beyond 6 sigma hold a fraction 2e-9 of the area
+        let integral = f.integrate(center - 6.0 * sigma, center + 6.0 * sigma);
+        assert!((integral / area - 1.0).abs() < 1e-8, "{integral} vs {area}");
+        let (integral, error) = f.integrate_with_error(-20.0, 20.0, 1e-12, 100);
+        assert!((integral / area - 1.0).abs() < 1e-12, "{integral} vs {area}");
+        assert!(error < 1e-10 * area);
+        let integral = f.integrate_simpson(center - 6.0 * sigma, center + 6.0 * sigma, 200);
+        assert!((integral / area - 1.0).abs() < 1e-8, "{integral} vs {area}");
+        let coarse = f.integrate_simpson(center - 6.0 * sigma, center + 6.0 * sigma, 12);
+        assert!((coarse / area - 1.0).abs() > 1e-4, "{coarse} vs {area}");
+
+        let f = target_func
+            .fit(&[0.0, 1.0, 2.0], &[1.0, 2.9, 5.1], Config::default())
+            .unwrap()
+            .curve;
+        let trapezoid = 0.5 * (4.5 - -1.5) * (f.eval(-1.5) + f.eval(4.5));
+        let integral = f.integrate(-1.5, 4.5);
+        assert!((integral - trapezoid).abs() < 1e-14 * trapezoid, "{integral} vs {trapezoid}");
+        // odd counts are rounded up to even ones, on which Simpson's rule is exact for a line
+        let simpson = f.integrate_simpson(4.5, -1.5, 3);
+        assert!((simpson + trapezoid).abs() < 1e-14 * trapezoid, "{simpson} vs {trapezoid}");
+        assert_eq!(f.integrate(4.5, -1.5), -integral);
+        assert_eq!(f.integrate(2.0, 2.0), 0.0);
+    }
+
+    #[test]
+    fn cost_of_parameters() {
+        let x_data = [0.0, 1.0, 2.0, 3.0, 4.0];
+        let y_data = [1.1, 2.9, 5.2, 6.8, 9.1];
+        let f = target_func.fit(&x_data, &y_data, Config::default()).unwrap();
+        let cost = f.curve.cost(&x_data, &y_data).unwrap();
+        assert!((2.0 * cost - f.ssr).abs() < 1e-12, "{cost} vs {}", f.ssr);
+
+        let mut guess = f.curve.clone();
+        guess.params = [Config::default().p0; 2];
+        let guess_cost = guess.cost(&x_data, &y_data).unwrap();
+        assert!(cost < guess_cost, "{cost} vs {guess_cost}");
+        let squares: f64 = x_data.iter().zip(&y_data).map(|(&x, y)| (x + 1.0 - y).powi(2)).sum();
+        assert!((guess_cost - 0.5 * squares).abs() < 1e-12);
+
+        assert!(matches!(
+            f.curve.cost(&x_data, &y_data[..4]),
+            Err(Error::UnmatchedLength {
+                x_data_len: 5,
+                y_data_len: 4
+            })
+        ));
+    }
+
+    #[test]
+    fn curve_from_known_parameters() {
+        let curve = Curve::from_params(target_func, [2.0, -1.0]);
+        assert_eq!(curve.params(), [2.0, -1.0]);
+        for x in [-3.0, 0.0, 0.5, 1e6] {
+            assert_eq!(curve.eval(x), target_func(x, [2.0, -1.0]));
+            assert!((curve.derivative(x) - 2.0).abs() < 1e-6);
+        }
+        assert!((curve.integrate(0.0, 2.0) - 2.0).abs() < 1e-10);
+        assert_eq!(curve.predict_with_errors(&[1.0]), vec![(1.0, 0.0)]);
+        assert_eq!(curve.at_bounds(), [false; 2]);
+        let named = curve.with_names(["slope", "intercept"]);
+        assert_eq!(named.parameter_names(), Some(["slope", "intercept"]));
+    }
+
+    #[test]
+    fn chi_squared_test_of_the_fit() {
+        let x_data: Vec<f64> = (0..20).map(|i| 0.25 * i as f64).collect();
+        let sigma = vec![0.3; 20];
+        let mut rng = StdRng::seed_from_u64(8);
+
+        // with the right model and sigma the p-value is uniform on (0, 1)
+        let p_values: Vec<f64> = (0..200)
+            .map(|_| {
+                let noise = Array::random_using(20, Normal::new(0.0, 0.3).unwrap(), &mut rng);
+                let y_data: Vec<f64> =
+                    x_data.iter().zip(&noise).map(|(&x, e)| 2.0 * x - 1.0 + e).collect();
+                let f = target_func.fit(&x_data, &y_data, Config::default()).unwrap();
+                f.curve.chi2_pvalue(&x_data, &y_data, &sigma).unwrap()
+            })
+            .collect();
+        let mean = p_values.iter().sum::<f64>() / 200.0;
+        assert!((mean - 0.5).abs() < 0.06, "{mean}");
+        let below = p_values.iter().filter(|&&p| p < 0.05).count();
+        assert!(below < 25, "{below}");
+
+        // a line through a parabola
+        let y_data: Vec<f64> = x_data.iter().map(|&x| x * x - 2.0 * x).collect();
+        let f = target_func.fit(&x_data, &y_data, Config::default()).unwrap();
+        let p = f.curve.chi2_pvalue(&x_data, &y_data, &sigma).unwrap();
+        assert!(p < 1e-10, "{p}");
+
+        assert!(matches!(
+            f.curve.chi2_pvalue(&x_data, &y_data, &sigma[1..]),
+            Err(Error::UnmatchedSigmaLength { data_len: 20, sigma_len: 19 })
+        ));
+        assert!(matches!(
+            f.curve.chi2_pvalue(&x_data[..2], &y_data[..2], &sigma[..2]),
+            Err(Error::InsufficientData { n_data: 2, n_params: 2 })
+        ));
+        let mut sigma = sigma;
+        sigma[3] = 0.0;
+        assert!(matches!(
+            f.curve.chi2_pvalue(&x_data, &y_data, &sigma),
+            Err(Error::InvalidSigma { index: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn prediction_interval_of_a_line() {
+        let x_data: Vec<f64> = (0..12).map(|i| 0.5 * i as f64).collect();
+        let y_data: Vec<f64> = x_data
+            .iter()
+            .enumerate()
+            .map(|(i, &x)| target_func(x, [1.7, -0.4]) + 0.3 * (2.1 * i as f64).sin())
+            .collect();
+        let fit = target_func.fit(&x_data, &y_data, Config::default()).unwrap();
+        let s = (fit.ssr / fit.dof() as f64).sqrt();
+
+        // the textbook interval y ± t s sqrt(1 + 1/n + (x - mean)^2 / Sxx)
+        let n = x_data.len() as f64;
+        let mean = x_data.iter().sum::<f64>() / n;
+        let sxx: f64 = x_data.iter().map(|x| (x - mean).powi(2)).sum();
+        let t = stats::student_t_quantile(0.975, n - 2.0);
+        for x in [-1.0, 0.0, 2.75, 4.0, 9.0] {
+            let half_width = t * s * (1.0 + 1.0 / n + (x - mean).powi(2) / sxx).sqrt();
+            let (lower, upper) = fit.predict_interval(x, s, 0.05);
+            let y = fit.curve.eval(x);
+            assert!((lower - (y - half_width)).abs() < 1e-8, "{x}: {lower}");
+            assert!((upper - (y + half_width)).abs() < 1e-8, "{x}: {upper}");
+        }
+        // without residual scatter it is the interval of the mean response, narrower
+        let (lower, upper) = fit.predict_interval(2.75, 0.0, 0.05);
+        let half_width = t * s * (1.0 / n + (2.75 - mean).powi(2) / sxx).sqrt();
+        assert!((upper - lower - 2.0 * half_width).abs() < 1e-8);
+
+        let xs = [-1.0, 0.0, 2.75, 4.0, 9.0];
+        let intervals = fit.prediction_intervals(&xs, 0.95).unwrap();
+        for (&x, &(lower, upper)) in xs.iter().zip(&intervals) {
+            assert_eq!((lower, upper), fit.predict_interval(x, s, 0.05));
+            let (mean_lower, mean_upper) = fit.predict_interval(x, 0.0, 0.05);
+            assert!(lower < mean_lower && mean_upper < upper, "{x}");
+        }
+        for confidence in [0.0, 1.0, f64::NAN] {
+            assert!(matches!(
+                fit.prediction_intervals(&xs, confidence),
+                Err(Error::InvalidConfidence { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn error_band_widens_away_from_the_centroid() {
+        let x_data: Vec<f64> = (0..21).map(|i| 0.25 * i as f64).collect();
+        let y_data: Vec<f64> = x_data
+            .iter()
+            .enumerate()
+            .map(|(i, &x)| target_func(x, [0.8, 2.0]) + 0.2 * (1.9 * i as f64).sin())
+            .collect();
+        let fit = target_func.fit(&x_data, &y_data, Config::default()).unwrap();
+        let (f, covariance) = (&fit.curve, fit.covariance);
+
+        // the data are centred on x = 2.5
+        let xs = [-2.0, 0.0, 1.5, 2.5, 3.5, 5.0, 7.0];
+        let band = f.predict_with_errors(&xs);
+        for (&x, &(y, sigma)) in xs.iter().zip(&band) {
+            assert_eq!(y, f.eval(x));
+            // for a line, var(a x + b) = x^2 var(a) + 2 x cov(a, b) + var(b)
+            let expected = x * x * covariance[0][0] + 2.0 * x * covariance[0][1] + covariance[1][1];
+            assert!((sigma * sigma - expected).abs() < 1e-8 * expected, "{x}: {sigma}");
+        }
+        let sigmas: Vec<f64> = band.iter().map(|&(_, sigma)| sigma).collect();
+        assert!(sigmas[..4].windows(2).all(|w| w[0] > w[1]), "{sigmas:?}");
+        assert!(sigmas[3..].windows(2).all(|w| w[0] < w[1]), "{sigmas:?}");
+        assert!((sigmas[2] - sigmas[4]).abs() < 1e-8);
+    }
+
+    #[test]
+    fn polynomial_approximation() {
+        let cubic = |x: f64, p: [f64; 4]| ((p[0] * x + p[1]) * x + p[2]) * x + p[3];
+        let x_data: Vec<f64> = (0..30).map(|i| -3.0 + 0.2 * i as f64).collect();
+        let y_data: Vec<f64> = x_data
+            .iter()
+            .map(|&x| cubic(x, [0.5, -1.2, 2.0, 0.3]))
+            .collect();
+        let f = cubic.fit(&x_data, &y_data, Config::default()).unwrap().curve;
+        let [a, b, c, d] = f.params();
+        // a polynomial of the same degree reproduces the curve
+        let coefficients = f.to_polynomial_approx(-3.0, 3.0, 3, 20);
+        for (coefficient, expected) in coefficients.iter().zip([d, c, b, a]) {
+            assert!((coefficient - expected).abs() < 1e-9, "{coefficients:?}");
+        }
+
+        let decay = |x: f64, p: [f64; 2]| p[0] * (-p[1] * x).exp();
+        let y_data: Vec<f64> = x_data.iter().map(|&x| decay(x, [2.0, 0.4])).collect();
+        let f = decay.fit(&x_data, &y_data, Config::default()).unwrap().curve;
+        let coefficients = f.to_polynomial_approx(0.0, 2.0, 8, 50);
+        assert_eq!(coefficients.len(), 9);
+        for x in [0.0, 0.33, 1.0, 1.7, 2.0] {
+            let approx = coefficients.iter().rev().fold(0.0, |sum, c| sum * x + c);
+            assert!((approx - f.eval(x)).abs() < 1e-8, "{x}: {approx} vs {}", f.eval(x));
+        }
+    }
+
+    #[test]
+    fn refit_follows_drifting_frames() {
+        let decay = |x: f64, p: [f64; 3]| p[0] * (-p[1] * x).exp() + p[2];
+        let x_data: Vec<f64> = (0..30).map(|i| 0.2 * i as f64).collect();
+        let frame = |k: f64| -> Vec<f64> {
+            let truth = [4.0 + 0.05 * k, 1.5 - 0.02 * k, 0.5 + 0.01 * k];
+            x_data.iter().map(|&x| decay(x, truth)).collect()
+        };
+
+        let mut curve = decay
+            .fit(&x_data, &frame(0.0), Config::default())
+            .unwrap()
+            .curve
+            .with_names(["amplitude", "rate", "offset"]);
+        for k in 1..5 {
+            let y_data = frame(k as f64);
+            let cold = decay.fit(&x_data, &y_data, Config::default()).unwrap();
+            let warm = curve.refit(&x_data, &y_data, Config::default()).unwrap();
+            assert!(
+                2 * warm.report.iterations <= cold.report.iterations,
+                "frame {k}: {:?} vs {:?}",
+                warm.report,
+                cold.report
+            );
+            for (warm, cold) in warm.curve.params().iter().zip(cold.curve.params()) {
+                assert!((warm - cold).abs() < 1e-6);
+            }
+            assert_eq!(warm.curve.parameter_names(), curve.parameter_names());
+            curve = warm.curve;
+        }
+    }
+
+    #[test]
+    fn results_cross_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        type Line = fn(f64, [f64; 2]) -> f64;
+        assert_send_sync::<Curve<2, Line>>();
+        assert_send_sync::<FitResult<2, Line>>();
+        assert_send_sync::<MultiStartResult<2, Line>>();
+        assert_send_sync::<GlobalFitResult<2, Line>>();
+        assert_send_sync::<Error>();
+
+        fn line(x: f64, p: [f64; 2]) -> f64 {
+            p[0] * x + p[1]
+        }
+        let worker = std::thread::spawn(|| {
+            let fit = (line as Line).fit(&[0.0, 1.0, 2.0], &[1.0, 3.0, 5.0], Config::default());
+            fit.map(|fit| fit.curve)
+        });
+        let curve = worker.join().unwrap().unwrap();
+        assert!((curve.eval(3.0) - 7.0).abs() < 1e-9, "{}", curve.eval(3.0));
+    }
+
+    #[test]
+    fn fits_models_owning_their_data() {
+        // a reference shape tabulated on a unit grid, interpolated linearly and fitted by
+        // scale and offset; the closure owns the table, so it is Clone but not Copy
+        let table: Vec<f64> = (0..=10).map(|i| (0.3 * i as f64).sin()).collect();
+        let model = move |x: f64, p: [f64; 2]| {
+            let i = (x.floor() as usize).min(table.len() - 2);
+            let t = x - i as f64;
+            p[0] * (table[i] * (1.0 - t) + table[i + 1] * t) + p[1]
+        };
+        let x_data: Vec<f64> = (0..40).map(|i| 0.25 * i as f64).collect();
+        let y_data: Vec<f64> = x_data.iter().map(|&x| model(x, [2.5, -0.7])).collect();
+
+        let f = model.fit(&x_data, &y_data, Config::default()).unwrap();
+        for (p, expected) in f.curve.params().iter().zip([2.5, -0.7]) {
+            assert!((p - expected).abs() < 1e-9, "{p}");
+        }
+        let y_shifted: Vec<f64> = y_data.iter().map(|y| y + 0.1).collect();
+        let refitted = f.curve.refit(&x_data, &y_shifted, Config::default()).unwrap();
+        assert!((refitted.curve.params()[1] + 0.6).abs() < 1e-9);
+        assert!((f.curve.eval(3.0) - model(3.0, [2.5, -0.7])).abs() < 1e-9);
+    }
+
+    #[test]
+    fn linear_confidence_intervals() {
+        let x_data: Vec<f64> = (0..10).map(|i| i as f64).collect();
+        let noise = [0.3, -0.2, 0.1, -0.4, 0.25, 0.05, -0.15, 0.35, -0.3, 0.0];
+        let y_data: Vec<f64> = x_data
+            .iter()
+            .zip(noise)
+            .map(|(&x, e)| target_func(x, [2.0, 1.0]) + e)
+            .collect();
+
+        // closed-form ordinary least squares
+        let n = x_data.len() as f64;
+        let x_mean = x_data.iter().sum::<f64>() / n;
+        let y_mean = y_data.iter().sum::<f64>() / n;
+        let sxx: f64 = x_data.iter().map(|x| (x - x_mean).powi(2)).sum();
+        let sxy: f64 = x_data
+            .iter()
+            .zip(&y_data)
+            .map(|(x, y)| (x - x_mean) * (y - y_mean))
+            .sum();
+        let slope = sxy / sxx;
+        let intercept = y_mean - slope * x_mean;
+        let ssr: f64 = x_data
+            .iter()
+            .zip(&y_data)
+            .map(|(&x, y)| (y - target_func(x, [slope, intercept])).powi(2))
+            .sum();
+        let variance = ssr / (n - 2.0);
+        let se_slope = (variance / sxx).sqrt();
+        let se_intercept = (variance * (1.0 / n + x_mean * x_mean / sxx)).sqrt();
+        // t(0.975, 8)
+        let t = 2.306_004_135_204_166;
+
+        let f = target_func.fit(&x_data, &y_data, Config::default()).unwrap();
+        assert_eq!(f.dof(), 8);
+        let [slope_ci, intercept_ci] = f.confidence_intervals(0.05);
+        for (ci, expected) in [
+            (slope_ci, [slope - t * se_slope, slope + t * se_slope]),
+            (intercept_ci, [intercept - t * se_intercept, intercept + t * se_intercept]),
+        ] {
+            assert!((ci[0] - expected[0]).abs() < 1e-6, "{ci:?} != {expected:?}");
+            assert!((ci[1] - expected[1]).abs() < 1e-6, "{ci:?} != {expected:?}");
+        }
+    }
+
+    #[test]
+    fn bic_rejects_useless_parameter() {
+        let quadratic = |x: f64, p: [f64; 3]| p[0] * x + p[1] + p[2] * x * x;
+        let xdata = Array::linspace(0., 4., 2000);
+        let normal = Normal::new(0.0, 0.5).unwrap();
+        let y_noise = Array::random_using(xdata.dim(), normal, &mut StdRng::seed_from_u64(3));
+        let y_data = xdata.map(|&x| target_func(x, [2.5, 1.3])) + y_noise;
+
+        let linear = target_func.fit_array(&xdata, &y_data, Config::default()).unwrap();
+        let overfit = quadratic.fit_array(&xdata, &y_data, Config::default()).unwrap();
+        // the extra parameter can only lower the residuals
+        assert!(overfit.ssr <= linear.ssr);
+        assert!(overfit.bic() > linear.bic());
+        assert!((linear.aic() - linear.bic() - (4.0 - 2.0 * 2000f64.ln())).abs() < 1e-9);
+    }
+
+    #[test]
+    fn correlations_of_a_line() {
+        let line = |x: f64, p: [f64; 2]| p[0] * x + p[1];
+        let x_data: Vec<f64> = (0..20).map(|i| 1.0 + 0.5 * i as f64).collect();
+        let y_data: Vec<f64> = x_data
+            .iter()
+            .enumerate()
+            .map(|(i, &x)| line(x, [2.0, -1.0]) + 0.1 * (1.9 * i as f64).sin())
+            .collect();
+        let f = line.fit(&x_data, &y_data, Config::default()).unwrap();
+
+        // slope and intercept correlate as `-mean(x) / sqrt(mean(x^2))`
+        let m = x_data.len() as f64;
+        let mean = x_data.iter().sum::<f64>() / m;
+        let mean_square = x_data.iter().map(|x| x * x).sum::<f64>() / m;
+        let expected = -mean / mean_square.sqrt();
+        let correlation = f.parameter_correlation_matrix();
+        assert!((correlation[0][1] - expected).abs() < 1e-9, "{correlation:?} vs {expected}");
+        assert_eq!(correlation[0][1], correlation[1][0]);
+        assert!((correlation[0][0] - 1.0).abs() < 1e-12);
+        assert!((correlation[1][1] - 1.0).abs() < 1e-12);
+
+        // a fixed parameter has no variance to correlate
+        let fixed = line.fit_partial(&x_data, &y_data, Config::default(), [None, Some(-1.0)]);
+        let correlation = fixed.unwrap().parameter_correlation_matrix();
+        assert!((correlation[0][0] - 1.0).abs() < 1e-12);
+        assert!(correlation[0][1].is_nan() && correlation[1][0].is_nan());
+        assert!(correlation[1][1].is_nan());
+    }
+
+    #[test]
+    fn aic_prefers_the_gaussian_of_the_data() {
+        let gaussian =
+            |x: f64, p: [f64; 3]| p[0] * (-(x - p[1]).powi(2) / (2.0 * p[2] * p[2])).exp();
+        // the same peak with its width held at 1
+        let unit_width = |x: f64, p: [f64; 2]| gaussian(x, [p[0], p[1], 1.0]);
+        let xdata = Array::linspace(-5., 5., 200);
+        let normal = Normal::new(0.0, 0.05).unwrap();
+        let y_noise = Array::random_using(xdata.dim(), normal, &mut StdRng::seed_from_u64(8));
+        let y_data = xdata.map(|&x| gaussian(x, [2.0, 0.4, 1.5])) + y_noise;
+
+        let cfg = Config { p0: 0.5, ..Default::default() };
+        let full = gaussian.fit_array(&xdata, &y_data, cfg).unwrap();
+        let reduced = unit_width.fit_array(&xdata, &y_data, cfg).unwrap();
+        assert!(full.aic() < reduced.aic(), "{} vs {}", full.aic(), reduced.aic());
+        assert!(full.bic() < reduced.bic());
+        let n = 200.0;
+        assert!((full.aic() - (n * (full.ssr / n).ln() + 6.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn fits_order_by_ssr() {
+        type Model = fn(f64, [f64; 2]) -> f64;
+        let models: [Model; 3] = [
+            |x, p| p[0] * x * x + p[1],
+            |x, p| p[0] * (p[1] * x).exp(),
+            |x, p| p[0] * x + p[1],
+        ];
+        let xdata = Array::linspace(0., 4., 50);
+        let normal = Normal::new(0.0, 0.2).unwrap();
+        let y_noise = Array::random_using(xdata.dim(), normal, &mut StdRng::seed_from_u64(7));
+        let y_data = xdata.map(|&x| target_func(x, [2.5, 1.3])) + y_noise;
+
+        let mut fits: Vec<FitResult<2, Model>> = models
+            .iter()
+            .map(|model| model.fit_array(&xdata, &y_data, Config::default()).unwrap())
+            .collect();
+        let best = fits.iter().min().unwrap();
+        assert!(fits.iter().all(|f| best.ssr <= f.ssr));
+        // the straight line generated the data
+        assert!(std::ptr::eq(best, &fits[2]));
+
+        fits[0].ssr = f64::NAN;
+        fits[1].ssr = -f64::NAN;
+        fits.sort();
+        assert!(fits[0].ssr.is_finite() && fits[1].ssr.is_nan() && fits[2].ssr.is_nan());
+        assert!(fits[0] < fits[1]);
+    }
+
+    #[test]
+    fn akaike_weights() {
+        let x_data: Vec<f64> = (0..30).map(|i| 0.1 * i as f64).collect();
+        let y_data: Vec<f64> = x_data
+            .iter()
+            .map(|&x| 0.5 * x * x - x + 2.0 + 0.05 * (7.3 * x).sin())
+            .collect();
+        let cfg = Config::default();
+        let quadratic = |x: f64, p: [f64; 3]| p[0] * x * x + p[1] * x + p[2];
+        let cubic = |x: f64, p: [f64; 4]| quadratic(x, [p[0], p[1], p[2]]) + p[3] * x.powi(3);
+        let aics = [
+            target_func.fit(&x_data, &y_data, cfg).unwrap().aic(),
+            quadratic.fit(&x_data, &y_data, cfg).unwrap().aic(),
+            cubic.fit(&x_data, &y_data, cfg).unwrap().aic(),
+        ];
+
+        let weights = model_weights(&aics);
+        assert!((weights.iter().sum::<f64>() - 1.0).abs() < 1e-12, "{weights:?}");
+        let best = (0..3).min_by(|&i, &j| aics[i].total_cmp(&aics[j])).unwrap();
+        assert!(weights.iter().all(|&w| w <= weights[best]), "{weights:?}");
+        assert!(weights[0] < 1e-6, "{weights:?}");
+
+        // equal AICs share the weight, and an AIC 2 higher has 1/e of the support
+        let weights = model_weights(&[10.0, 10.0, 12.0, f64::NAN]);
+        let e = std::f64::consts::E;
+        let expected = [e, e, 1.0, 0.0].map(|w| w / (2.0 * e + 1.0));
+        for (w, expected) in weights.iter().zip(expected) {
+            assert!((w - expected).abs() < 1e-12, "{weights:?}");
+        }
+        assert!(model_weights(&[]).is_empty());
+
+        // exact fits take all the weight, and without a finite AIC there is none to give
+        let inf = f64::INFINITY;
+        assert_eq!(model_weights(&[-inf, 3.0]), [1.0, 0.0]);
+        assert_eq!(model_weights(&[-inf, f64::NAN, -inf, inf]), [0.5, 0.0, 0.5, 0.0]);
+        for aics in [[f64::NAN, f64::NAN], [inf, f64::NAN]] {
+            let weights = model_weights(&aics);
+            assert!(weights.len() == 2 && weights.iter().all(|w| w.is_nan()), "{weights:?}");
+        }
+    }
+
+    #[test]
+    fn curves_as_functions() {
+        fn sample<F: Fn(f64) -> f64>(f: F) -> Vec<f64> {
+            (0..5).map(|i| f(0.5 * i as f64)).collect()
+        }
+
+        let x_data = [0.0, 1.0, 2.0, 3.0];
+        let y_data = [1.0, 2.5, 4.5, 7.0];
+        let f = target_func.fit(&x_data, &y_data, Config::default()).unwrap();
+        let expected: Vec<f64> = (0..5).map(|i| f.curve.eval(0.5 * i as f64)).collect();
+        assert_eq!(sample(f.curve.as_fn()), expected);
+    }
+
+    #[test]
+    fn parameters_by_index() {
+        let x_data = [0.0, 1.0, 2.0, 3.0];
+        let y_data = [1.0, 3.0, 5.0, 7.0];
+        let f = target_func.fit(&x_data, &y_data, Config::default()).unwrap();
+        let (a, b) = (f[0], f[1]);
+        assert_eq!([a, b], f.curve.params());
+        assert_eq!(f.curve[1], b);
+        assert_eq!((&f).into_iter().collect::<Vec<f64>>(), vec![a, b]);
+        let mut sum = 0.0;
+        for p in &f.curve {
+            sum += p;
+        }
+        assert_eq!(sum, a + b);
+        assert!(std::panic::catch_unwind(|| f.curve[2]).is_err());
+    }
+
+    #[test]
+    fn fit_results_clone() {
+        let model: fn(f64, [f64; 2]) -> f64 = target_func;
+        let cfg = Config {
+            record_history: true,
+            ..Default::default()
+        };
+        let f = model
+            .fit(&[0.0, 1.0, 2.0, 3.0], &[1.1, 2.9, 5.2, 7.0], cfg)
+            .unwrap();
+        let shared = std::sync::Arc::new(f.clone());
+        assert_eq!(shared.curve.params(), f.curve.params());
+        assert_eq!(shared.covariance, f.covariance);
+        assert_eq!(shared.history, f.history);
+        assert_eq!(shared.report, f.report);
+        assert_eq!(shared.curve.eval(1.5), f.curve.eval(1.5));
+    }
+
+    #[test]
+    fn curves_clone_and_configs_compare() {
+        let offset = 0.25;
+        let model = move |x: f64, p: [f64; 2]| p[0] * x + p[1] + offset;
+        let curve = model
+            .fit(&[0.0, 1.0, 2.0, 3.0], &[1.1, 2.9, 5.2, 7.0], Config::default())
+            .unwrap()
+            .curve
+            .with_names(["slope", "intercept"]);
+        let copy = curve.clone();
+        assert_eq!(copy.params(), curve.params());
+        assert_eq!(copy.parameter_names(), curve.parameter_names());
+        assert_eq!(copy.eval(1.5), curve.eval(1.5));
+        let fitted = [2.0, 1.05 - offset];
+        for (p, expected) in copy.params().iter().zip(fitted) {
+            assert!((p - expected).abs() < 1e-8, "{p}");
+        }
+        assert_eq!(copy.eval(1.5), model(1.5, copy.params()));
+
+        let cfg = Config { method: Method::TRF, ..Default::default() };
+        assert_eq!(cfg, Config { method: Method::TRF, ..Default::default() });
+        assert_ne!(cfg, Config::default());
+        assert_ne!(cfg, Config { regularization: Some(1.0), ..cfg });
+    }
+
+    #[test]
+    fn history_decreases_to_ssr() {
+        let xdata = Array::linspace(0., 4., 50);
+        let normal = Normal::new(0.0, 0.2).unwrap();
+        let y_noise = Array::random_using(xdata.dim(), normal, &mut StdRng::seed_from_u64(7));
+        let y_data = xdata.map(|&x| target_func(x, [2.5, 1.3])) + y_noise;
+
+        let f = target_func.fit_array(&xdata, &y_data, Config::default()).unwrap();
+        assert!(f.history.is_none());
+
+        for method in [Method::LM, Method::DogBox, Method::TRF, Method::LBFGS] {
+            let cfg = Config {
+                method,
+                record_history: true,
+                ..Default::default()
+            };
+            let f = target_func.fit_array(&xdata, &y_data, cfg).unwrap();
+            let history = f.history.unwrap();
+            assert_eq!(history.len(), f.report.iterations + 1, "{method:?}");
+            assert!(history.windows(2).all(|w| w[1] <= w[0]), "{method:?}: {history:?}");
+            assert_eq!(history.last(), Some(&f.ssr));
+        }
+    }
+
+    #[test]
+    fn traces_replay_the_fit() {
+        let decay = |x: f64, p: [f64; 3]| p[0] * (-p[1] * x).exp() + p[2];
+        let x_data: Vec<f64> = (0..25).map(|i| 0.2 * i as f64).collect();
+        let y_data: Vec<f64> = x_data.iter().map(|&x| decay(x, [3.0, 1.3, 0.5])).collect();
+        let f = decay.fit(&x_data, &y_data, Config::default()).unwrap();
+        assert!(f.trace.is_none());
+
+        let methods = [
+            Method::LM,
+            Method::TRF,
+            Method::DogBox,
+            Method::NelderMead,
+            Method::LBFGS,
+        ];
+        for method in methods {
+            let cfg = Config {
+                method,
+                record_trace: true,
+                ..Default::default()
+            };
+            let f = decay.fit(&x_data, &y_data, cfg).unwrap();
+            let trace = f.trace.unwrap();
+            assert_eq!(trace.len(), f.report.iterations, "{method:?}");
+            assert!(trace.iter().all(|entry| !entry.rejected));
+            assert!(trace.windows(2).all(|w| w[1].ssr <= w[0].ssr), "{method:?}");
+            let last = trace.last().unwrap();
+            assert_eq!((last.params, last.ssr), (f.curve.params(), f.ssr), "{method:?}");
+            let damped = matches!(method, Method::LM | Method::TRF | Method::DogBox);
+            assert!(trace.iter().all(|entry| entry.damping.is_some() == damped));
+            for (i, w) in trace.windows(2).enumerate() {
+                assert_eq!(w[1].iteration, i + 2);
+                let step: f64 = (0..3).map(|k| (w[1].params[k] - w[0].params[k]).powi(2)).sum();
+                assert!((w[1].step_norm - step.sqrt()).abs() < 1e-12, "{method:?}");
+            }
+        }
+
+        // nearly undamped steps from a rate of 3 overshoot, and LM backs off
+        let cfg = Config {
+            p0: 3.0,
+            method: Method::LM,
+            record_trace: true,
+            trace_rejected: true,
+            lm: LmOptions {
+                lambda_init: 1e-12,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let f = decay.fit(&x_data, &y_data, cfg).unwrap();
+        let trace = f.trace.unwrap();
+        let (rejected, iterations): (Vec<TraceEntry<3>>, _) =
+            trace.iter().partition(|entry| entry.rejected);
+        assert_eq!(iterations.len(), f.report.iterations);
+        assert!(!rejected.is_empty());
+        let mut ssr = 2.0 * f64::MAX;
+        for entry in &trace {
+            if entry.rejected {
+                assert!(entry.ssr >= ssr || entry.ssr.is_nan(), "{entry:?}");
+            } else {
+                ssr = entry.ssr;
+            }
+        }
+    }
+
+    #[test]
+    fn callbacks_follow_the_history() {
+        let decay = |x: f64, p: [f64; 2]| p[0] * (-p[1] * x).exp();
+        let x_data: Vec<f64> = (0..30).map(|i| 0.2 * i as f64).collect();
+        let y_data: Vec<f64> = x_data
+            .iter()
+            .enumerate()
+            .map(|(i, &x)| decay(x, [4.0, 1.5]) + 0.05 * (1.3 * i as f64).sin())
+            .collect();
+
+        for method in [Method::LM, Method::TRF, Method::NelderMead, Method::LBFGS] {
+            let steps = RefCell::new(vec![]);
+            let collect = |iteration: usize, params: [f64; 2], ssr: f64| {
+                steps.borrow_mut().push((iteration, params, ssr));
+            };
+            let cfg = Config { method, record_history: true, ..Default::default() };
+            let f = decay.fit_with_callback(&x_data, &y_data, cfg, &collect).unwrap();
+            assert!(f == decay.fit(&x_data, &y_data, cfg).unwrap(), "{method:?}");
+
+            let steps = steps.into_inner();
+            let costs: Vec<f64> = steps.iter().map(|&(_, _, ssr)| ssr).collect();
+            assert_eq!(Some(costs), f.history, "{method:?}");
+            assert!(steps.iter().enumerate().all(|(i, &(iteration, _, _))| iteration == i));
+            // each sum of squares belongs to the parameters reported with it
+            for &(_, params, ssr) in &steps {
+                let expected: f64 =
+                    x_data.iter().zip(&y_data).map(|(&x, y)| (decay(x, params) - y).powi(2)).sum();
+                assert!((ssr - expected).abs() < 1e-12 * expected, "{method:?}");
+            }
+            assert_eq!(steps.last().unwrap().1, f.curve.params(), "{method:?}");
+        }
+    }
+
+    #[test]
+    fn regularization_bounds_collinear_parameters() {
+        // the two basis functions differ by less than one part in a thousand
+        let collinear = |x: f64, p: [f64; 2]| p[0] * x + p[1] * x * (1.0 + 1e-4 * x);
+        let xdata = Array::linspace(0., 4., 50);
+        let normal = Normal::new(0.0, 0.2).unwrap();
+        let y_noise = Array::random_using(xdata.dim(), normal, &mut StdRng::seed_from_u64(7));
+        let y_data = xdata.map(|&x| 3.0 * x) + y_noise;
+
+        let plain = collinear.fit_array(&xdata, &y_data, Config::default()).unwrap();
+        assert!(plain.curve.params()[1].abs() > 5.0, "{:?}", plain.curve.params());
+
+        let cfg = Config {
+            regularization: Some(1e-3),
+            record_history: true,
+            ..Default::default()
+        };
+        let regularized = collinear.fit_array(&xdata, &y_data, cfg).unwrap();
+        let [a, b] = regularized.curve.params();
+        assert!(a.abs() < 2.0 && b.abs() < 2.0 && (a + b - 3.0).abs() < 0.1, "{a}, {b}");
+        // the penalty is minimized along with the data residuals but not reported in ssr
+        let penalty = 1e-3 * (a * a + b * b);
+        let objective = regularized.history.unwrap().pop().unwrap();
+        assert!((objective - regularized.ssr - penalty).abs() < 1e-9);
+
+        let cfg = Config {
+            regularization: Some(-1.0),
+            ..Default::default()
+        };
+        assert!(matches!(
+            collinear.fit_array(&xdata, &y_data, cfg),
+            Err(Error::ConfigCheckFailed(_))
+        ));
+    }
+
+    #[test]
+    fn regularization_pulls_towards_reference() {
+        let collinear = |x: f64, p: [f64; 2]| p[0] * x + p[1] * x * (1.0 + 1e-4 * x);
+        let x_data: Vec<f64> = (0..50).map(|i| 0.08 * i as f64).collect();
+        let y_data: Vec<f64> = x_data
+            .iter()
+            .enumerate()
+            .map(|(i, &x)| 3.0 * x + 0.2 * (1.7 * i as f64).sin())
+            .collect();
+        let cfg = Config {
+            regularization: Some(1e-3),
+            record_history: true,
+            ..Default::default()
+        };
+
+        // the data fix the sum of the parameters, the reference how it is split
+        let p_ref = [1.0, 2.0];
+        let f = collinear.fit_regularized(&x_data, &y_data, cfg, p_ref).unwrap();
+        let [a, b] = f.curve.params();
+        assert!((a - 1.0).abs() < 0.1 && (b - 2.0).abs() < 0.1, "{a}, {b}");
+        let penalty = 1e-3 * ((a - 1.0).powi(2) + (b - 2.0).powi(2));
+        let objective = f.history.unwrap().pop().unwrap();
+        assert!((objective - f.ssr - penalty).abs() < 1e-9);
+
+        let towards_zero = collinear.fit_regularized(&x_data, &y_data, cfg, [0.0; 2]).unwrap();
+        let plain = collinear.fit(&x_data, &y_data, cfg).unwrap();
+        assert_eq!(towards_zero.curve.params(), plain.curve.params());
+
+        // a vanishing weight leaves a well-posed fit alone, a large one holds the reference
+        let weight = |lambda| Config {
+            regularization: Some(lambda),
+            ..Default::default()
+        };
+        let plain = target_func.fit(&x_data, &y_data, Config::default()).unwrap();
+        let faint = target_func.fit_regularized(&x_data, &y_data, weight(1e-12), p_ref).unwrap();
+        for (p, q) in faint.curve.params().iter().zip(plain.curve.params()) {
+            assert!((p - q).abs() < 1e-8, "{p} vs {q}");
+        }
+        let strong = target_func.fit_regularized(&x_data, &y_data, weight(1e8), p_ref).unwrap();
+        for (p, q) in strong.curve.params().iter().zip(p_ref) {
+            assert!((p - q).abs() < 1e-3, "{p} vs {q}");
+        }
+    }
+
+    #[test]
+    fn regularization_shrinks_std_errors() {
+        let x_data: Vec<f64> = (0..20).map(|i| 0.5 * i as f64).collect();
+        let y_data: Vec<f64> = x_data
+            .iter()
+            .enumerate()
+            .map(|(i, &x)| 2.0 * x + 1.0 + 0.3 * (1.3 * i as f64).sin())
+            .collect();
+        let weight = |lambda| Config {
+            regularization: Some(lambda),
+            ..Default::default()
+        };
+        let fits: Vec<_> = [0.0, 1.0, 10.0, 100.0]
+            .into_iter()
+            .map(|lambda| target_func.fit_regularized(&x_data, &y_data, weight(lambda), [2.0, 1.0]))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        for pair in fits.windows(2) {
+            let (wide, narrow) = (pair[0].std_errors(), pair[1].std_errors());
+            for (w, n) in wide.iter().zip(narrow) {
+                assert!(n < *w, "{n} vs {w}");
+            }
+        }
+
+        // the covariance is ssr / (m - N) * (J^T J + lambda I)^-1 with J = [x, 1]
+        let f = &fits[2];
+        let sxx: f64 = x_data.iter().map(|x| x * x).sum();
+        let (sx, m): (f64, f64) = (x_data.iter().sum(), 20.0);
+        let (a, d) = (sxx + 10.0, m + 10.0);
+        let scale = f.ssr / (m - 2.0) / (a * d - sx * sx);
+        let expected = [[scale * d, -scale * sx], [-scale * sx, scale * a]];
+        for (row, expected) in f.covariance.iter().zip(expected) {
+            for (c, e) in row.iter().zip(expected) {
+                assert!((c - e).abs() < 1e-6 * e.abs(), "{c} vs {e}");
+            }
+        }
+    }
+
+    #[test]
+    fn all_config_errors_are_reported() {
+        assert!(Config::default().validate_all().is_ok());
+
+        let cfg = Config {
+            p0: f64::MIN_POSITIVE / 2.0,
+            ..Default::default()
+        };
+        let Err(error) = target_func.fit(&[1.0, 2.0, 3.0], &[1.0, 2.0, 3.0], cfg) else {
+            panic!("p0 passed");
+        };
+        assert!(matches!(
+            error,
+            Error::ConfigCheckFailed(range_checker::Error::CheckFailed { .. })
+        ));
+        // the failed check is the source, for error chains such as those of `anyhow`
+        let source = std::error::Error::source(&error).expect("the check is the source");
+        assert!(source.to_string().contains("p0"), "{source}");
+
+        let cfg = Config {
+            p0: 0.0,
+            regularization: Some(-1.0),
+            ..Default::default()
+        };
+        let errors = cfg.validate_all().unwrap_err();
+        assert_eq!(errors.len(), 2);
+        match target_func.fit(&[1.0, 2.0, 3.0], &[1.0, 2.0, 3.0], cfg) {
+            Err(error @ Error::MultipleConfigErrors(_)) => {
+                let message = error.to_string();
+                assert!(message.starts_with("2 config checks failed"), "{message}");
+                assert!(message.contains("p0") && message.contains("regularization"), "{message}");
+            }
+            _ => panic!("both fields should be reported"),
+        }
+    
+        let cfg = Config {
+            method: Method::SGD { batch_size: 0, learning_rate: f64::NAN },
+            n_epochs: 0,
+            ..Default::default()
+        };
+        let errors = cfg.validate_all().unwrap_err();
+        let message = Error::MultipleConfigErrors(errors).to_string();
+        assert!(message.starts_with("3 config checks failed"), "{message}");
+        assert!(message.contains("n_epochs") && message.contains("batch_size"), "{message}");
+    }
+
+    #[test]
+    fn simple_test() {
+        let xdata = Array::linspace(0., 4., 50);
+        let y = xdata.map(|&x| target_func(x, [2.5, 1.3]));
+        let normal = Normal::new(0.0, 1.0).unwrap();
+        let y_noise = Array::random_using(xdata.shape(), normal, &mut StdRng::seed_from_u64(7));
+        let y_data = y + y_noise; 
+        println!("y data:\n{:?}", y_data);
+    
+
+        // dbg!(xdata, ydata);
+    }
+
+    #[test]
+    fn auto_scaling_needs_fewer_iterations() {
+        // an offset given in thousandths next to a decay rate of order one
+        let offset_decay = |x: f64, p: [f64; 2]| 1e-3 * p[0] + (-p[1] * x).exp();
+        let x_data: Vec<f64> = (0..40).map(|i| 0.1 * i as f64).collect();
+        let y_data: Vec<f64> = x_data.iter().map(|&x| offset_decay(x, [5e3, 1.5])).collect();
+        let fit = |x_scale| {
+            let cfg = Config { x_scale, ..Default::default() };
+            offset_decay.fit(&x_data, &y_data, cfg).unwrap()
+        };
+
+        let unscaled = fit(XScale::None);
+        let auto = fit(XScale::Auto);
+        let manual = offset_decay
+            .fit_scaled(&x_data, &y_data, Config::default(), [1e3, 1.0])
+            .unwrap();
+        for f in [&unscaled, &auto, &manual] {
+            let params = f.curve.params();
+            assert!((params[0] - 5e3).abs() < 1e-3 && (params[1] - 1.5).abs() < 1e-6, "{params:?}");
+        }
+        assert!(
+            2 * auto.report.iterations < unscaled.report.iterations,
+            "{:?} vs {:?}",
+            auto.report,
+            unscaled.report
+        );
+        assert!(2 * manual.report.iterations < unscaled.report.iterations);
+
+        assert!(matches!(
+            offset_decay.fit_scaled(&x_data, &y_data, Config::default(), [1e3, 0.0]),
+            Err(Error::InvalidScale { index: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn scaling_matches_manual_rescaling() {
+        // an amplitude of order 1e6 next to a rate of order 1e-3
+        let decay = |t: f64, p: [f64; 2]| p[0] * (-p[1] * t).exp();
+        let rescaled = |t: f64, q: [f64; 2]| 1e6 * q[0] * (-1e-3 * q[1] * t).exp();
+        let t_data: Vec<f64> = (0..=100).map(|i| 50.0 * i as f64).collect();
+        let y_data: Vec<f64> = t_data
+            .iter()
+            .map(|&t| decay(t, [1.2e6, 1.7e-3]) * (1.0 + 0.01 * (0.37 * t).sin()))
+            .collect();
+        let start = [1e6, 1e-3];
+
+        for method in [Method::LM, Method::TRF, Method::DogBox] {
+            let cfg = Config { method, ..Default::default() };
+            let manual = solve(&rescaled, &t_data, &y_data, [1.0, 1.0], cfg.scaling(), &cfg);
+            assert!(manual.converged(), "{method:?}");
+            let expected = [1e6 * manual.params[0], 1e-3 * manual.params[1]];
+            for scaling in [solver::Scaling::Fixed(start), solver::Scaling::Jacobian] {
+                let scaled = solve(&decay, &t_data, &y_data, start, scaling, &cfg);
+                assert!(scaled.converged(), "{method:?}, {scaling:?}");
+                assert!(scaled.iterations <= manual.iterations + 1, "{method:?}, {scaling:?}");
+                for (p, expected) in scaled.params.iter().zip(expected) {
+                    assert!((p / expected - 1.0).abs() < 1e-6, "{method:?}, {scaling:?}");
+                }
+            }
+        }
+
+        // without scaling, LM stalls far from the minimum
+        let cfg = Config::default();
+        let unscaled = solve(&decay, &t_data, &y_data, start, cfg.scaling(), &cfg);
+        let scaled = solve(&decay, &t_data, &y_data, start, solver::Scaling::Fixed(start), &cfg);
+        assert!(unscaled.cost > 100.0 * scaled.cost);
+    }
+
+    #[test]
+    fn fits_pairs() {
+        struct Measurement {
+            t: f64,
+            v: f64,
+            valid: bool,
+        }
+        let measurements: Vec<Measurement> = (0..20)
+            .map(|i| Measurement {
+                t: 0.5 * i as f64,
+                v: target_func(0.5 * i as f64, [2.5, 1.3]) + 0.1 * (1.7 * i as f64).sin(),
+                valid: i % 4 != 3,
+            })
+            .collect();
+
+        let from_pairs = target_func
+            .fit_pairs(
+                measurements.iter().filter(|m| m.valid).map(|m| (m.t, m.v)),
+                Config::default(),
+            )
+            .unwrap();
+        let (x_data, y_data): (Vec<f64>, Vec<f64>) = measurements
+            .iter()
+            .filter(|m| m.valid)
+            .map(|m| (m.t, m.v))
+            .unzip();
+        let from_slices = target_func.fit(&x_data, &y_data, Config::default()).unwrap();
+        assert_eq!(from_pairs.curve.params(), from_slices.curve.params());
+        assert_eq!(from_pairs.n_data, 15);
+
+        assert!(matches!(
+            target_func.fit_pairs(std::iter::empty(), Config::default()),
+            Err(Error::InsufficientData { n_data: 0, n_params: 2 })
+        ));
+    }
+
+    #[test]
+    fn fits_ndarray_data() {
+        let xdata = Array::linspace(0., 4., 50);
+        let normal = Normal::new(0.0, 0.2).unwrap();
+        let y_noise = Array::random_using(xdata.dim(), normal, &mut StdRng::seed_from_u64(7));
+        let y_data = xdata.map(|&x| target_func(x, [2.5, 1.3])) + y_noise;
+
+        let from_arrays = target_func.fit_array(&xdata, &y_data, Config::default()).unwrap();
+        let from_slices = target_func
+            .fit(xdata.as_slice().unwrap(), y_data.as_slice().unwrap(), Config::default())
+            .unwrap();
+        assert_eq!(from_arrays.curve.params(), from_slices.curve.params());
+        assert_eq!(
+            from_arrays.curve.eval_array(&xdata.view()).to_vec(),
+            xdata.iter().map(|&x| from_slices.curve.eval(x)).collect::<Vec<_>>()
+        );
+
+        // every other point, through strided views
+        let x_view = xdata.slice(ndarray::s![..;2]);
+        let y_view = y_data.slice(ndarray::s![..;2]);
+        let strided = target_func.fit_array(&x_view, &y_view, Config::default()).unwrap();
+        let copied = target_func
+            .fit(&x_view.to_vec(), &y_view.to_vec(), Config::default())
+            .unwrap();
+        assert_eq!(strided.curve.params(), copied.curve.params());
+
+        // columns of a table with one row per measurement, stride 2
+        let table = ndarray::Array2::from_shape_fn((xdata.len(), 2), |(i, j)| {
+            if j == 0 { xdata[i] } else { y_data[i] }
+        });
+        let (x_column, y_column) = (table.column(0), table.column(1));
+        assert_eq!(x_column.strides(), [2]);
+        let from_columns = target_func.fit_array(&x_column, &y_column, Config::default()).unwrap();
+        assert_eq!(from_columns.curve.params(), from_slices.curve.params());
+        assert_eq!(from_columns.ssr, from_slices.ssr);
+    }
+
+    #[test]
+    fn seeded_fits_are_identical() {
+        let xdata = Array::linspace(0., 4., 50);
+        let normal = Normal::new(0.0, 0.2).unwrap();
+        let y_noise = Array::random_using(xdata.shape(), normal, &mut StdRng::seed_from_u64(7));
+        let y_data = xdata.map(|&x| target_func(x, [2.5, 1.3])) + y_noise;
+        let cfg = Config {
+            seed: Some(42),
+            ..Default::default()
+        };
+
+        let (x_data, y_data) = (xdata.as_slice().unwrap(), y_data.as_slice().unwrap());
+        let ranges = [(-10.0, 10.0); 2];
+        let run = |cfg| target_func.fit_multistart(x_data, y_data, cfg, 5, ranges).unwrap();
+        let (first, second) = (run(cfg), run(cfg));
+        let starts = |fit: &MultiStartResult<2, _>| -> Vec<[u64; 2]> {
+            fit.starts.iter().map(|start| start.p0.map(f64::to_bits)).collect()
+        };
+        assert_eq!(starts(&first), starts(&second));
+        assert_eq!(
+            first.best.curve.params().map(f64::to_bits),
+            second.best.curve.params().map(f64::to_bits)
+        );
+
+        // another seed draws other starts
+        let other = run(Config { seed: Some(43), ..cfg });
+        assert_ne!(starts(&first), starts(&other));
     }
 }
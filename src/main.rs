@@ -1,5 +1,5 @@
 use levenberg_marquardt::{LeastSquaresProblem, LevenbergMarquardt};
-use nalgebra::{VectorN, Vector2, Matrix2, Owned, U2};
+use nalgebra::{Vector2, Matrix2, Owned, U2};
 
 #[derive(Debug)]
 struct ExampleProblem {
@@ -13,12 +13,12 @@ impl LeastSquaresProblem<f64, U2, U2> for ExampleProblem {
     type ResidualStorage = Owned<f64, U2>;
     type JacobianStorage = Owned<f64, U2, U2>;
      
-    fn set_params(&mut self, p: &VectorN<f64, U2>) {
+    fn set_params(&mut self, p: &Vector2<f64>) {
         self.p.copy_from(p);
         // do common calculations for residuals and the Jacobian here
     }
      
-    fn params(&self) -> VectorN<f64, U2> { self.p }
+    fn params(&self) -> Vector2<f64> { self.p }
      
     fn residuals(&self) -> Option<Vector2<f64>> {
         let [x, y] = [self.p.x, self.p.y];
@@ -0,0 +1,519 @@
+//! Ready-made model functions for common curve shapes, and heuristics that guess their
+//! parameters from the data.
+
+use crate::{check_input, solve, stats, Config, Error, FitResult};
+
+/// A model function with `N` parameters, the type of the fitted models of this module.
+pub type Model<const N: usize> = fn(f64, [f64; N]) -> f64;
+
+/// Straight line `p[0] * x + p[1]` with parameters `[slope, intercept]`.
+pub fn linear(x: f64, p: [f64; 2]) -> f64 {
+    p[0] * x + p[1]
+}
+
+/// Gaussian peak `p[0] * exp(-(x - p[1])^2 / (2 p[2]^2))` with parameters
+/// `[amplitude, center, sigma]`.
+pub fn gaussian(x: f64, p: [f64; 3]) -> f64 {
+    p[0] * (-(x - p[1]).powi(2) / (2.0 * p[2].powi(2))).exp()
+}
+
+/// [`gaussian`] on a constant baseline, `gaussian(x, [p[0], p[1], p[2]]) + p[3]`, with
+/// parameters `[amplitude, center, sigma, baseline]`.
+pub fn gaussian_with_baseline(x: f64, p: [f64; 4]) -> f64 {
+    gaussian(x, [p[0], p[1], p[2]]) + p[3]
+}
+
+/// Exponential decay `p[0] * exp(-p[1] * x)` with parameters `[amplitude, rate]`.
+pub fn exponential_decay(x: f64, p: [f64; 2]) -> f64 {
+    p[0] * (-p[1] * x).exp()
+}
+
+/// Exponential `p[0] * exp(p[1] * x) + p[2]` with parameters `[amplitude, rate, offset]`,
+/// growing away from the offset for a positive rate and decaying towards it for a negative one.
+pub fn exponential(x: f64, p: [f64; 3]) -> f64 {
+    p[0] * (p[1] * x).exp() + p[2]
+}
+
+/// Logistic curve `p[0] / (1 + exp(-p[1] * (x - p[2])))` with parameters
+/// `[maximum, steepness, midpoint]`.
+pub fn logistic(x: f64, p: [f64; 3]) -> f64 {
+    p[0] / (1.0 + (-p[1] * (x - p[2])).exp())
+}
+
+/// Largest step of the integrator of [`ode_model`].
+const ODE_STEP: f64 = 0.01;
+
+/// The solution `y(x)` of the ordinary differential equation `dy/dt = rhs(t, y, p)` with
+/// `y(0) = y0`, as a model of the parameters `p` of the equation, e.g. to fit a rate constant
+/// to measurements of a process known by its equation alone.
+///
+/// Each evaluation integrates from 0 to `x`, backwards for a negative `x`, by the classical
+/// fourth-order Runge-Kutta method in equal steps of at most 0.01. This is accurate for
+/// solutions that change on scales of `x` well above the step, but slow for large `x`, and
+/// the steps do not adapt to stiff equations.
+pub fn ode_model<const N: usize>(
+    rhs: impl Fn(f64, f64, [f64; N]) -> f64 + Clone,
+    y0: f64,
+) -> impl Fn(f64, [f64; N]) -> f64 + Clone {
+    move |x, p| {
+        let n_steps = (x.abs() / ODE_STEP).ceil().max(1.0);
+        let h = x / n_steps;
+        let (mut t, mut y) = (0.0, y0);
+        for _ in 0..n_steps as usize {
+            let k1 = rhs(t, y, p);
+            let k2 = rhs(t + 0.5 * h, y + 0.5 * h * k1, p);
+            let k3 = rhs(t + 0.5 * h, y + 0.5 * h * k2, p);
+            let k4 = rhs(t + h, y + h * k3, p);
+            y += h / 6.0 * (k1 + 2.0 * k2 + 2.0 * k3 + k4);
+            t += h;
+        }
+        y
+    }
+}
+
+/// Direction of a peak from its baseline, for [`fit_gaussian`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeakSign {
+    /// A maximum, with a positive amplitude.
+    Positive,
+    /// A minimum, like an absorption line, with a negative amplitude.
+    Negative,
+}
+
+/// The model functions of this module, for [`estimate_p0`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KnownModel {
+    /// [`linear`]
+    Linear,
+    /// [`gaussian`]
+    Gaussian,
+    /// [`exponential_decay`]
+    ExponentialDecay,
+    /// [`logistic`]
+    Logistic,
+}
+
+impl KnownModel {
+    /// Number of parameters of the model function.
+    pub fn n_params(self) -> usize {
+        match self {
+            KnownModel::Linear | KnownModel::ExponentialDecay => 2,
+            KnownModel::Gaussian | KnownModel::Logistic => 3,
+        }
+    }
+}
+
+/// Guesses the parameters of `model_type`, in the order of its model function, from simple
+/// statistics of the data:
+///
+/// - `Linear`: the ordinary least-squares line, which is already the solution.
+/// - `Gaussian`: the center at the `x` of the largest `y`, the amplitude `max(y) - min(y)` and
+///   sigma from the full width at half maximum, `FWHM / (2 sqrt(2 ln 2))`.
+/// - `ExponentialDecay`: the line through `(x, ln y)` of the positive `y`, whose slope is the
+///   negative rate and whose intercept is the log of the amplitude.
+/// - `Logistic`: the maximum `max(y)` and the line through the logit
+///   `ln(y / (max - y)) = steepness * (x - midpoint)` of the points between 10% and 90% of it.
+///
+/// Parameters the data do not determine, like a rate with fewer than two positive `y`, are
+/// guessed as 1. The result is a starting point for the fit, not an estimate to report.
+///
+/// # Panics
+///
+/// If `N` is not the number of parameters of `model_type`.
+pub fn estimate_p0<const N: usize>(
+    model_type: KnownModel,
+    x_data: &[f64],
+    y_data: &[f64],
+) -> [f64; N] {
+    assert_eq!(
+        N,
+        model_type.n_params(),
+        "{model_type:?} has {} parameters",
+        model_type.n_params()
+    );
+    let points = || x_data.iter().copied().zip(y_data.iter().copied());
+    let y_max = y_data.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let y_min = y_data.iter().copied().fold(f64::INFINITY, f64::min);
+
+    let p0: Vec<f64> = match model_type {
+        KnownModel::Linear => {
+            let (slope, intercept) = fit_line(points()).unwrap_or((1.0, 1.0));
+            vec![slope, intercept]
+        }
+        KnownModel::Gaussian => {
+            let center = points().find(|&(_, y)| y == y_max).map_or(1.0, |(x, _)| x);
+            let amplitude = y_max - y_min;
+            let half_maximum = y_min + 0.5 * amplitude;
+            let (left, right) = points().filter(|&(_, y)| y >= half_maximum).fold(
+                (f64::INFINITY, f64::NEG_INFINITY),
+                |(left, right), (x, _)| (left.min(x), right.max(x)),
+            );
+            let fwhm = right - left;
+            let sigma = if fwhm > 0.0 {
+                fwhm / (2.0 * (2.0 * std::f64::consts::LN_2).sqrt())
+            } else {
+                1.0
+            };
+            vec![amplitude, center, sigma]
+        }
+        KnownModel::ExponentialDecay => {
+            let log_points = points().filter(|&(_, y)| y > 0.0).map(|(x, y)| (x, y.ln()));
+            let (slope, intercept) = fit_line(log_points).unwrap_or((-1.0, 0.0));
+            vec![intercept.exp(), -slope]
+        }
+        KnownModel::Logistic => {
+            let logit_points = points()
+                .filter(|&(_, y)| 0.1 * y_max < y && y < 0.9 * y_max)
+                .map(|(x, y)| (x, (y / (y_max - y)).ln()));
+            let (steepness, midpoint) = match fit_line(logit_points) {
+                Some((slope, intercept)) if slope != 0.0 => (slope, -intercept / slope),
+                _ => (1.0, 1.0),
+            };
+            vec![y_max, steepness, midpoint]
+        }
+    };
+    std::array::from_fn(|i| p0[i])
+}
+
+/// Fits [`exponential`] from a guess of its parameters made from the data alone; `cfg.p0` is
+/// not used.
+///
+/// The offset is guessed as the mean `y` of the quarter of the points, by `x`, at the end
+/// where the data flatten out. Amplitude and rate come from the line through
+/// `(x, ln |y - offset|)` of the points on the far side of the offset that are at least 10% of
+/// the largest deviation away from it; with fewer than two such points the guess fails with
+/// `Error::InsufficientData`.
+pub fn fit_exponential(
+    x_data: &[f64],
+    y_data: &[f64],
+    cfg: Config,
+) -> Result<FitResult<3, Model<3>>, Error> {
+    let (x_data, y_data) = check_input::<3>(x_data, y_data, &cfg)?;
+    let p0 = estimate_exponential(&x_data, &y_data)?;
+    fit_from(exponential, &x_data, &y_data, p0, &cfg)
+}
+
+/// The guess of [`fit_exponential`].
+fn estimate_exponential(x_data: &[f64], y_data: &[f64]) -> Result<[f64; 3], Error> {
+    let mut points: Vec<(f64, f64)> = x_data.iter().copied().zip(y_data.iter().copied()).collect();
+    points.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let n = points.len();
+    let quarter = (n / 4).max(1);
+    let mean =
+        |points: &[(f64, f64)]| points.iter().map(|(_, y)| y).sum::<f64>() / points.len() as f64;
+    let (first, second) = (
+        mean(&points[..quarter]),
+        mean(&points[quarter..2 * quarter]),
+    );
+    let (third, last) = (
+        mean(&points[n - 2 * quarter..n - quarter]),
+        mean(&points[n - quarter..]),
+    );
+    let (offset, far) = if (second - first).abs() < (last - third).abs() {
+        (first, last)
+    } else {
+        (last, first)
+    };
+
+    let sign = if far >= offset { 1.0 } else { -1.0 };
+    let largest = points
+        .iter()
+        .map(|(_, y)| sign * (y - offset))
+        .fold(0.0, f64::max);
+    let log_points: Vec<(f64, f64)> = points
+        .iter()
+        .filter(|&&(_, y)| sign * (y - offset) > 0.1 * largest)
+        .map(|&(x, y)| (x, (sign * (y - offset)).ln()))
+        .collect();
+    let n_log = log_points.len();
+    match fit_line(log_points.into_iter()) {
+        Some((rate, intercept)) if n_log >= 2 => Ok([sign * intercept.exp(), rate, offset]),
+        _ => Err(Error::InsufficientData {
+            n_data: n_log,
+            n_params: 2,
+        }),
+    }
+}
+
+/// Fits [`gaussian_with_baseline`] to a single peak pointing in the direction of `sign`, from a
+/// guess of its parameters made from the data alone; `cfg.p0` is not used.
+///
+/// The baseline is guessed as the median `y` of the outer 10% of the points at either end, the
+/// end further from the peak direction. After smoothing over 5 neighbouring points, the
+/// extreme point gives center and amplitude and the half-maximum crossings on either side
+/// sigma, through `FWHM = 2 sqrt(2 ln 2) sigma`. A peak cut off by the end of the data has
+/// its sigma taken from the visible half alone.
+pub fn fit_gaussian(
+    x_data: &[f64],
+    y_data: &[f64],
+    sign: PeakSign,
+    cfg: Config,
+) -> Result<FitResult<4, Model<4>>, Error> {
+    let (x_data, y_data) = check_input::<4>(x_data, y_data, &cfg)?;
+    let p0 = estimate_gaussian(&x_data, &y_data, sign);
+    fit_from(gaussian_with_baseline, &x_data, &y_data, p0, &cfg)
+}
+
+/// The guess of [`fit_gaussian`].
+fn estimate_gaussian(x_data: &[f64], y_data: &[f64], sign: PeakSign) -> [f64; 4] {
+    let sign = match sign {
+        PeakSign::Positive => 1.0,
+        PeakSign::Negative => -1.0,
+    };
+    let mut points: Vec<(f64, f64)> = x_data.iter().copied().zip(y_data.iter().copied()).collect();
+    points.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let n = points.len();
+
+    let edge = (n / 10).max(1);
+    let median = |points: &[(f64, f64)]| {
+        let mut y: Vec<f64> = points.iter().map(|&(_, y)| y).collect();
+        y.sort_by(f64::total_cmp);
+        stats::quantile(&y, 0.5)
+    };
+    // the peak can only pull an edge towards it
+    let (left, right) = (median(&points[..edge]), median(&points[n - edge..]));
+    let baseline = if sign * left < sign * right {
+        left
+    } else {
+        right
+    };
+
+    // heights above the baseline in the direction of the peak, averaged over 5 points
+    let height: Vec<f64> = (0..n)
+        .map(|i| {
+            let window = &points[i.saturating_sub(2)..(i + 3).min(n)];
+            window
+                .iter()
+                .map(|&(_, y)| sign * (y - baseline))
+                .sum::<f64>()
+                / window.len() as f64
+        })
+        .collect();
+    let peak = (0..n)
+        .max_by(|&i, &j| height[i].total_cmp(&height[j]))
+        .expect("the data were checked to have points");
+    let (center, half_maximum) = (points[peak].0, 0.5 * height[peak]);
+
+    // `x` where the height falls below half its maximum, interpolated between the points
+    let crossing = |mut indices: Box<dyn Iterator<Item = usize>>| {
+        let mut previous = peak;
+        indices.find_map(|i| {
+            let found = height[i] < half_maximum;
+            let t = (height[previous] - half_maximum) / (height[previous] - height[i]);
+            let x = points[previous].0 + t * (points[i].0 - points[previous].0);
+            previous = i;
+            found.then_some(x)
+        })
+    };
+    let half_widths = [
+        crossing(Box::new((0..peak).rev())).map(|x| center - x),
+        crossing(Box::new(peak + 1..n)).map(|x| x - center),
+    ];
+    let half_width = match half_widths {
+        [Some(left), Some(right)] => 0.5 * (left + right),
+        [Some(width), None] | [None, Some(width)] => width,
+        [None, None] => 0.5 * (points[n - 1].0 - points[0].0),
+    };
+    let sigma = half_width / (2.0 * std::f64::consts::LN_2).sqrt();
+    [sign * height[peak], center, sigma, baseline]
+}
+
+/// Runs the configured solver on checked data from the guess `p0`.
+fn fit_from<const N: usize>(
+    model: Model<N>,
+    x_data: &[f64],
+    y_data: &[f64],
+    p0: [f64; N],
+    cfg: &Config,
+) -> Result<FitResult<N, Model<N>>, Error> {
+    let solution = solve(&model, x_data, y_data, p0, cfg.scaling(), cfg);
+    FitResult::converged(model, solution, y_data)
+}
+
+/// Least-squares `(slope, intercept)` of the points, `None` without two distinct `x`.
+fn fit_line(points: impl Iterator<Item = (f64, f64)>) -> Option<(f64, f64)> {
+    let points: Vec<(f64, f64)> = points.collect();
+    let n = points.len() as f64;
+    let x_mean = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let y_mean = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let sxx: f64 = points.iter().map(|(x, _)| (x - x_mean).powi(2)).sum();
+    let sxy: f64 = points
+        .iter()
+        .map(|(x, y)| (x - x_mean) * (y - y_mean))
+        .sum();
+    // also false for NaN, i.e. no points at all
+    if sxx > 0.0 {
+        let slope = sxy / sxx;
+        Some((slope, y_mean - slope * x_mean))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::Array;
+    use ndarray_rand::rand_distr::Normal;
+    use ndarray_rand::RandomExt;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    fn distance(a: &[f64], b: &[f64]) -> f64 {
+        a.iter()
+            .zip(b)
+            .map(|(a, b)| (a - b).powi(2))
+            .sum::<f64>()
+            .sqrt()
+    }
+
+    fn assert_closer_than_ones<const N: usize>(
+        model_type: KnownModel,
+        model: fn(f64, [f64; N]) -> f64,
+        truth: [f64; N],
+        x_data: &[f64],
+    ) {
+        // a deterministic wiggle standing in for noise
+        let y_data: Vec<f64> = x_data
+            .iter()
+            .enumerate()
+            .map(|(i, &x)| model(x, truth) * (1.0 + 0.02 * (i as f64 * 1.7).sin()))
+            .collect();
+        let p0: [f64; N] = estimate_p0(model_type, x_data, &y_data);
+        assert!(
+            distance(&p0, &truth) < distance(&[1.0; N], &truth),
+            "{model_type:?}: {p0:?} vs {truth:?}"
+        );
+    }
+
+    #[test]
+    fn estimates_beat_ones() {
+        let x_data: Vec<f64> = (0..60).map(|i| 0.25 * i as f64).collect();
+        assert_closer_than_ones(KnownModel::Linear, linear, [-3.0, 12.0], &x_data);
+        assert_closer_than_ones(KnownModel::Gaussian, gaussian, [40.0, 6.5, 1.2], &x_data);
+        assert_closer_than_ones(
+            KnownModel::ExponentialDecay,
+            exponential_decay,
+            [250.0, 0.6],
+            &x_data,
+        );
+        assert_closer_than_ones(KnownModel::Logistic, logistic, [8.0, 2.5, 9.0], &x_data);
+    }
+
+    #[test]
+    fn gaussian_estimate_from_exact_peak() {
+        let x_data: Vec<f64> = (0..201).map(|i| -10.0 + 0.1 * i as f64).collect();
+        let y_data: Vec<f64> = x_data
+            .iter()
+            .map(|&x| gaussian(x, [3.0, 2.0, 1.5]))
+            .collect();
+        let [amplitude, center, sigma] = estimate_p0(KnownModel::Gaussian, &x_data, &y_data);
+        assert!((amplitude - 3.0).abs() < 1e-3);
+        assert!((center - 2.0).abs() < 1e-9);
+        // the half-maximum crossing is resolved to the grid spacing
+        assert!((sigma - 1.5).abs() < 0.1, "{sigma}");
+    }
+
+    #[test]
+    fn exponentials_fit_from_the_data_alone() {
+        let x_data: Vec<f64> = (0..60).map(|i| 0.1 * i as f64).collect();
+        let normal = Normal::new(0.0, 1.0).unwrap();
+        let noise = Array::random_using(x_data.len(), normal, &mut StdRng::seed_from_u64(3));
+        for truth in [[5.0, -0.8, 1.0], [0.5, 0.6, -2.0], [-3.0, -1.5, 10.0]] {
+            // noise of 10% of the range of the curve
+            let clean: Vec<f64> = x_data.iter().map(|&x| exponential(x, truth)).collect();
+            let range = clean.iter().copied().fold(f64::NEG_INFINITY, f64::max)
+                - clean.iter().copied().fold(f64::INFINITY, f64::min);
+            let y_data: Vec<f64> = clean
+                .iter()
+                .zip(&noise)
+                .map(|(y, e)| y + 0.1 * range * e)
+                .collect();
+
+            let f = fit_exponential(&x_data, &y_data, Config::default())
+                .unwrap_or_else(|e| panic!("{truth:?}: {e}"));
+            let params = f.curve.params();
+            for ((p, expected), s) in params.iter().zip(truth).zip(f.std_errors()) {
+                assert!((p - expected).abs() < 4.0 * s, "{truth:?}: {params:?}");
+            }
+        }
+
+        // a flat line has nothing to take a logarithm of
+        let x_data = [0.0, 1.0, 2.0, 3.0, 4.0];
+        let y_data = [1.0, 1.0, 1.0, 1.0, 1.1];
+        assert!(matches!(
+            fit_exponential(&x_data, &y_data, Config::default()),
+            Err(Error::InsufficientData { n_params: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn gaussian_peaks_fit_from_the_data_alone() {
+        let x_data: Vec<f64> = (0..120).map(|i| 0.1 * i as f64).collect();
+        let normal = Normal::new(0.0, 1.0).unwrap();
+        let mut rng = StdRng::seed_from_u64(11);
+        // amplitude, center, sigma and baseline, and the peak direction
+        let peaks = [
+            ([2.0_f64, 6.0, 0.8, 0.5], PeakSign::Positive),
+            ([-4.0, 3.5, 1.2, 10.0], PeakSign::Negative),
+            // cut off by the start of the data
+            ([3.0, 0.4, 1.0, -1.0], PeakSign::Positive),
+        ];
+        for (truth, sign) in peaks {
+            for snr in [3.0, 10.0, 100.0] {
+                let noise = Array::random_using(x_data.len(), normal, &mut rng);
+                let sigma_noise = truth[0].abs() / snr;
+                let y_data: Vec<f64> = x_data
+                    .iter()
+                    .zip(&noise)
+                    .map(|(&x, e)| gaussian_with_baseline(x, truth) + sigma_noise * e)
+                    .collect();
+
+                let f = fit_gaussian(&x_data, &y_data, sign, Config::default())
+                    .unwrap_or_else(|e| panic!("{truth:?} at {snr}: {e}"));
+                let [amplitude, center, sigma, _] = f.curve.params();
+                // half a peak only determines its center to a few standard errors
+                let tolerance = if truth[1] - 3.0 * truth[2] > x_data[0] {
+                    0.2 * truth[2]
+                } else {
+                    3.0 * f.std_errors()[1]
+                };
+                assert!(
+                    (center - truth[1]).abs() < tolerance,
+                    "{truth:?} at {snr}: {center}"
+                );
+                assert!(
+                    (sigma.abs() - truth[2]).abs() < 0.3 * truth[2],
+                    "{truth:?} at {snr}: {sigma}"
+                );
+                assert_eq!(amplitude.signum(), truth[0].signum());
+            }
+        }
+    }
+
+    #[test]
+    fn decay_rate_from_its_equation() {
+        let decay = ode_model(|_, y, p: [f64; 1]| -p[0] * y, 2.0);
+        for x in [0.0_f64, 0.37, 3.0, -1.5] {
+            let exact = 2.0 * (-0.8 * x).exp();
+            assert!((decay(x, [0.8]) / exact - 1.0).abs() < 1e-10, "{x}");
+        }
+
+        let x_data: Vec<f64> = (0..25).map(|i| 0.2 * i as f64).collect();
+        let y_data: Vec<f64> = x_data
+            .iter()
+            .enumerate()
+            .map(|(i, &x)| 2.0 * (-0.8 * x).exp() + 0.01 * (1.3 * i as f64).sin())
+            .collect();
+        let f = crate::CurveFit::fit(&decay, &x_data, &y_data, Config::default()).unwrap();
+        let [rate] = f.curve.params();
+        assert!((rate - 0.8).abs() < 0.01, "{rate}");
+    }
+
+    #[test]
+    #[should_panic(expected = "Gaussian has 3 parameters")]
+    fn wrong_parameter_count_panics() {
+        let _: [f64; 2] = estimate_p0(KnownModel::Gaussian, &[0.0, 1.0], &[1.0, 2.0]);
+    }
+}
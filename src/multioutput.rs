@@ -0,0 +1,270 @@
+//! Fitting of models that predict several quantities at once, `Fn(f64, [f64; N]) -> [f64; M]`.
+//!
+//! The `M` outputs of every point are flattened into one residual vector, point by point, so
+//! the scalar solvers run unchanged on `m * M` residuals.
+
+use std::cell::RefCell;
+
+use crate::{
+    check_config, check_order, solve, Config, Error, FitReport, FitResult, NonFinitePolicy,
+};
+
+/// Point index, parameters and outputs of a model call.
+type Call<const N: usize, const M: usize> = (usize, [f64; N], [f64; M]);
+
+/// A fitted vector-valued model together with the statistics of the fit.
+#[derive(Clone)]
+pub struct MultiOutputFitResult<const N: usize, const M: usize, F: Fn(f64, [f64; N]) -> [f64; M]> {
+    func: F,
+    params: [f64; N],
+    /// Sum of squared residuals over every point and output.
+    pub ssr: f64,
+    /// Estimated covariance of the parameters, `ssr / (m * M - N) * (J^T J)^-1` for the
+    /// `(m * M) x N` Jacobian of all outputs.
+    pub covariance: [[f64; N]; N],
+    /// Number of data points `m` the model was fitted to, each with `M` outputs.
+    pub n_data: usize,
+    pub report: FitReport,
+}
+
+impl<const N: usize, const M: usize, F: Fn(f64, [f64; N]) -> [f64; M]>
+    MultiOutputFitResult<N, M, F>
+{
+    pub fn params(&self) -> [f64; N] {
+        self.params
+    }
+
+    /// All outputs of the model at `x` with the fitted parameters.
+    pub fn eval(&self, x: f64) -> [f64; M] {
+        (self.func)(x, self.params)
+    }
+
+    /// Standard error of each parameter, the square root of the covariance diagonal.
+    pub fn std_errors(&self) -> [f64; N] {
+        std::array::from_fn(|i| self.covariance[i][i].sqrt())
+    }
+}
+
+pub trait MultiOutputCurveFit<const N: usize, const M: usize>
+where
+    Self: std::marker::Sized + Fn(f64, [f64; N]) -> [f64; M],
+{
+    /// Fits the model to points with `M` measured outputs each, `y_data[i][k]` being output
+    /// `k` at `x_data[i]`, by least squares over all outputs, as [`crate::CurveFit::fit`]
+    /// does for a single one. Scale the outputs to comparable units first, since each
+    /// residual weighs the same.
+    ///
+    /// `cfg.non_finite` applies to whole points: a point is non-finite if its `x` or any of
+    /// its outputs is. The model is called once per point and pass over the data.
+    fn fit(
+        &self,
+        x_data: &[f64],
+        y_data: &[[f64; M]],
+        cfg: Config,
+    ) -> Result<MultiOutputFitResult<N, M, Self>, Error>;
+}
+
+impl<T, const N: usize, const M: usize> MultiOutputCurveFit<N, M> for T
+where
+    T: Fn(f64, [f64; N]) -> [f64; M] + Clone,
+{
+    fn fit(
+        &self,
+        x_data: &[f64],
+        y_data: &[[f64; M]],
+        cfg: Config,
+    ) -> Result<MultiOutputFitResult<N, M, Self>, Error> {
+        let (x_data, y_data) = check_input::<N, M>(x_data, y_data, &cfg)?;
+
+        // residual `i * M + k` is output `k` of point `i`; the outputs of the latest point
+        // are kept so that the model runs once for all of them
+        let latest: RefCell<Option<Call<N, M>>> = RefCell::new(None);
+        let flat = |index: f64, p: [f64; N]| {
+            let (i, k) = (index as usize / M, index as usize % M);
+            let mut latest = latest.borrow_mut();
+            match *latest {
+                Some((j, q, y)) if j == i && q == p => y[k],
+                _ => {
+                    let y = self(x_data[i], p);
+                    *latest = Some((i, p, y));
+                    y[k]
+                }
+            }
+        };
+        let indices: Vec<f64> = (0..y_data.len() * M).map(|index| index as f64).collect();
+        let y_flat: Vec<f64> = y_data.iter().flatten().copied().collect();
+
+        // the data scales come from the points, not the indices the solver sees
+        let p0 = cfg.initial_guess(&x_data, &y_flat);
+        let solution = solve(&flat, &indices, &y_flat, p0, cfg.scaling(), &cfg);
+        let fit = FitResult::converged(flat, solution, &y_flat)?;
+        Ok(MultiOutputFitResult {
+            func: self.clone(),
+            params: fit.curve.params,
+            ssr: fit.ssr,
+            covariance: fit.covariance,
+            n_data: x_data.len(),
+            report: fit.report,
+        })
+    }
+}
+
+/// The checks of [`crate::CurveFit::fit`] on points with several outputs, returning the points
+/// to fit.
+fn check_input<const N: usize, const M: usize>(
+    x_data: &[f64],
+    y_data: &[[f64; M]],
+    cfg: &Config,
+) -> Result<(Vec<f64>, Vec<[f64; M]>), Error> {
+    if x_data.len() != y_data.len() {
+        return Err(Error::UnmatchedLength {
+            x_data_len: x_data.len(),
+            y_data_len: y_data.len(),
+        });
+    }
+    check_config(cfg)?;
+    check_order(x_data, cfg)?;
+
+    let is_finite = |i: &usize| x_data[*i].is_finite() && y_data[*i].iter().all(|y| y.is_finite());
+    let kept: Vec<usize> = match cfg.non_finite {
+        NonFinitePolicy::Error if cfg.check_finite => {
+            if let Some(index) = (0..x_data.len()).find(|i| !is_finite(i)) {
+                return Err(Error::NonFiniteData { index });
+            }
+            (0..x_data.len()).collect()
+        }
+        NonFinitePolicy::Drop if cfg.check_finite => (0..x_data.len()).filter(is_finite).collect(),
+        _ => (0..x_data.len()).collect(),
+    };
+    let x_data: Vec<f64> = kept.iter().map(|&i| x_data[i]).collect();
+    let y_data: Vec<[f64; M]> = kept.iter().map(|&i| y_data[i]).collect();
+
+    if x_data.len() * M < N.max(1) {
+        return Err(Error::InsufficientData {
+            n_data: x_data.len() * M,
+            n_params: N,
+        });
+    }
+    if cfg.check_finite && y_data.iter().flatten().all(|&y| y == y_data[0][0]) {
+        return Err(Error::ZeroVarianceData { y: y_data[0][0] });
+    }
+    Ok((x_data, y_data))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::{CurveFit, Method};
+
+    /// Position and velocity of uniformly accelerated motion, parameters
+    /// `[position, velocity, acceleration]` at `t = 0`.
+    fn trajectory(t: f64, p: [f64; 3]) -> [f64; 2] {
+        [p[0] + p[1] * t + 0.5 * p[2] * t * t, p[1] + p[2] * t]
+    }
+
+    #[test]
+    fn fits_position_and_velocity() {
+        let t_data: Vec<f64> = (0..20).map(|i| 0.1 * i as f64).collect();
+        let y_data: Vec<[f64; 2]> = t_data
+            .iter()
+            .enumerate()
+            .map(|(i, &t)| {
+                let [x, v] = trajectory(t, [1.0, 3.0, -9.8]);
+                [
+                    x + 0.01 * (1.3 * i as f64).sin(),
+                    v + 0.02 * (2.9 * i as f64).cos(),
+                ]
+            })
+            .collect();
+
+        let calls = Cell::new(0);
+        let counted = |t: f64, p: [f64; 3]| {
+            calls.set(calls.get() + 1);
+            trajectory(t, p)
+        };
+        for method in [Method::LM, Method::TRF] {
+            calls.set(0);
+            let f = counted
+                .fit(
+                    &t_data,
+                    &y_data,
+                    Config {
+                        method,
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+            for (p, expected) in f.params().iter().zip([1.0, 3.0, -9.8]) {
+                assert!((p - expected).abs() < 0.05, "{method:?}: {p}");
+            }
+            let ssr: f64 = t_data
+                .iter()
+                .zip(&y_data)
+                .flat_map(|(&t, y)| {
+                    let fitted = f.eval(t);
+                    (0..2).map(move |k| (fitted[k] - y[k]).powi(2))
+                })
+                .sum();
+            assert!((f.ssr - ssr).abs() < 1e-12, "{} vs {ssr}", f.ssr);
+            assert_eq!(f.n_data, 20);
+            // one model call per point and pass, and those for the sum of squares above
+            assert_eq!(
+                calls.get(),
+                f.report.n_fev * t_data.len() + 20,
+                "{method:?}"
+            );
+            assert!(f.std_errors().iter().all(|s| s.is_finite() && *s > 0.0));
+        }
+
+        // an output given twice weighs twice as much, which leaves the estimate unchanged
+        let position = |t: f64, p: [f64; 3]| trajectory(t, p)[0];
+        let x_only: Vec<f64> = y_data.iter().map(|y| y[0]).collect();
+        let single = position.fit(&t_data, &x_only, Config::default()).unwrap();
+        let both_x = |t: f64, p: [f64; 3]| [trajectory(t, p)[0]; 2];
+        let doubled: Vec<[f64; 2]> = x_only.iter().map(|&x| [x; 2]).collect();
+        let multi = both_x.fit(&t_data, &doubled, Config::default()).unwrap();
+        for (a, b) in multi.params().iter().zip(single.curve.params()) {
+            assert!((a - b).abs() < 1e-8, "{a} vs {b}");
+        }
+        assert!((multi.ssr - 2.0 * single.ssr).abs() < 1e-10);
+    }
+
+    #[test]
+    fn checks_points_as_a_whole() {
+        let t_data = [0.0, 0.5, 1.0, 1.5];
+        let mut y_data: Vec<[f64; 2]> = t_data
+            .iter()
+            .map(|&t| trajectory(t, [0.0, 1.0, 2.0]))
+            .collect();
+        assert!(matches!(
+            trajectory.fit(&t_data[..3], &y_data, Config::default()),
+            Err(Error::UnmatchedLength {
+                x_data_len: 3,
+                y_data_len: 4
+            })
+        ));
+        assert!(matches!(
+            trajectory.fit(&[], &[], Config::default()),
+            Err(Error::InsufficientData {
+                n_data: 0,
+                n_params: 3
+            })
+        ));
+        y_data[2][1] = f64::NAN;
+        assert!(matches!(
+            trajectory.fit(&t_data, &y_data, Config::default()),
+            Err(Error::NonFiniteData { index: 2 })
+        ));
+        let cfg = Config {
+            non_finite: NonFinitePolicy::Drop,
+            ..Default::default()
+        };
+        let f = trajectory.fit(&t_data, &y_data, cfg).unwrap();
+        assert_eq!(f.n_data, 3);
+        for (p, expected) in f.params().iter().zip([0.0, 1.0, 2.0]) {
+            assert!((p - expected).abs() < 1e-6, "{p}");
+        }
+    }
+}
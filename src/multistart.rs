@@ -0,0 +1,178 @@
+//! Multi-start fitting: the configured solver run from several random initial guesses.
+
+use ndarray::Array2;
+use ndarray_rand::rand_distr::Uniform;
+use ndarray_rand::RandomExt;
+use rand::rngs::StdRng;
+
+use crate::solver::Solution;
+use crate::{Error, FitResult};
+
+/// Outcome of one start of a multi-start fit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Start<const N: usize> {
+    /// Initial guess the solver started from.
+    pub p0: [f64; N],
+    /// Final sum of squared residuals, `None` when the solver did not converge from `p0`
+    /// or ended on non-finite values.
+    pub ssr: Option<f64>,
+}
+
+/// The best fit of a multi-start run together with every start in sampling order.
+pub struct MultiStartResult<const N: usize, F: Fn(f64, [f64; N]) -> f64> {
+    pub best: FitResult<N, F>,
+    pub starts: Vec<Start<N>>,
+}
+
+/// Draws `n_starts` initial guesses uniformly from the per-parameter ranges.
+pub(crate) fn sample_starts<const N: usize>(
+    p0_ranges: &[(f64, f64); N],
+    n_starts: usize,
+    rng: &mut StdRng,
+) -> Result<Vec<[f64; N]>, Error> {
+    for (index, &(lower, upper)) in p0_ranges.iter().enumerate() {
+        if !(lower.is_finite() && upper.is_finite() && lower <= upper) {
+            return Err(Error::InvalidStartRange {
+                index,
+                lower,
+                upper,
+            });
+        }
+    }
+
+    let unit = Array2::random_using(
+        (n_starts, N),
+        Uniform::new(0.0, 1.0).expect("the unit interval is a valid range"),
+        rng,
+    );
+    Ok(unit
+        .rows()
+        .into_iter()
+        .map(|u| {
+            std::array::from_fn(|j| {
+                let (lower, upper) = p0_ranges[j];
+                lower + u[j] * (upper - lower)
+            })
+        })
+        .collect())
+}
+
+/// Keeps the converged solution with the lowest cost, recording every start.
+pub(crate) fn select_best<const N: usize, F: Fn(f64, [f64; N]) -> f64>(
+    func: F,
+    runs: impl Iterator<Item = ([f64; N], Solution<N>)>,
+    y_data: &[f64],
+) -> Result<MultiStartResult<N, F>, Error> {
+    let mut best: Option<Solution<N>> = None;
+    let mut starts = vec![];
+    for (p0, solution) in runs {
+        let converged = solution.converged();
+        starts.push(Start {
+            p0,
+            ssr: converged.then_some(2.0 * solution.cost),
+        });
+        if converged && best.as_ref().is_none_or(|best| solution.cost < best.cost) {
+            best = Some(solution);
+        }
+    }
+
+    match best {
+        Some(solution) => Ok(MultiStartResult {
+            best: FitResult::new(func, solution, y_data),
+            starts,
+        }),
+        None => Err(Error::NoConvergedStart {
+            n_starts: starts.len(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Config, CurveFit, Error};
+
+    fn wave(x: f64, p: [f64; 2]) -> f64 {
+        p[0] * (p[1] * x).sin()
+    }
+
+    fn data() -> (Vec<f64>, Vec<f64>) {
+        let x_data: Vec<f64> = (0..80).map(|i| 0.125 * i as f64).collect();
+        let y_data = x_data.iter().map(|&x| wave(x, [2.0, 4.0])).collect();
+        (x_data, y_data)
+    }
+
+    #[test]
+    fn finds_global_minimum_missed_by_single_start() {
+        let (x_data, y_data) = data();
+        let cfg = Config {
+            seed: Some(1),
+            ..Default::default()
+        };
+
+        // the frequency has many local minima and p0 = 1 lands in a wrong one
+        let single = wave.fit(&x_data, &y_data, cfg).unwrap();
+        assert!((single.curve.params()[1] - 4.0).abs() > 0.1);
+        assert!(single.ssr > 1.0);
+
+        let multi = wave
+            .fit_multistart(&x_data, &y_data, cfg, 20, [(0.5, 4.0), (0.5, 5.0)])
+            .unwrap();
+        assert_eq!(multi.starts.len(), 20);
+        let [amplitude, frequency] = multi.best.curve.params();
+        assert!((amplitude - 2.0).abs() < 1e-6, "{amplitude}");
+        assert!((frequency - 4.0).abs() < 1e-6, "{frequency}");
+        let lowest = multi
+            .starts
+            .iter()
+            .filter_map(|start| start.ssr)
+            .fold(f64::INFINITY, f64::min);
+        assert_eq!(lowest, multi.best.ssr);
+    }
+
+    #[test]
+    fn seeded_starts_are_reproducible() {
+        let (x_data, y_data) = data();
+        let cfg = Config {
+            seed: Some(9),
+            ..Default::default()
+        };
+        let ranges = [(0.5, 4.0), (0.5, 5.0)];
+        let first = wave
+            .fit_multistart(&x_data, &y_data, cfg, 5, ranges)
+            .unwrap();
+        let second = wave
+            .fit_multistart(&x_data, &y_data, cfg, 5, ranges)
+            .unwrap();
+        assert_eq!(first.starts, second.starts);
+    }
+
+    #[test]
+    fn failed_starts_are_skipped() {
+        let x_data = [0.0, 1.0, 2.0, 3.0];
+        let y_data = [1.0, 2.0, 3.0, 4.0];
+        // non-finite wherever the second parameter is negative
+        let model = |x: f64, p: [f64; 2]| p[0] * x + p[1].sqrt();
+        let cfg = Config {
+            seed: Some(3),
+            ..Default::default()
+        };
+
+        let result = model
+            .fit_multistart(&x_data, &y_data, cfg, 10, [(0.0, 2.0), (-2.0, 2.0)])
+            .unwrap();
+        assert!(result.starts.iter().any(|start| start.ssr.is_none()));
+        assert!(result.best.ssr < 1e-12);
+
+        let result = model.fit_multistart(&x_data, &y_data, cfg, 10, [(0.0, 2.0), (-3.0, -2.0)]);
+        assert!(matches!(
+            result,
+            Err(Error::NoConvergedStart { n_starts: 10 })
+        ));
+
+        let result = model.fit_multistart(&x_data, &y_data, cfg, 10, [(0.0, 2.0), (1.0, -1.0)]);
+        assert!(matches!(
+            result,
+            Err(Error::InvalidStartRange { index: 1, .. })
+        ));
+    }
+}
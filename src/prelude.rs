@@ -0,0 +1,22 @@
+//! The traits and types needed for everyday fitting, importable at once.
+//!
+//! [`CurveFit`] in particular has to be in scope for `.fit` to be callable on a function.
+//!
+//! ```
+//! use curve_fit::prelude::*;
+//!
+//! fn line(x: f64, p: [f64; 2]) -> f64 {
+//!     p[0] * x + p[1]
+//! }
+//!
+//! let x_data = [0.0, 1.0, 2.0, 3.0];
+//! let y_data = [1.0, 3.0, 5.0, 7.0];
+//! let cfg = Config { method: Method::LM, ..Default::default() };
+//! let fit = line.fit(&x_data, &y_data, cfg)?;
+//! let curve: &Curve<2, _> = &fit.curve;
+//! assert!((curve.params()[0] - 2.0).abs() < 1e-9);
+//! assert!((curve.eval(4.0) - 9.0).abs() < 1e-9);
+//! # Ok::<(), Error>(())
+//! ```
+
+pub use crate::{Config, Curve, CurveFit, Error, Method};
@@ -0,0 +1,203 @@
+//! Least-squares problems given by their residuals. The solvers take every fit in this form,
+//! a model fitted to data points being the problem of its residuals `f(x_i, p) - y_i`; the
+//! [`Problem`] trait opens it to problems that do not fit that shape or that compute their own
+//! derivatives.
+
+use crate::solver::{self, DIFF_STEP};
+use crate::{check_config, check_converged, run_solver, Config, Error, FitReport};
+
+/// A least-squares problem in `N` parameters: the solvers minimize the sum of squares of its
+/// residuals.
+pub trait Problem<const N: usize> {
+    /// The residuals at the parameters `p`, the same number of them for every `p`.
+    fn residuals(&self, p: &[f64; N]) -> Vec<f64>;
+
+    /// The Jacobian of [`Problem::residuals`] at `p`, row `i` holding the derivatives of
+    /// residual `i` by each parameter. Forward differences of the residuals by default.
+    fn jacobian(&self, p: &[f64; N]) -> Vec<[f64; N]> {
+        let r = self.residuals(p);
+        let mut jac = vec![[0.0; N]; r.len()];
+        for j in 0..N {
+            let mut p_step = *p;
+            p_step[j] += DIFF_STEP * p[j].abs().max(1.0);
+            // use the step that is actually representable
+            let h = p_step[j] - p[j];
+            for (row, (r_step, r)) in jac.iter_mut().zip(self.residuals(&p_step).iter().zip(&r)) {
+                row[j] = (r_step - r) / h;
+            }
+        }
+        jac
+    }
+
+    /// The residuals numbered `indices` at `p`, in that order, for the solvers that step
+    /// through mini-batches of them. By default all residuals are evaluated and these picked.
+    fn batch_residuals(&self, p: &[f64; N], indices: &[usize]) -> Vec<f64> {
+        let r = self.residuals(p);
+        indices.iter().map(|&i| r[i]).collect()
+    }
+}
+
+/// A model fitted to `(x_data, y_data)`, whose residuals are `func(x_i, p) - y_i`.
+impl<const N: usize, F: Fn(f64, [f64; N]) -> f64> Problem<N> for (F, &[f64], &[f64]) {
+    fn residuals(&self, p: &[f64; N]) -> Vec<f64> {
+        let (func, x_data, y_data) = self;
+        x_data
+            .iter()
+            .zip(*y_data)
+            .map(|(&x, y)| func(x, *p) - y)
+            .collect()
+    }
+
+    fn batch_residuals(&self, p: &[f64; N], indices: &[usize]) -> Vec<f64> {
+        let (func, x_data, y_data) = self;
+        indices
+            .iter()
+            .map(|&i| func(x_data[i], *p) - y_data[i])
+            .collect()
+    }
+}
+
+/// The parameters that solve a [`Problem`], together with the statistics of the fit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProblemFitResult<const N: usize> {
+    pub params: [f64; N],
+    /// Sum of squared residuals at `params`.
+    pub ssr: f64,
+    /// Estimated covariance of the parameters, `ssr / (m - N) * (J^T J)^-1` for the `m`
+    /// residuals, or `ssr / (m - N) * (J^T J + lambda I)^-1` with [`Config::regularization`].
+    pub covariance: [[f64; N]; N],
+    /// Number of residuals `m`.
+    pub n_residuals: usize,
+    pub report: FitReport,
+}
+
+impl<const N: usize> ProblemFitResult<N> {
+    /// Standard error of each parameter, the square root of the covariance diagonal.
+    pub fn std_errors(&self) -> [f64; N] {
+        std::array::from_fn(|i| self.covariance[i][i].sqrt())
+    }
+}
+
+/// Minimizes the sum of squares of the residuals of `problem` from `p0` with the solver of
+/// `cfg.method`, taking the Jacobian from [`Problem::jacobian`], with the errors of
+/// [`crate::CurveFit::fit`].
+///
+/// `cfg.p0`, `cfg.p0_strategy` and the settings of the data checks and of the finite
+/// differences do not apply, and bounds are not supported.
+///
+/// # Panics
+///
+/// If `problem` returns a different number of residuals or Jacobian rows than at `p0`.
+pub fn fit_problem<const N: usize>(
+    problem: &dyn Problem<N>,
+    p0: [f64; N],
+    cfg: Config,
+) -> Result<ProblemFitResult<N>, Error> {
+    check_config(&cfg)?;
+    let m = problem.residuals(&p0).len();
+    if m < N.max(1) {
+        return Err(Error::InsufficientData {
+            n_data: m,
+            n_params: N,
+        });
+    }
+
+    let setup = solver::Setup {
+        exact_jacobian: true,
+        ..Default::default()
+    };
+    let bounds = solver::Bounds::UNBOUNDED;
+    let solution = run_solver(problem, p0, cfg.scaling(), bounds, &cfg, setup, None);
+    check_converged(&solution)?;
+    let ssr = 2.0 * solution.cost;
+    let lambda = solution.regularization;
+    Ok(ProblemFitResult {
+        params: solution.params,
+        ssr,
+        covariance: solver::covariance(&solution.jacobian, ssr, solution.n_free, lambda),
+        n_residuals: m,
+        report: FitReport::of(&solution),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::{CurveFit, Method};
+
+    /// The Rosenbrock function as a sum of squares, with its exact Jacobian and a count of
+    /// the residual evaluations.
+    struct Rosenbrock {
+        evaluations: Cell<usize>,
+    }
+
+    impl Problem<2> for Rosenbrock {
+        fn residuals(&self, p: &[f64; 2]) -> Vec<f64> {
+            self.evaluations.set(self.evaluations.get() + 1);
+            vec![10.0 * (p[1] - p[0] * p[0]), 1.0 - p[0]]
+        }
+
+        fn jacobian(&self, p: &[f64; 2]) -> Vec<[f64; 2]> {
+            vec![[-20.0 * p[0], 10.0], [-1.0, 0.0]]
+        }
+    }
+
+    fn line(x: f64, p: [f64; 2]) -> f64 {
+        p[0] * x + p[1]
+    }
+
+    #[test]
+    fn fits_hand_written_problem() {
+        let rosenbrock = Rosenbrock {
+            evaluations: Cell::new(0),
+        };
+        let cfg = Config {
+            method: Method::LM,
+            ..Default::default()
+        };
+        let fit = fit_problem(&rosenbrock, [-1.2, 1.0], cfg).unwrap();
+        for p in fit.params {
+            assert!((p - 1.0).abs() < 1e-6, "{:?}", fit.params);
+        }
+        assert!(fit.ssr < 1e-12, "{}", fit.ssr);
+        assert_eq!(fit.n_residuals, 2);
+        assert_eq!(fit.report.method, Method::LM);
+        // the exact Jacobian takes no evaluations, and counting the residuals takes one
+        assert_eq!(rosenbrock.evaluations.get(), fit.report.n_fev + 1);
+
+        let one = (line, &[1.0][..], &[2.0][..]);
+        assert!(matches!(
+            fit_problem(&one, [0.0; 2], cfg),
+            Err(Error::InsufficientData {
+                n_data: 1,
+                n_params: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn data_triples_fit_as_curves() {
+        let x_data = [0.0, 1.0, 2.0, 3.0, 4.0];
+        let y_data = [1.1, 2.9, 5.2, 6.8, 9.1];
+        let triple = (line, &x_data[..], &y_data[..]);
+        let residuals = triple.residuals(&[2.0, 1.0]);
+        assert_eq!(residuals.len(), 5);
+        assert!((residuals[2] - (-0.2)).abs() < 1e-12);
+        let batch = triple.batch_residuals(&[2.0, 1.0], &[4, 2]);
+        assert_eq!(batch, [residuals[4], residuals[2]]);
+        let jacobian = triple.jacobian(&[2.0, 1.0]);
+        for (row, &x) in jacobian.iter().zip(&x_data) {
+            assert!((row[0] - x).abs() < 1e-6 && (row[1] - 1.0).abs() < 1e-6);
+        }
+
+        let fit = fit_problem(&triple, [1.0; 2], Config::default()).unwrap();
+        let curve = line.fit(&x_data, &y_data, Config::default()).unwrap();
+        for (a, b) in fit.params.iter().zip(curve.curve.params()) {
+            assert!((a - b).abs() < 1e-8, "{a} vs {b}");
+        }
+        assert!((fit.ssr - curve.ssr).abs() < 1e-10);
+        assert!(fit.std_errors().iter().all(|s| s.is_finite() && *s > 0.0));
+    }
+}
@@ -0,0 +1,261 @@
+//! Fitting with iterative outlier rejection: the points far from the fitted curve are left
+//! out and the others fitted again, until the points left out stop changing.
+
+use range_checker::CheckVerbose;
+
+use crate::{trace, Config, CurveFit, Error, FitResult};
+
+/// Ratio of the standard deviation of normal errors to their median absolute deviation.
+const MAD_TO_STD_DEV: f64 = 1.4826;
+
+/// The spread of the residuals that a point is measured against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClipScale {
+    /// `1.4826` times the median absolute deviation of the inlier residuals from their median,
+    /// the standard deviation for normal errors, which the outliers barely move.
+    Mad,
+    /// The standard deviation `sqrt(ssr / dof)` of the fit, which the outliers still among the
+    /// inliers inflate, so that fewer of them are caught in each round.
+    StdDev,
+}
+
+/// Settings of the sigma clipping of [`CurveFit::fit_robust_reject`].
+#[derive(Debug, Clone, Copy, PartialEq, range_checker::CheckVerbose)]
+pub struct RejectOptions {
+    /// Points whose residual is more than `k` times the spread away from the center are
+    /// rejected, 3 by default.
+    #[filter(|k: &f64| *k > 0.0 && k.is_finite())]
+    pub k: f64,
+    /// The spread, `ClipScale::Mad` by default.
+    pub scale: ClipScale,
+    /// Most fits to make, the first one to all points included, 10 by default.
+    #[filter(|rounds: &usize| *rounds > 0)]
+    pub max_rounds: usize,
+}
+
+impl Default for RejectOptions {
+    fn default() -> Self {
+        Self {
+            k: 3.0,
+            scale: ClipScale::Mad,
+            max_rounds: 10,
+        }
+    }
+}
+
+/// How the rounds of a fit with outlier rejection ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectStatus {
+    /// The last fit rejects exactly the points it was fitted without.
+    Converged,
+    /// `max_rounds` fits were made, the inliers still changing after the last.
+    MaxRounds,
+    /// The last fit would leave no more inliers than parameters to fit again, so it was
+    /// kept: the rejection is likely too strict or the model wrong.
+    Underdetermined,
+}
+
+/// The fit to the points that survived outlier rejection.
+#[derive(Clone)]
+pub struct RejectResult<const N: usize, F: Fn(f64, [f64; N]) -> f64> {
+    /// The fit to the inliers; its `n_data` counts them.
+    pub fit: FitResult<N, F>,
+    /// Whether each point is among those `fit` was fitted to, in the order of the data.
+    pub inliers: Vec<bool>,
+    /// Number of fits made.
+    pub rounds: usize,
+    pub status: RejectStatus,
+}
+
+impl<const N: usize, F: Fn(f64, [f64; N]) -> f64> RejectResult<N, F> {
+    /// Indices of the rejected points.
+    pub fn outliers(&self) -> Vec<usize> {
+        (0..self.inliers.len())
+            .filter(|&i| !self.inliers[i])
+            .collect()
+    }
+}
+
+pub(crate) fn fit_rejecting<const N: usize, F: Fn(f64, [f64; N]) -> f64 + Clone>(
+    func: &F,
+    x_data: &[f64],
+    y_data: &[f64],
+    cfg: Config,
+    reject: RejectOptions,
+) -> Result<RejectResult<N, F>, Error> {
+    reject.check().map_err(|errors| {
+        let mut errors: Vec<Error> = errors.into_iter().map(Error::from).collect();
+        if errors.len() == 1 {
+            errors.remove(0)
+        } else {
+            Error::MultipleConfigErrors(errors)
+        }
+    })?;
+
+    let mut inliers = vec![true; x_data.len()];
+    let mut fit = func.fit_masked(x_data, y_data, cfg, &inliers)?;
+    let mut rounds = 1;
+    let status = loop {
+        let residuals: Vec<f64> = x_data
+            .iter()
+            .zip(y_data)
+            .map(|(&x, y)| y - fit.curve.eval(x))
+            .collect();
+        let (center, spread) = match reject.scale {
+            ClipScale::Mad => {
+                let inlier_residuals: Vec<f64> = (0..residuals.len())
+                    .filter(|&i| inliers[i])
+                    .map(|i| residuals[i])
+                    .collect();
+                let center = median(inlier_residuals.clone());
+                let deviations = inlier_residuals.iter().map(|r| (r - center).abs());
+                (center, MAD_TO_STD_DEV * median(deviations.collect()))
+            }
+            ClipScale::StdDev => (0.0, (fit.ssr / fit.dof() as f64).sqrt()),
+        };
+        // non-finite residuals compare false and are rejected too
+        let next: Vec<bool> = residuals
+            .iter()
+            .map(|r| (r - center).abs() <= reject.k * spread)
+            .collect();
+
+        if next == inliers {
+            break RejectStatus::Converged;
+        }
+        let n_inliers = next.iter().filter(|&&inlier| inlier).count();
+        if n_inliers <= N {
+            trace::underdetermined_rejection(rounds, n_inliers);
+            break RejectStatus::Underdetermined;
+        }
+        if rounds == reject.max_rounds {
+            break RejectStatus::MaxRounds;
+        }
+        fit = func.fit_masked(x_data, y_data, cfg, &next)?;
+        inliers = next;
+        rounds += 1;
+    };
+
+    Ok(RejectResult {
+        fit,
+        inliers,
+        rounds,
+        status,
+    })
+}
+
+/// Median of `values`, the mean of the middle two for an even count.
+fn median(mut values: Vec<f64>) -> f64 {
+    values.sort_by(f64::total_cmp);
+    let n = values.len();
+    if n % 2 == 1 {
+        values[n / 2]
+    } else {
+        0.5 * (values[n / 2 - 1] + values[n / 2])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Method;
+
+    fn line(x: f64, p: [f64; 2]) -> f64 {
+        p[0] * x + p[1]
+    }
+
+    #[test]
+    fn gross_outliers_are_rejected() {
+        let x_data: Vec<f64> = (0..50).map(|i| 0.2 * i as f64).collect();
+        let mut y_data: Vec<f64> = x_data
+            .iter()
+            .enumerate()
+            .map(|(i, &x)| line(x, [2.0, 1.0]) + 0.05 * (1.7 * i as f64).sin())
+            .collect();
+        // every fifth point is hit far above the line, the later ones harder
+        let hits: Vec<usize> = (0..50).step_by(5).collect();
+        for (k, &i) in hits.iter().enumerate() {
+            y_data[i] += 10.0 + 2.0 * k as f64;
+        }
+
+        let plain = line.fit(&x_data, &y_data, Config::default()).unwrap();
+        assert!((plain.curve.params()[0] - 2.0).abs() > 0.2, "{:?}", plain.curve.params());
+
+        // the outliers swell the standard deviation so much that none is 3 of them away
+        for (scale, k) in [(ClipScale::Mad, 3.0), (ClipScale::StdDev, 2.0)] {
+            let reject = RejectOptions {
+                k,
+                scale,
+                ..Default::default()
+            };
+            let f = line
+                .fit_robust_reject(&x_data, &y_data, Config::default(), reject)
+                .unwrap();
+            assert_eq!(f.status, RejectStatus::Converged, "{scale:?}");
+            assert_eq!(f.outliers(), hits, "{scale:?}");
+            assert_eq!(f.fit.n_data, 40);
+            assert!(f.rounds > 1 && f.rounds <= reject.max_rounds);
+            let [slope, intercept] = f.fit.curve.params();
+            assert!((slope - 2.0).abs() < 0.04, "{scale:?}: {slope}");
+            assert!((intercept - 1.0).abs() < 0.05, "{scale:?}: {intercept}");
+        }
+
+        // a single round is the plain fit
+        let once = RejectOptions {
+            max_rounds: 1,
+            ..Default::default()
+        };
+        let f = line
+            .fit_robust_reject(&x_data, &y_data, Config::default(), once)
+            .unwrap();
+        assert_eq!(f.status, RejectStatus::MaxRounds);
+        assert_eq!(f.rounds, 1);
+        assert!(f.inliers.iter().all(|&inlier| inlier));
+        assert_eq!(f.fit.curve.params(), plain.curve.params());
+    }
+
+    #[test]
+    fn rejection_keeps_enough_points() {
+        // three points, one of them off the line, cannot lose more than one
+        let x_data = [0.0, 1.0, 2.0];
+        let y_data = [1.0, 3.0, 9.0];
+        let strict = RejectOptions {
+            k: 0.1,
+            ..Default::default()
+        };
+        let cfg = Config {
+            method: Method::LM,
+            ..Default::default()
+        };
+        let f = line
+            .fit_robust_reject(&x_data, &y_data, cfg, strict)
+            .unwrap();
+        assert_eq!(f.status, RejectStatus::Underdetermined);
+        assert_eq!(f.rounds, 1);
+        assert_eq!(f.fit.n_data, 3);
+        assert!(f.inliers.iter().all(|&inlier| inlier));
+
+        for (field, reject) in [
+            (
+                "k",
+                RejectOptions {
+                    k: 0.0,
+                    ..Default::default()
+                },
+            ),
+            (
+                "max_rounds",
+                RejectOptions {
+                    max_rounds: 0,
+                    ..Default::default()
+                },
+            ),
+        ] {
+            match line.fit_robust_reject(&x_data, &y_data, cfg, reject) {
+                Err(Error::ConfigCheckFailed(range_checker::Error::CheckFailed {
+                    ident, ..
+                })) => assert_eq!(ident, field),
+                _ => panic!("{field} passed"),
+            }
+        }
+    }
+}
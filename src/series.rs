@@ -0,0 +1,63 @@
+//! Data from `polars` series, behind the `polars` feature.
+
+use polars::prelude::{DataType, Series};
+
+use crate::Error;
+
+/// The values of a numeric `series` as `f64`, nulls becoming NaN so that `Config::non_finite`
+/// handles them. Integer series are converted; other types are an `Error::PolarsError`.
+pub(crate) fn values(series: &Series) -> Result<Vec<f64>, Error> {
+    let series = if series.dtype().is_primitive_numeric() {
+        series.cast(&DataType::Float64)?
+    } else {
+        series.clone()
+    };
+    Ok(series
+        .f64()?
+        .iter()
+        .map(|value| value.unwrap_or(f64::NAN))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use polars::prelude::{NamedFrom, PlSmallStr};
+
+    use super::*;
+    use crate::{Config, CurveFit, NonFinitePolicy};
+
+    fn line(x: f64, p: [f64; 2]) -> f64 {
+        p[0] * x + p[1]
+    }
+
+    #[test]
+    fn fits_numeric_series() {
+        let x = Series::new(PlSmallStr::from("x"), &[0i32, 1, 2, 3]);
+        let y = Series::new(PlSmallStr::from("y"), &[1.0, 3.1, 4.9, 7.0]);
+        let f = line.fit_series(&x, &y, Config::default()).unwrap();
+        let [slope, intercept] = f.curve.params();
+        assert!((slope - 1.98).abs() < 1e-9, "{slope}");
+        assert!((intercept - 1.03).abs() < 1e-9, "{intercept}");
+
+        // nulls are non-finite points
+        let y = Series::new(
+            PlSmallStr::from("y"),
+            &[Some(1.0), None, Some(5.0), Some(7.0)],
+        );
+        assert!(matches!(
+            line.fit_series(&x, &y, Config::default()),
+            Err(Error::NonFiniteData { index: 1 })
+        ));
+        let cfg = Config {
+            non_finite: NonFinitePolicy::Drop,
+            ..Default::default()
+        };
+        assert_eq!(line.fit_series(&x, &y, cfg).unwrap().n_data, 3);
+
+        let labels = Series::new(PlSmallStr::from("y"), &["a", "b", "c", "d"]);
+        assert!(matches!(
+            line.fit_series(&x, &labels, Config::default()),
+            Err(Error::PolarsError(_))
+        ));
+    }
+}
@@ -0,0 +1,2224 @@
+//! Least-squares solvers minimizing `0.5 * sum((f(x_i, p) - y_i)^2)` over `p`.
+//!
+//! The loops follow scipy's `least_squares`: LM is a damped Gauss-Newton iteration, TRF solves
+//! the trust-region subproblem exactly through an SVD of the Jacobian and DogBox takes dogleg
+//! steps inside a box-shaped trust region. TRF and DogBox also keep the parameters within
+//! bounds. Nelder-Mead, as in scipy's `minimize`, searches without derivatives, and L-BFGS
+//! minimizes the cost as a general smooth function. Models linear in their parameters are
+//! solved directly, and stochastic gradient descent steps through large data sets in random
+//! mini-batches. The [`odr`] submodule extends LM to errors in `x`, and the [`global`] one to
+//! several data sets with shared parameters.
+
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use nalgebra::{DMatrix, DVector};
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::{trace, JacMethod, LmOptions, Method, TrustRegionOptions};
+
+pub(crate) mod global;
+pub(crate) mod odr;
+
+const FTOL: f64 = 1e-8;
+const XTOL: f64 = 1e-8;
+const GTOL: f64 = 1e-8;
+/// Default relative step of the finite differences, which balances their truncation and
+/// rounding errors for a well-scaled model.
+pub(crate) const DIFF_STEP: f64 = 1.490_116_119_384_765_6e-8;
+/// Default relative step of central differences, whose truncation error is of second order.
+const CENTRAL_DIFF_STEP: f64 = 6.055_454_452_393_343e-6;
+
+/// What a fit does with the parameters of its problem besides fitting them.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Setup<const N: usize> {
+    /// The parameters the regularization pulls towards, zero by default.
+    pub(crate) reference: [f64; N],
+    /// Parameters held at the given values rather than fitted.
+    pub(crate) fixed: [Option<f64>; N],
+    /// Whether the rows of the data residuals of the Jacobian come from
+    /// [`crate::Problem::jacobian`] instead of finite differences. These rows ignore the bounds.
+    pub(crate) exact_jacobian: bool,
+}
+
+impl<const N: usize> Default for Setup<N> {
+    fn default() -> Self {
+        Self {
+            reference: [0.0; N],
+            fixed: [None; N],
+            exact_jacobian: false,
+        }
+    }
+}
+
+/// A [`crate::Problem`] as the solvers see it, with its regularization, fixed parameters and
+/// derivatives.
+///
+/// The solvers iterate on the free parameters only, as a vector of their values in order;
+/// [`Problem::reduce`] and [`Problem::params`] translate from and to all `N` parameters.
+pub(crate) struct Problem<'a, const N: usize> {
+    source: &'a dyn crate::Problem<N>,
+    /// Tikhonov weight `lambda`, adding `lambda * |p - reference|^2` to the sum of squares.
+    regularization: Option<f64>,
+    reference: [f64; N],
+    /// Parameters held at the given values rather than fitted.
+    fixed: [Option<f64>; N],
+    /// Indices of the fitted parameters.
+    free: Vec<usize>,
+    /// Step of the finite differences relative to `max(|p_i|, 1)`.
+    diff_step: f64,
+    differences: JacMethod,
+    exact_jacobian: bool,
+    /// Evaluations of the residuals, including those of the Jacobian.
+    evaluations: Cell<usize>,
+}
+
+impl<'a, const N: usize> Problem<'a, N> {
+    pub(crate) fn new(
+        source: &'a dyn crate::Problem<N>,
+        regularization: Option<f64>,
+        setup: Setup<N>,
+    ) -> Self {
+        Self {
+            source,
+            regularization,
+            reference: setup.reference,
+            fixed: setup.fixed,
+            free: (0..N).filter(|&i| setup.fixed[i].is_none()).collect(),
+            diff_step: DIFF_STEP,
+            differences: JacMethod::Forward,
+            exact_jacobian: setup.exact_jacobian,
+            evaluations: Cell::new(0),
+        }
+    }
+
+    /// Differentiates by `differences` with steps of `diff_step` relative to the parameters,
+    /// or of the default step of the `differences` for `None`.
+    pub(crate) fn with_differences(
+        mut self,
+        diff_step: Option<f64>,
+        differences: JacMethod,
+    ) -> Self {
+        self.diff_step = diff_step.unwrap_or(match differences {
+            JacMethod::Forward => DIFF_STEP,
+            JacMethod::Central => CENTRAL_DIFF_STEP,
+        });
+        self.differences = differences;
+        self
+    }
+
+    /// Number of parameters that are fitted rather than fixed.
+    pub(crate) fn n_free(&self) -> usize {
+        self.free.len()
+    }
+
+    /// The regularization weight of each parameter, zero for the fixed ones.
+    fn penalty_weights(&self) -> [f64; N] {
+        let lambda = self.regularization.unwrap_or(0.0);
+        std::array::from_fn(|i| if self.fixed[i].is_none() { lambda } else { 0.0 })
+    }
+
+    /// The entries of the free parameters of `full`.
+    fn reduce(&self, full: &[f64; N]) -> DVector<f64> {
+        DVector::from_iterator(self.free.len(), self.free.iter().map(|&i| full[i]))
+    }
+
+    /// All parameters for the free ones `p`, with the fixed values in place.
+    fn params(&self, p: &DVector<f64>) -> [f64; N] {
+        let mut params = self.fixed.map(|value| value.unwrap_or(f64::NAN));
+        for (k, &i) in self.free.iter().enumerate() {
+            params[i] = p[k];
+        }
+        params
+    }
+
+    /// Scales of the free parameters.
+    fn scales(&self, scaling: &Scaling<N>, jac: &DMatrix<f64>) -> Scales {
+        let fixed = match scaling {
+            Scaling::Fixed(scale) => Some(self.reduce(scale)),
+            Scaling::Jacobian => None,
+        };
+        Scales::new(fixed, jac)
+    }
+
+    /// Lower and upper bounds of the free parameters.
+    fn bounds(&self, bounds: &Bounds<N>) -> (DVector<f64>, DVector<f64>) {
+        (self.reduce(&bounds.lower), self.reduce(&bounds.upper))
+    }
+
+    fn data_residuals(&self, p: &DVector<f64>) -> DVector<f64> {
+        self.evaluations.set(self.evaluations.get() + 1);
+        DVector::from_vec(self.source.residuals(&self.params(p)))
+    }
+
+    /// Number of data residuals among the residuals `r`, which end with a row for each free
+    /// parameter when regularized.
+    fn n_data(&self, r: &DVector<f64>) -> usize {
+        match self.regularization {
+            Some(_) => r.len() - self.free.len(),
+            None => r.len(),
+        }
+    }
+
+    /// Residuals of the data, followed by `sqrt(lambda) * (p - reference)` when regularized, so
+    /// that the solvers minimize the penalized sum of squares without knowing about the penalty.
+    fn residuals(&self, p: &DVector<f64>) -> DVector<f64> {
+        let r = self.data_residuals(p);
+        match self.regularization {
+            Some(lambda) => {
+                let (m, n) = (r.len(), p.len());
+                let mut r = r.resize_vertically(m + n, 0.0);
+                let offset = p - self.reduce(&self.reference);
+                r.rows_mut(m, n).copy_from(&(offset * lambda.sqrt()));
+                r
+            }
+            None => r,
+        }
+    }
+
+    /// Jacobian of [`Problem::residuals`], `r` being the residuals at `p`. The data rows are
+    /// forward differences, or backward ones where the forward step would cross an upper bound;
+    /// central differences fall back to these where either step would cross a bound. The
+    /// regularization rows are exactly `sqrt(lambda) * I`. With [`Setup::exact_jacobian`],
+    /// [`crate::Problem::jacobian`] replaces the differences.
+    fn jacobian(&self, p: &DVector<f64>, r: &DVector<f64>, bounds: &Bounds<N>) -> DMatrix<f64> {
+        let m = self.n_data(r);
+        let mut jac = DMatrix::zeros(r.len(), p.len());
+        let rows = self
+            .exact_jacobian
+            .then(|| self.source.jacobian(&self.params(p)));
+        let mut p_step = p.clone();
+        for (j, &i) in self.free.iter().enumerate() {
+            let h = self.diff_step * p[j].abs().max(1.0);
+            let (forward, backward) = (p[j] + h, p[j] - h);
+            let column = if let Some(rows) = &rows {
+                DVector::from_iterator(m, rows.iter().map(|row| row[i]))
+            } else if self.differences == JacMethod::Central
+                && forward <= bounds.upper[i]
+                && backward >= bounds.lower[i]
+            {
+                p_step[j] = forward;
+                let r_forward = self.data_residuals(&p_step);
+                p_step[j] = backward;
+                // the steps that are actually representable
+                (r_forward - self.data_residuals(&p_step)) / (forward - backward)
+            } else {
+                p_step[j] = if forward > bounds.upper[i] {
+                    backward
+                } else {
+                    forward
+                };
+                // use the step that is actually representable
+                let h = p_step[j] - p[j];
+                (self.data_residuals(&p_step) - r.rows(0, m)) / h
+            };
+            jac.view_mut((0, j), (m, 1)).copy_from(&column);
+            p_step[j] = p[j];
+        }
+        if let Some(lambda) = self.regularization {
+            jac.rows_mut(m, p.len()).fill_diagonal(lambda.sqrt());
+        }
+        jac
+    }
+
+    /// Whether residuals and Jacobian are finite at `p0`, i.e. whether the derivative-based
+    /// solvers can take a first step.
+    pub(crate) fn jacobian_is_finite(&self, p0: [f64; N], bounds: &Bounds<N>) -> bool {
+        let p = self.reduce(&p0);
+        let r = self.residuals(&p);
+        r.iter().all(|r| r.is_finite())
+            && self.jacobian(&p, &r, bounds).iter().all(|j| j.is_finite())
+    }
+
+    /// Cost and Jacobian of the data alone, leaving out the regularization rows. The Jacobian
+    /// has a column for each of the `N` parameters, zero for the fixed ones.
+    fn data_part(&self, r: &DVector<f64>, jac: DMatrix<f64>) -> (f64, DMatrix<f64>) {
+        let m = self.n_data(r);
+        let mut data_jac = DMatrix::zeros(m, N);
+        for (j, &i) in self.free.iter().enumerate() {
+            data_jac.set_column(i, &jac.column(j).rows(0, m));
+        }
+        (0.5 * r.rows(0, m).norm_squared(), data_jac)
+    }
+}
+
+/// Why a solver stopped iterating. The first three are convergence, the tolerances being 1e-8,
+/// and so is `Epochs`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TerminationReason {
+    /// The gradient norm dropped below the tolerance.
+    Gtol,
+    /// The relative cost reduction dropped below the tolerance.
+    Ftol,
+    /// The relative step size dropped below the tolerance.
+    Xtol,
+    /// The budget of residual evaluations was exhausted.
+    MaxEvaluations,
+    /// The residuals or the Jacobian were not finite at the current point.
+    NonFinite,
+    /// The Levenberg-Marquardt damping grew past `LmOptions::lambda_max`, every step having
+    /// been rejected until the trust region collapsed.
+    MaxDamping,
+    /// Stochastic gradient descent made all of its epochs, which is how it ends: its noisy
+    /// steps never meet the tolerances above.
+    Epochs,
+    /// `Config::timeout` expired, `elapsed` after the solver started.
+    Timeout { elapsed: Duration },
+}
+
+/// The wall-clock budget of a solver, counted from its creation.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Deadline {
+    start: Instant,
+    timeout: Option<Duration>,
+}
+
+impl Deadline {
+    pub(crate) fn new(timeout: Option<Duration>) -> Self {
+        Self {
+            start: Instant::now(),
+            timeout,
+        }
+    }
+
+    /// The time since the start, once it exceeds the timeout.
+    pub(crate) fn expired(&self) -> Option<Duration> {
+        let timeout = self.timeout?;
+        let elapsed = self.start.elapsed();
+        (elapsed > timeout).then_some(elapsed)
+    }
+}
+
+/// One iteration of a solver, or one step it rejected, as recorded with
+/// [`Config::record_trace`](crate::Config::record_trace).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TraceEntry<const N: usize> {
+    /// The iteration, counting from 1, or the one a rejected step was tried in.
+    pub iteration: usize,
+    /// The parameters after the iteration, or those a rejected step tried.
+    pub params: [f64; N],
+    /// Sum of squares at `params`, including any regularization penalty; NaN for a rejected
+    /// step to non-finite residuals.
+    pub ssr: f64,
+    /// Norm of the change of the parameters, from those after the previous iteration; zero for
+    /// an iteration that ended without a step.
+    pub step_norm: f64,
+    /// For LM the damping, for TRF and DogBox the trust radius, as it stood after the
+    /// iteration or when the rejected step was taken; `None` for the other methods.
+    pub damping: Option<f64>,
+    /// Whether this is a step the solver rejected rather than an iteration.
+    pub rejected: bool,
+}
+
+/// The trace entries of a solver run, kept while it iterates.
+pub(crate) struct Tracer<const N: usize> {
+    /// Whether to keep the rejected steps too.
+    rejected: bool,
+    entries: RefCell<Vec<TraceEntry<N>>>,
+    /// Parameters after the latest iteration.
+    latest: Cell<Option<[f64; N]>>,
+}
+
+impl<const N: usize> Tracer<N> {
+    pub(crate) fn new(rejected: bool) -> Self {
+        Self {
+            rejected,
+            entries: RefCell::new(vec![]),
+            latest: Cell::new(None),
+        }
+    }
+
+    pub(crate) fn into_entries(self) -> Vec<TraceEntry<N>> {
+        self.entries.into_inner()
+    }
+
+    fn push(
+        &self,
+        iteration: usize,
+        params: [f64; N],
+        cost: f64,
+        damping: Option<f64>,
+        rejected: bool,
+    ) {
+        let step_norm = match self.latest.get() {
+            Some(latest) => params
+                .iter()
+                .zip(latest)
+                .map(|(p, q)| (p - q).powi(2))
+                .sum::<f64>()
+                .sqrt(),
+            None => f64::NAN,
+        };
+        self.entries.borrow_mut().push(TraceEntry {
+            iteration,
+            params,
+            ssr: 2.0 * cost,
+            step_norm,
+            damping,
+            rejected,
+        });
+    }
+}
+
+/// Settings shared by all solvers.
+pub(crate) struct Options<'a, const N: usize> {
+    pub scaling: Scaling<N>,
+    /// Whether to keep the sum of squared residuals after every iteration.
+    pub record_history: bool,
+    /// Only honoured by LM.
+    pub lm: LmOptions,
+    /// Only honoured by TRF and DogBox.
+    pub tr: TrustRegionOptions,
+    pub deadline: Deadline,
+    /// Only honoured by TRF and DogBox.
+    pub bounds: Bounds<N>,
+    /// Called with the iteration, the parameters and the sum of squares at the initial guess,
+    /// as iteration 0, and after every iteration, like the recorded history.
+    pub callback: Option<&'a dyn Fn(usize, [f64; N], f64)>,
+    /// Keeps a trace of the iterations; only LM, TRF, DogBox and L-BFGS reject steps.
+    pub tracer: Option<Tracer<N>>,
+}
+
+impl<const N: usize> Options<'_, N> {
+    /// The history holding the initial `cost`, when it is recorded, after reporting `params`
+    /// and `cost` to the callback.
+    fn start_history(&self, params: [f64; N], cost: f64) -> Option<Vec<f64>> {
+        if let Some(callback) = self.callback {
+            callback(0, params, 2.0 * cost);
+        }
+        if let Some(tracer) = &self.tracer {
+            tracer.latest.set(Some(params));
+        }
+        self.record_history.then(|| vec![2.0 * cost])
+    }
+
+    /// Records the `cost` at `params` after `iteration`, with the `damping` or trust radius of
+    /// the solver for the trace.
+    fn record(
+        &self,
+        history: &mut Option<Vec<f64>>,
+        iteration: usize,
+        params: [f64; N],
+        cost: f64,
+        damping: Option<f64>,
+    ) {
+        if let Some(callback) = self.callback {
+            callback(iteration, params, 2.0 * cost);
+        }
+        if let Some(history) = history {
+            history.push(2.0 * cost);
+        }
+        if let Some(tracer) = &self.tracer {
+            tracer.push(iteration, params, cost, damping, false);
+            tracer.latest.set(Some(params));
+        }
+    }
+
+    /// Traces a step to `params` rejected in `iteration`, when rejected steps are traced.
+    fn reject(&self, iteration: usize, params: [f64; N], cost: f64, damping: Option<f64>) {
+        if let Some(tracer) = self.tracer.as_ref().filter(|tracer| tracer.rejected) {
+            tracer.push(iteration, params, cost, damping, true);
+        }
+    }
+}
+
+/// Box constraints `lower <= p <= upper`, infinite on open sides.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Bounds<const N: usize> {
+    pub lower: [f64; N],
+    pub upper: [f64; N],
+}
+
+impl<const N: usize> Bounds<N> {
+    pub(crate) const UNBOUNDED: Self = Self {
+        lower: [f64::NEG_INFINITY; N],
+        upper: [f64::INFINITY; N],
+    };
+
+    /// Whether any side is finite.
+    pub(crate) fn is_bounded(&self) -> bool {
+        self.lower.iter().chain(&self.upper).any(|b| b.is_finite())
+    }
+
+    /// Whether each parameter is within `XTOL * max(|bound|, 1)` of a finite lower or upper
+    /// bound.
+    pub(crate) fn active(&self, p: &[f64; N]) -> [bool; N] {
+        let near = |distance: f64, bound: f64| {
+            bound.is_finite() && distance <= XTOL * bound.abs().max(1.0)
+        };
+        std::array::from_fn(|i| {
+            near(p[i] - self.lower[i], self.lower[i]) || near(self.upper[i] - p[i], self.upper[i])
+        })
+    }
+}
+
+/// Characteristic scale `d` of each parameter; the solvers step in the variables `p / d`,
+/// so parameters of very different magnitudes get comparable trust regions and damping.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Scaling<const N: usize> {
+    Fixed([f64; N]),
+    /// Inverse column norms of the Jacobian, like scipy's `x_scale='jac'`. Updated with every
+    /// new Jacobian, keeping the largest norm seen so far.
+    Jacobian,
+}
+
+/// Scales of a running solver, stored as their inverses `1 / d`.
+struct Scales {
+    inverse: DVector<f64>,
+    from_jacobian: bool,
+}
+
+impl Scales {
+    /// The `fixed` scales of `Scaling::Fixed`, or those of `Scaling::Jacobian` for `None`.
+    fn new(fixed: Option<DVector<f64>>, jac: &DMatrix<f64>) -> Self {
+        match fixed {
+            Some(scale) => Self {
+                inverse: scale.map(|d| 1.0 / d),
+                from_jacobian: false,
+            },
+            None => Self {
+                inverse: column_norms(jac).map(|norm| if norm == 0.0 { 1.0 } else { norm }),
+                from_jacobian: true,
+            },
+        }
+    }
+
+    fn update(&mut self, jac: &DMatrix<f64>) {
+        if self.from_jacobian {
+            self.inverse = self.inverse.zip_map(&column_norms(jac), f64::max);
+        }
+    }
+
+    fn scale(&self) -> DVector<f64> {
+        self.inverse.map(|inverse| 1.0 / inverse)
+    }
+}
+
+/// Final state of a solver run.
+pub(crate) struct Solution<const N: usize> {
+    pub params: [f64; N],
+    /// `0.5 * sum(r_i^2)` at `params`, over the data residuals only.
+    pub cost: f64,
+    /// Jacobian of the data residuals at `params`.
+    pub jacobian: DMatrix<f64>,
+    pub termination: TerminationReason,
+    /// Number of outer iterations, each ending with an accepted step or a termination.
+    pub iterations: usize,
+    /// Number of evaluations of the model over the data set.
+    pub n_fev: usize,
+    /// Minimized sum of squares, including any regularization penalty, at `p0` and after each
+    /// iteration, if it was recorded.
+    pub history: Option<Vec<f64>>,
+    pub method: Method,
+    /// Which parameters ended on one of their bounds.
+    pub at_bounds: [bool; N],
+    /// Number of parameters that were fitted rather than fixed.
+    pub n_free: usize,
+    /// Regularization weight `lambda` of each parameter, zero for the fixed ones and without
+    /// regularization.
+    pub regularization: [f64; N],
+    /// Final trust radius of TRF and DogBox.
+    pub trust_radius: Option<f64>,
+    /// The trace of the run, filled in from [`Options::tracer`] by the caller.
+    pub trace: Option<Vec<TraceEntry<N>>>,
+}
+
+impl<const N: usize> Solution<N> {
+    /// Whether the solver stopped on a convergence criterion at a finite point.
+    pub(crate) fn converged(&self) -> bool {
+        matches!(
+            self.termination,
+            TerminationReason::Gtol
+                | TerminationReason::Ftol
+                | TerminationReason::Xtol
+                | TerminationReason::Epochs
+        ) && self.cost.is_finite()
+            && self.params.iter().all(|p| p.is_finite())
+    }
+}
+
+/// Covariance `s^2 (J^T J + diag(lambda))^-1` of the parameters, `s^2 = ssr / (m - n_free)`
+/// estimating the variance of the data, `n_free` counting the fitted parameters and `lambda`
+/// holding the regularization weight of each, so that the penalty shrinks the covariance as
+/// it shrinks the parameters. Computed through a pseudo-inverse of `J` stacked on the rows
+/// `sqrt(lambda) * I`, which drops singular values below `EPSILON * max(m, N) * s_max` and so
+/// leaves zeros for the zero columns of fixed parameters; without degrees of freedom every
+/// entry is infinite.
+pub(crate) fn covariance<const N: usize>(
+    jacobian: &DMatrix<f64>,
+    ssr: f64,
+    n_free: usize,
+    lambda: [f64; N],
+) -> [[f64; N]; N] {
+    let m = jacobian.nrows();
+    let mut stacked = jacobian.clone().resize_vertically(m + N, 0.0);
+    for (i, lambda) in lambda.iter().enumerate() {
+        stacked[(m + i, i)] = lambda.sqrt();
+    }
+    let covariance = stacked_covariance(&stacked, m, ssr, n_free);
+    std::array::from_fn(|i| std::array::from_fn(|j| covariance[(i, j)]))
+}
+
+/// [`covariance`] for any number of parameters, the columns of `jacobian`, without
+/// regularization.
+pub(crate) fn covariance_matrix(jacobian: &DMatrix<f64>, ssr: f64, n_free: usize) -> DMatrix<f64> {
+    stacked_covariance(jacobian, jacobian.nrows(), ssr, n_free)
+}
+
+/// [`covariance`] of `jacobian`, whose first `m` rows are those of the data and any further
+/// ones those of the regularization.
+fn stacked_covariance(jacobian: &DMatrix<f64>, m: usize, ssr: f64, n_free: usize) -> DMatrix<f64> {
+    let n = jacobian.ncols();
+    if jacobian.iter().any(|j| !j.is_finite()) {
+        return DMatrix::from_element(n, n, f64::NAN);
+    }
+    if m <= n_free {
+        return DMatrix::from_element(n, n, f64::INFINITY);
+    }
+    let svd = jacobian.clone().svd(false, true);
+    let v_t = svd.v_t.expect("V was computed");
+    let threshold = f64::EPSILON * m.max(n) as f64 * svd.singular_values.max();
+    let variance = ssr / (m - n_free) as f64;
+    let mut covariance = DMatrix::zeros(n, n);
+    for (k, &s) in svd.singular_values.iter().enumerate() {
+        if s > threshold {
+            let v = v_t.row(k).transpose();
+            covariance += &v * v.transpose() * (variance / (s * s));
+        }
+    }
+    covariance
+}
+
+pub(crate) fn levenberg_marquardt<const N: usize>(
+    problem: &Problem<'_, N>,
+    p0: [f64; N],
+    options: &Options<N>,
+) -> Solution<N> {
+    let n = problem.n_free();
+    let max_evaluations = 100 * (n + 1);
+    let mut p = problem.reduce(&p0);
+    let mut r = problem.residuals(&p);
+    let mut evaluations = 1;
+    let mut cost = 0.5 * r.norm_squared();
+    let mut jac = problem.jacobian(&p, &r, &options.bounds);
+    let mut scales = problem.scales(&options.scaling, &jac);
+    let (mut damping, max_damping) =
+        initial_damping(&options.lm, &scale_columns(&jac, &scales.scale()));
+    let mut iteration = 0;
+    let mut history = options.start_history(problem.params(&p), cost);
+
+    let termination = loop {
+        if !cost.is_finite() || jac.iter().any(|j| !j.is_finite()) {
+            break TerminationReason::NonFinite;
+        }
+        let gradient = jac.tr_mul(&r);
+        let gradient_norm = gradient.amax();
+        if gradient_norm < GTOL {
+            break TerminationReason::Gtol;
+        }
+        if evaluations >= max_evaluations {
+            break TerminationReason::MaxEvaluations;
+        }
+        if let Some(elapsed) = options.deadline.expired() {
+            break TerminationReason::Timeout { elapsed };
+        }
+        if damping > max_damping {
+            break TerminationReason::MaxDamping;
+        }
+        if trace::ENABLED && is_near_singular(&jac) {
+            trace::near_singular(Method::LM, iteration);
+        }
+
+        // the damped system is solved for the scaled step `step / d`
+        let d = scales.scale();
+        let jac_h = scale_columns(&jac, &d);
+        let gradient_h = gradient.component_mul(&d);
+        let system = DampedSvd::new(&jac_h, &r, options.lm.svd_threshold);
+        let mut termination = None;
+        let mut accepted = false;
+        while !accepted
+            && termination.is_none()
+            && evaluations < max_evaluations
+            && damping <= max_damping
+        {
+            let step_h = system.step(damping);
+            let step = step_h.component_mul(&d);
+            let p_new = &p + &step;
+            let r_new = problem.residuals(&p_new);
+            evaluations += 1;
+
+            let cost_new = 0.5 * r_new.norm_squared();
+            let actual_reduction = cost - cost_new;
+            let predicted_reduction = -evaluate_quadratic(&jac_h, &gradient_h, &step_h, None);
+            let ratio = gain_ratio(actual_reduction, predicted_reduction);
+            let step_norm = step.norm();
+            termination = check_termination(actual_reduction, cost, step_norm, p.norm(), ratio);
+
+            if actual_reduction > 0.0 {
+                trace::damped_iteration(iteration, cost_new, damping, step_norm, gradient_norm);
+                damping /= options.lm.lambda_down_factor;
+                p = p_new;
+                r = r_new;
+                cost = cost_new;
+                accepted = true;
+            } else {
+                trace::rejected_step(Method::LM, iteration, cost_new, step_norm);
+                options.reject(
+                    iteration + 1,
+                    problem.params(&p_new),
+                    cost_new,
+                    Some(damping),
+                );
+                damping *= options.lm.lambda_up_factor;
+            }
+        }
+
+        iteration += 1;
+        options.record(
+            &mut history,
+            iteration,
+            problem.params(&p),
+            cost,
+            Some(damping),
+        );
+        if accepted {
+            jac = problem.jacobian(&p, &r, &options.bounds);
+            scales.update(&jac);
+        }
+        if let Some(termination) = termination {
+            break termination;
+        }
+    };
+
+    trace::finished(Method::LM, termination, iteration, cost);
+    let (cost, jacobian) = problem.data_part(&r, jac);
+    let params = problem.params(&p);
+    Solution {
+        params,
+        cost,
+        jacobian,
+        termination,
+        iterations: iteration,
+        n_fev: problem.evaluations.get(),
+        history,
+        method: Method::LM,
+        at_bounds: options.bounds.active(&params),
+        n_free: problem.n_free(),
+        regularization: problem.penalty_weights(),
+        trust_radius: None,
+        trace: None,
+    }
+}
+
+pub(crate) fn trust_region_reflective<const N: usize>(
+    problem: &Problem<'_, N>,
+    p0: [f64; N],
+    options: &Options<N>,
+) -> Solution<N> {
+    if options.bounds.is_bounded() {
+        return trust_region_reflective_bounded(problem, p0, options);
+    }
+    let n = problem.n_free();
+    let max_evaluations = 100 * (n + 1);
+    let mut p = problem.reduce(&p0);
+    let mut r = problem.residuals(&p);
+    let mut evaluations = 1;
+    let mut cost = 0.5 * r.norm_squared();
+    let mut jac = problem.jacobian(&p, &r, &options.bounds);
+    let mut scales = problem.scales(&options.scaling, &jac);
+    let mut radius = initial_radius(&options.tr, p.component_mul(&scales.inverse).norm());
+    // Levenberg-Marquardt parameter of the last subproblem, reused as a warm start
+    let mut alpha = 0.0;
+    let mut iteration = 0;
+    let mut history = options.start_history(problem.params(&p), cost);
+
+    let termination = loop {
+        if !cost.is_finite() || jac.iter().any(|j| !j.is_finite()) {
+            break TerminationReason::NonFinite;
+        }
+        let gradient = jac.tr_mul(&r);
+        let gradient_norm = gradient.amax();
+        if gradient_norm < GTOL {
+            break TerminationReason::Gtol;
+        }
+        if evaluations >= max_evaluations {
+            break TerminationReason::MaxEvaluations;
+        }
+        if let Some(elapsed) = options.deadline.expired() {
+            break TerminationReason::Timeout { elapsed };
+        }
+        if trace::ENABLED && is_near_singular(&jac) {
+            trace::near_singular(Method::TRF, iteration);
+        }
+
+        // the trust region is a ball in the scaled variables `p / d`
+        let d = scales.scale();
+        let jac_h = scale_columns(&jac, &d);
+        let gradient_h = gradient.component_mul(&d);
+        let svd = jac_h.clone().svd(true, true);
+        let (u, v_t) = (svd.u.unwrap(), svd.v_t.unwrap());
+        let uf = u.tr_mul(&r);
+        let mut termination = None;
+        let mut accepted = false;
+        while !accepted && termination.is_none() && evaluations < max_evaluations {
+            let step_h;
+            (step_h, alpha) = solve_trust_region_subproblem(
+                jac.nrows(),
+                &uf,
+                &svd.singular_values,
+                &v_t,
+                radius,
+                alpha,
+            );
+            let predicted_reduction = -evaluate_quadratic(&jac_h, &gradient_h, &step_h, None);
+            let step = step_h.component_mul(&d);
+            let p_new = &p + &step;
+            let r_new = problem.residuals(&p_new);
+            evaluations += 1;
+
+            let step_norm = step.norm();
+            let step_h_norm = step_h.norm();
+            if !r_new.iter().all(|r| r.is_finite()) {
+                trace::rejected_step(Method::TRF, iteration, f64::NAN, step_norm);
+                options.reject(
+                    iteration + 1,
+                    problem.params(&p_new),
+                    f64::NAN,
+                    Some(radius),
+                );
+                radius = options.tr.shrink_factor * step_h_norm;
+                continue;
+            }
+
+            let cost_new = 0.5 * r_new.norm_squared();
+            let actual_reduction = cost - cost_new;
+            let (radius_new, ratio) = update_trust_radius(
+                &options.tr,
+                radius,
+                actual_reduction,
+                predicted_reduction,
+                step_h_norm,
+                step_h_norm > 0.95 * radius,
+            );
+            termination = check_termination(actual_reduction, cost, step_norm, p.norm(), ratio);
+            if termination.is_none() {
+                alpha *= radius / radius_new;
+                radius = radius_new;
+            }
+
+            if actual_reduction > 0.0 && ratio >= options.tr.eta_accept {
+                trace::trust_region_iteration(
+                    Method::TRF,
+                    iteration,
+                    cost_new,
+                    radius,
+                    step_norm,
+                    gradient_norm,
+                );
+                p = p_new;
+                r = r_new;
+                cost = cost_new;
+                accepted = true;
+            } else {
+                trace::rejected_step(Method::TRF, iteration, cost_new, step_norm);
+                options.reject(
+                    iteration + 1,
+                    problem.params(&p_new),
+                    cost_new,
+                    Some(radius),
+                );
+            }
+        }
+
+        iteration += 1;
+        options.record(
+            &mut history,
+            iteration,
+            problem.params(&p),
+            cost,
+            Some(radius),
+        );
+        if accepted {
+            jac = problem.jacobian(&p, &r, &options.bounds);
+            scales.update(&jac);
+        }
+        if let Some(termination) = termination {
+            break termination;
+        }
+    };
+
+    trace::finished(Method::TRF, termination, iteration, cost);
+    let (cost, jacobian) = problem.data_part(&r, jac);
+    let params = problem.params(&p);
+    Solution {
+        params,
+        cost,
+        jacobian,
+        termination,
+        iterations: iteration,
+        n_fev: problem.evaluations.get(),
+        history,
+        method: Method::TRF,
+        at_bounds: options.bounds.active(&params),
+        n_free: problem.n_free(),
+        regularization: problem.penalty_weights(),
+        trust_radius: Some(radius),
+        trace: None,
+    }
+}
+
+/// TRF with bounds (Branch, Coleman and Li, "A subspace, interior, and conjugate gradient method
+/// for large-scale bound-constrained minimization problems"): the iterates stay strictly inside
+/// the bounds, the variables are scaled by their distance to the bound the gradient points at,
+/// and a step leaving the box is replaced by the best of the step cut at the boundary, its
+/// reflection off the boundary and a Cauchy step.
+fn trust_region_reflective_bounded<const N: usize>(
+    problem: &Problem<'_, N>,
+    p0: [f64; N],
+    options: &Options<N>,
+) -> Solution<N> {
+    let (lower, upper) = problem.bounds(&options.bounds);
+    let n = problem.n_free();
+    let max_evaluations = 100 * (n + 1);
+    let mut p = make_strictly_feasible(&problem.reduce(&p0), &lower, &upper, 1e-10);
+    let mut r = problem.residuals(&p);
+    let mut evaluations = 1;
+    let mut cost = 0.5 * r.norm_squared();
+    let mut jac = problem.jacobian(&p, &r, &options.bounds);
+    let mut scales = problem.scales(&options.scaling, &jac);
+    let (mut v, dv) = coleman_li_scaling(&p, &jac.tr_mul(&r), &lower, &upper);
+    for i in (0..n).filter(|&i| dv[i] != 0.0) {
+        v[i] *= scales.inverse[i];
+    }
+    let scaled_p0 = p
+        .component_mul(&scales.inverse)
+        .component_div(&v.map(f64::sqrt));
+    let mut radius = initial_radius(&options.tr, scaled_p0.norm());
+    let mut alpha = 0.0;
+    let mut iteration = 0;
+    let mut history = options.start_history(problem.params(&p), cost);
+
+    let termination = loop {
+        if !cost.is_finite() || jac.iter().any(|j| !j.is_finite()) {
+            break TerminationReason::NonFinite;
+        }
+        let gradient = jac.tr_mul(&r);
+        let (mut v, dv) = coleman_li_scaling(&p, &gradient, &lower, &upper);
+        let gradient_norm = gradient.component_mul(&v).amax();
+        if gradient_norm < GTOL {
+            break TerminationReason::Gtol;
+        }
+        if evaluations >= max_evaluations {
+            break TerminationReason::MaxEvaluations;
+        }
+        if let Some(elapsed) = options.deadline.expired() {
+            break TerminationReason::Timeout { elapsed };
+        }
+        if trace::ENABLED && is_near_singular(&jac) {
+            trace::near_singular(Method::TRF, iteration);
+        }
+
+        // the Coleman-Li scaling applies on top of the parameter scales
+        let scale = scales.scale();
+        for i in (0..n).filter(|&i| dv[i] != 0.0) {
+            v[i] *= scales.inverse[i];
+        }
+        let d = v.map(f64::sqrt).component_mul(&scale);
+        let diag_h = gradient.component_mul(&dv).component_mul(&scale);
+        let gradient_h = gradient.component_mul(&d);
+        let jac_h = scale_columns(&jac, &d);
+        // the subproblem of the scaled variables is that of `[J_h; diag(sqrt(diag_h))]`
+        let m = jac.nrows();
+        let mut jac_augmented = jac_h.clone().resize_vertically(m + n, 0.0);
+        for i in 0..n {
+            jac_augmented[(m + i, i)] = diag_h[i].sqrt();
+        }
+        let svd = jac_augmented.svd(true, true);
+        let (u, v_t) = (svd.u.unwrap(), svd.v_t.unwrap());
+        let uf = u.tr_mul(&r.clone().resize_vertically(m + n, 0.0));
+        // fraction of the way to the boundary a step may go
+        let theta = f64::max(0.995, 1.0 - gradient_norm);
+
+        let mut termination = None;
+        let mut accepted = false;
+        while !accepted && termination.is_none() && evaluations < max_evaluations {
+            let step_h;
+            (step_h, alpha) =
+                solve_trust_region_subproblem(m, &uf, &svd.singular_values, &v_t, radius, alpha);
+            let (step, step_h, predicted_reduction) = select_step(
+                &p,
+                &jac_h,
+                &diag_h,
+                &gradient_h,
+                step_h,
+                &d,
+                radius,
+                &lower,
+                &upper,
+                theta,
+            );
+            let p_new = make_strictly_feasible(&(&p + &step), &lower, &upper, 0.0);
+            let r_new = problem.residuals(&p_new);
+            evaluations += 1;
+
+            let step_norm = step.norm();
+            let step_h_norm = step_h.norm();
+            if !r_new.iter().all(|r| r.is_finite()) {
+                trace::rejected_step(Method::TRF, iteration, f64::NAN, step_norm);
+                options.reject(
+                    iteration + 1,
+                    problem.params(&p_new),
+                    f64::NAN,
+                    Some(radius),
+                );
+                radius = options.tr.shrink_factor * step_h_norm;
+                continue;
+            }
+
+            let cost_new = 0.5 * r_new.norm_squared();
+            let actual_reduction = cost - cost_new;
+            let (radius_new, ratio) = update_trust_radius(
+                &options.tr,
+                radius,
+                actual_reduction,
+                predicted_reduction,
+                step_h_norm,
+                step_h_norm > 0.95 * radius,
+            );
+            termination = check_termination(actual_reduction, cost, step_norm, p.norm(), ratio);
+            if termination.is_none() {
+                alpha *= radius / radius_new;
+                radius = radius_new;
+            }
+
+            if actual_reduction > 0.0 && ratio >= options.tr.eta_accept {
+                trace::trust_region_iteration(
+                    Method::TRF,
+                    iteration,
+                    cost_new,
+                    radius,
+                    step_norm,
+                    gradient_norm,
+                );
+                p = p_new;
+                r = r_new;
+                cost = cost_new;
+                accepted = true;
+            } else {
+                trace::rejected_step(Method::TRF, iteration, cost_new, step_norm);
+                options.reject(
+                    iteration + 1,
+                    problem.params(&p_new),
+                    cost_new,
+                    Some(radius),
+                );
+            }
+        }
+
+        iteration += 1;
+        options.record(
+            &mut history,
+            iteration,
+            problem.params(&p),
+            cost,
+            Some(radius),
+        );
+        if accepted {
+            jac = problem.jacobian(&p, &r, &options.bounds);
+            scales.update(&jac);
+        }
+        if let Some(termination) = termination {
+            break termination;
+        }
+    };
+
+    trace::finished(Method::TRF, termination, iteration, cost);
+    let (cost, jacobian) = problem.data_part(&r, jac);
+    let params = problem.params(&p);
+    Solution {
+        params,
+        cost,
+        jacobian,
+        termination,
+        iterations: iteration,
+        n_fev: problem.evaluations.get(),
+        history,
+        method: Method::TRF,
+        at_bounds: options.bounds.active(&params),
+        n_free: problem.n_free(),
+        regularization: problem.penalty_weights(),
+        trust_radius: Some(radius),
+        trace: None,
+    }
+}
+
+pub(crate) fn dogbox<const N: usize>(
+    problem: &Problem<'_, N>,
+    p0: [f64; N],
+    options: &Options<N>,
+) -> Solution<N> {
+    let (lower, upper) = problem.bounds(&options.bounds);
+    let n = problem.n_free();
+    let max_evaluations = 100 * (n + 1);
+    let mut p = problem.reduce(&p0);
+    let mut r = problem.residuals(&p);
+    let mut evaluations = 1;
+    let mut cost = 0.5 * r.norm_squared();
+    let mut jac = problem.jacobian(&p, &r, &options.bounds);
+    let mut scales = problem.scales(&options.scaling, &jac);
+    // the trust region is a box, so its radius is measured in the infinity norm
+    let mut radius = initial_radius(&options.tr, p.component_mul(&scales.inverse).amax());
+    // -1 or 1 for a parameter on its lower or upper bound
+    let mut on_bound: Vec<i8> = (0..n)
+        .map(|i| {
+            if p[i] == lower[i] {
+                -1
+            } else if p[i] == upper[i] {
+                1
+            } else {
+                0
+            }
+        })
+        .collect();
+    let mut iteration = 0;
+    let mut history = options.start_history(problem.params(&p), cost);
+
+    let termination = loop {
+        if !cost.is_finite() || jac.iter().any(|j| !j.is_finite()) {
+            break TerminationReason::NonFinite;
+        }
+        let gradient = jac.tr_mul(&r);
+        // parameters on a bound that the gradient pushes them against stay where they are
+        let free: Vec<usize> = (0..n)
+            .filter(|&i| f64::from(on_bound[i]) * gradient[i] >= 0.0)
+            .collect();
+        let gradient_norm = free.iter().map(|&i| gradient[i].abs()).fold(0.0, f64::max);
+        if gradient_norm < GTOL {
+            break TerminationReason::Gtol;
+        }
+        if evaluations >= max_evaluations {
+            break TerminationReason::MaxEvaluations;
+        }
+        if let Some(elapsed) = options.deadline.expired() {
+            break TerminationReason::Timeout { elapsed };
+        }
+        if trace::ENABLED && is_near_singular(&jac) {
+            trace::near_singular(Method::DogBox, iteration);
+        }
+
+        let select =
+            |v: &DVector<f64>| DVector::from_iterator(free.len(), free.iter().map(|&i| v[i]));
+        let jac_free = jac.select_columns(&free);
+        let gradient_free = select(&gradient);
+        let (p_free, lower_free, upper_free) = (select(&p), select(&lower), select(&upper));
+        let newton_step = jac_free
+            .clone()
+            .svd(true, true)
+            .solve(&-&r, f64::EPSILON)
+            .expect("U and V were computed");
+        // the model along the steepest descent direction is a*t^2 + b*t
+        let (a, b, _) = build_quadratic_1d(&jac_free, &gradient_free, &-&gradient_free, None, None);
+
+        // the side of the box along each parameter is proportional to its scale
+        let d = scales.scale();
+        let d_free = select(&d);
+        let mut termination = None;
+        let mut accepted = false;
+        while !accepted && termination.is_none() && evaluations < max_evaluations {
+            let (step_free, on_bound_free, radius_hit) = dogleg_step(
+                &p_free,
+                &newton_step,
+                &gradient_free,
+                a,
+                b,
+                &(&d_free * radius),
+                &lower_free,
+                &upper_free,
+            );
+            let predicted_reduction =
+                -evaluate_quadratic(&jac_free, &gradient_free, &step_free, None);
+            let mut step = DVector::zeros(n);
+            for (k, &i) in free.iter().enumerate() {
+                step[i] = step_free[k];
+            }
+            let p_new =
+                (&p + &step).zip_zip_map(&lower, &upper, |p, lower, upper| p.clamp(lower, upper));
+            let r_new = problem.residuals(&p_new);
+            evaluations += 1;
+
+            let step_norm = step.norm();
+            let step_size = step.component_div(&d).amax();
+            if !r_new.iter().all(|r| r.is_finite()) {
+                trace::rejected_step(Method::DogBox, iteration, f64::NAN, step_norm);
+                options.reject(
+                    iteration + 1,
+                    problem.params(&p_new),
+                    f64::NAN,
+                    Some(radius),
+                );
+                radius = options.tr.shrink_factor * step_size;
+                continue;
+            }
+
+            let cost_new = 0.5 * r_new.norm_squared();
+            let actual_reduction = cost - cost_new;
+            let ratio;
+            (radius, ratio) = update_trust_radius(
+                &options.tr,
+                radius,
+                actual_reduction,
+                predicted_reduction,
+                step_size,
+                radius_hit,
+            );
+            termination = check_termination(actual_reduction, cost, step_norm, p.norm(), ratio);
+
+            if actual_reduction > 0.0 && ratio >= options.tr.eta_accept {
+                trace::trust_region_iteration(
+                    Method::DogBox,
+                    iteration,
+                    cost_new,
+                    radius,
+                    step_norm,
+                    gradient_norm,
+                );
+                for (k, &i) in free.iter().enumerate() {
+                    on_bound[i] = on_bound_free[k];
+                }
+                p = p_new;
+                // put the parameters that hit a bound exactly on it
+                for i in 0..n {
+                    match on_bound[i] {
+                        -1 => p[i] = lower[i],
+                        1 => p[i] = upper[i],
+                        _ => {}
+                    }
+                }
+                r = r_new;
+                cost = cost_new;
+                accepted = true;
+            } else {
+                trace::rejected_step(Method::DogBox, iteration, cost_new, step_norm);
+                options.reject(
+                    iteration + 1,
+                    problem.params(&p_new),
+                    cost_new,
+                    Some(radius),
+                );
+            }
+        }
+
+        iteration += 1;
+        options.record(
+            &mut history,
+            iteration,
+            problem.params(&p),
+            cost,
+            Some(radius),
+        );
+        if accepted {
+            jac = problem.jacobian(&p, &r, &options.bounds);
+            scales.update(&jac);
+        }
+        if let Some(termination) = termination {
+            break termination;
+        }
+    };
+
+    trace::finished(Method::DogBox, termination, iteration, cost);
+    let (cost, jacobian) = problem.data_part(&r, jac);
+    let params = problem.params(&p);
+    Solution {
+        params,
+        cost,
+        jacobian,
+        termination,
+        iterations: iteration,
+        n_fev: problem.evaluations.get(),
+        history,
+        method: Method::DogBox,
+        at_bounds: options.bounds.active(&params),
+        n_free: problem.n_free(),
+        regularization: problem.penalty_weights(),
+        trust_radius: Some(radius),
+        trace: None,
+    }
+}
+
+/// Nelder-Mead simplex search on the cost, which needs neither a Jacobian nor finite residuals
+/// everywhere: vertices where the model is not finite count as infinitely bad. Parameter
+/// scales and bounds are ignored. The simplex starts with a 5% step along each parameter and
+/// stops once both its extent and the spread of its costs are below the tolerances.
+pub(crate) fn nelder_mead<const N: usize>(
+    problem: &Problem<'_, N>,
+    p0: [f64; N],
+    options: &Options<N>,
+) -> Solution<N> {
+    const REFLECTION: f64 = 1.0;
+    const EXPANSION: f64 = 2.0;
+    const CONTRACTION: f64 = 0.5;
+    const SHRINK: f64 = 0.5;
+
+    let n = problem.n_free();
+    let max_evaluations = 200 * n;
+    let evaluate = |p: DVector<f64>| {
+        let cost = 0.5 * problem.residuals(&p).norm_squared();
+        (p, if cost.is_nan() { f64::INFINITY } else { cost })
+    };
+    let p0 = problem.reduce(&p0);
+    let mut simplex = vec![evaluate(p0.clone())];
+    for i in 0..n {
+        let mut p = p0.clone();
+        p[i] = if p[i] != 0.0 { 1.05 * p[i] } else { 0.00025 };
+        simplex.push(evaluate(p));
+    }
+    let mut evaluations = n + 1;
+    simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+    let mut iteration = 0;
+    let mut history = options.start_history(problem.params(&simplex[0].0), simplex[0].1);
+
+    let termination = loop {
+        let (best, best_cost) = &simplex[0];
+        if !best_cost.is_finite() {
+            break TerminationReason::NonFinite;
+        }
+        let extent = simplex[1..]
+            .iter()
+            .map(|(p, _)| (p - best).amax())
+            .fold(0.0, f64::max);
+        let spread = simplex[n].1 - best_cost;
+        if extent <= XTOL * (XTOL + best.amax()) && spread <= FTOL * (FTOL + best_cost) {
+            break TerminationReason::Xtol;
+        }
+        if evaluations >= max_evaluations {
+            break TerminationReason::MaxEvaluations;
+        }
+        if let Some(elapsed) = options.deadline.expired() {
+            break TerminationReason::Timeout { elapsed };
+        }
+
+        let (worst, worst_cost) = simplex[n].clone();
+        let centroid = simplex[..n]
+            .iter()
+            .fold(DVector::zeros(n), |sum, (p, _)| sum + p)
+            / n as f64;
+        let along = |t: f64| &centroid + (&centroid - &worst) * t;
+        let reflected = evaluate(along(REFLECTION));
+        evaluations += 1;
+        let mut shrink = false;
+        if reflected.1 < simplex[0].1 {
+            let expanded = evaluate(along(REFLECTION * EXPANSION));
+            evaluations += 1;
+            simplex[n] = if expanded.1 < reflected.1 {
+                expanded
+            } else {
+                reflected
+            };
+        } else if reflected.1 < simplex[n - 1].1 {
+            simplex[n] = reflected;
+        } else if reflected.1 < worst_cost {
+            let contracted = evaluate(along(REFLECTION * CONTRACTION));
+            evaluations += 1;
+            if contracted.1 <= reflected.1 {
+                simplex[n] = contracted;
+            } else {
+                shrink = true;
+            }
+        } else {
+            let contracted = evaluate(along(-CONTRACTION));
+            evaluations += 1;
+            if contracted.1 < worst_cost {
+                simplex[n] = contracted;
+            } else {
+                shrink = true;
+            }
+        }
+        if shrink {
+            let best = simplex[0].0.clone();
+            for vertex in &mut simplex[1..] {
+                *vertex = evaluate(&best + (&vertex.0 - &best) * SHRINK);
+            }
+            evaluations += n;
+        }
+        simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+        iteration += 1;
+        options.record(
+            &mut history,
+            iteration,
+            problem.params(&simplex[0].0),
+            simplex[0].1,
+            None,
+        );
+    };
+
+    let p = simplex.swap_remove(0).0;
+    let r = problem.residuals(&p);
+    trace::finished(
+        Method::NelderMead,
+        termination,
+        iteration,
+        0.5 * r.norm_squared(),
+    );
+    let (cost, jacobian) = problem.data_part(&r, problem.jacobian(&p, &r, &options.bounds));
+    let params = problem.params(&p);
+    Solution {
+        params,
+        cost,
+        jacobian,
+        termination,
+        iterations: iteration,
+        n_fev: problem.evaluations.get(),
+        history,
+        method: Method::NelderMead,
+        at_bounds: options.bounds.active(&params),
+        n_free: problem.n_free(),
+        regularization: problem.penalty_weights(),
+        trust_radius: None,
+        trace: None,
+    }
+}
+
+/// Limited-memory BFGS on the cost, a general quasi-Newton minimizer that uses only the
+/// gradient `J^T r`, not the least-squares structure. The inverse Hessian is approximated from
+/// the last `MEMORY` steps and gradient changes, starting from `diag(d^2)` for the parameter
+/// scales `d`, and each direction is searched by backtracking until the cost decreases
+/// sufficiently (the Armijo condition). Bounds are ignored.
+pub(crate) fn lbfgs<const N: usize>(
+    problem: &Problem<'_, N>,
+    p0: [f64; N],
+    options: &Options<N>,
+) -> Solution<N> {
+    const MEMORY: usize = 10;
+    const SUFFICIENT_DECREASE: f64 = 1e-4;
+    const BACKTRACK: f64 = 0.5;
+
+    let n = problem.n_free();
+    let max_evaluations = 100 * (n + 1);
+    let mut p = problem.reduce(&p0);
+    let mut r = problem.residuals(&p);
+    let mut evaluations = 1;
+    let mut cost = 0.5 * r.norm_squared();
+    let mut jac = problem.jacobian(&p, &r, &options.bounds);
+    let mut scales = problem.scales(&options.scaling, &jac);
+    let mut gradient = jac.tr_mul(&r);
+    // steps, gradient changes and the inverses of their products, oldest first
+    let mut pairs: VecDeque<(DVector<f64>, DVector<f64>, f64)> = VecDeque::with_capacity(MEMORY);
+    let mut iteration = 0;
+    let mut history = options.start_history(problem.params(&p), cost);
+
+    let termination = loop {
+        if !cost.is_finite() || jac.iter().any(|j| !j.is_finite()) {
+            break TerminationReason::NonFinite;
+        }
+        let gradient_norm = gradient.amax();
+        if gradient_norm < GTOL {
+            break TerminationReason::Gtol;
+        }
+        if evaluations >= max_evaluations {
+            break TerminationReason::MaxEvaluations;
+        }
+        if let Some(elapsed) = options.deadline.expired() {
+            break TerminationReason::Timeout { elapsed };
+        }
+
+        let direction = -lbfgs_direction(&gradient, &pairs, &scales.scale());
+        let slope = gradient.dot(&direction);
+        // without curvature pairs the length of the direction means nothing, so the first
+        // trial step is at most one unit in the scaled variables
+        let mut step_length = if pairs.is_empty() {
+            (1.0 / direction.component_mul(&scales.inverse).norm()).min(1.0)
+        } else {
+            1.0
+        };
+        let mut termination = None;
+        let mut accepted = None;
+        while accepted.is_none() && termination.is_none() && evaluations < max_evaluations {
+            let step = &direction * step_length;
+            let p_new = &p + &step;
+            let r_new = problem.residuals(&p_new);
+            evaluations += 1;
+
+            let cost_new = 0.5 * r_new.norm_squared();
+            let actual_reduction = cost - cost_new;
+            // the quasi-Newton model along the direction has its minimum at step length 1
+            let predicted_reduction = -slope * step_length * (1.0 - 0.5 * step_length);
+            let ratio = gain_ratio(actual_reduction, predicted_reduction);
+            let step_norm = step.norm();
+            termination = check_termination(actual_reduction, cost, step_norm, p.norm(), ratio);
+
+            if actual_reduction > 0.0
+                && actual_reduction >= -SUFFICIENT_DECREASE * step_length * slope
+            {
+                trace::line_search_iteration(
+                    iteration,
+                    cost_new,
+                    step_length,
+                    step_norm,
+                    gradient_norm,
+                );
+                p = p_new;
+                r = r_new;
+                cost = cost_new;
+                accepted = Some(step);
+            } else {
+                trace::rejected_step(Method::LBFGS, iteration, cost_new, step_norm);
+                options.reject(iteration + 1, problem.params(&p_new), cost_new, None);
+                step_length *= BACKTRACK;
+            }
+        }
+
+        iteration += 1;
+        options.record(&mut history, iteration, problem.params(&p), cost, None);
+        if let Some(step) = accepted {
+            jac = problem.jacobian(&p, &r, &options.bounds);
+            scales.update(&jac);
+            let gradient_new = jac.tr_mul(&r);
+            let change = &gradient_new - &gradient;
+            let curvature = step.dot(&change);
+            // a pair without positive curvature would make the approximation indefinite
+            if curvature > f64::EPSILON * change.norm_squared() {
+                if pairs.len() == MEMORY {
+                    pairs.pop_front();
+                }
+                pairs.push_back((step, change, 1.0 / curvature));
+            }
+            gradient = gradient_new;
+        }
+        if let Some(termination) = termination {
+            break termination;
+        }
+    };
+
+    trace::finished(Method::LBFGS, termination, iteration, cost);
+    let (cost, jacobian) = problem.data_part(&r, jac);
+    let params = problem.params(&p);
+    Solution {
+        params,
+        cost,
+        jacobian,
+        termination,
+        iterations: iteration,
+        n_fev: problem.evaluations.get(),
+        history,
+        method: Method::LBFGS,
+        at_bounds: options.bounds.active(&params),
+        n_free: problem.n_free(),
+        regularization: problem.penalty_weights(),
+        trust_radius: None,
+        trace: None,
+    }
+}
+
+/// `H g` for the L-BFGS approximation `H` of the inverse Hessian from the curvature `pairs`,
+/// by the two-loop recursion. The initial approximation is `gamma * diag(d^2)`, `gamma`
+/// matching the curvature of the latest pair.
+fn lbfgs_direction(
+    gradient: &DVector<f64>,
+    pairs: &VecDeque<(DVector<f64>, DVector<f64>, f64)>,
+    d: &DVector<f64>,
+) -> DVector<f64> {
+    let mut q = gradient.clone();
+    let mut coefficients = Vec::with_capacity(pairs.len());
+    for (step, change, rho) in pairs.iter().rev() {
+        let coefficient = rho * step.dot(&q);
+        q.axpy(-coefficient, change, 1.0);
+        coefficients.push(coefficient);
+    }
+    let d_squared = d.component_mul(d);
+    let gamma = pairs.back().map_or(1.0, |(step, change, _)| {
+        step.dot(change) / change.component_mul(&d_squared).dot(change)
+    });
+    let mut h = q.component_mul(&d_squared) * gamma;
+    for ((step, change, rho), coefficient) in pairs.iter().zip(coefficients.iter().rev()) {
+        let correction = rho * change.dot(&h);
+        h.axpy(coefficient - correction, step, 1.0);
+    }
+    h
+}
+
+/// Linear least squares for models linear in their parameters, whose Jacobian does not depend
+/// on them: the Jacobian at `p0` is computed once and its pseudo-inverse takes the Gauss-Newton
+/// step to the solution. Further steps with the same Jacobian remove the error its finite
+/// differences leave, until a step is below the tolerance. For a nonlinear model these are
+/// chord steps, which converge slowly if at all. Bounds are ignored.
+pub(crate) fn linear_least_squares<const N: usize>(
+    problem: &Problem<'_, N>,
+    p0: [f64; N],
+    options: &Options<N>,
+) -> Solution<N> {
+    let n = problem.n_free();
+    let max_evaluations = 100 * (n + 1);
+    let mut p = problem.reduce(&p0);
+    let mut r = problem.residuals(&p);
+    let mut evaluations = 1;
+    let mut cost = 0.5 * r.norm_squared();
+    let jac = problem.jacobian(&p, &r, &options.bounds);
+    let svd = jac.clone().svd(true, true);
+    let threshold = f64::EPSILON * jac.nrows().max(n) as f64 * svd.singular_values.max();
+    let mut iteration = 0;
+    let mut history = options.start_history(problem.params(&p), cost);
+
+    let termination = loop {
+        if !cost.is_finite() || jac.iter().any(|j| !j.is_finite()) {
+            break TerminationReason::NonFinite;
+        }
+        if evaluations >= max_evaluations {
+            break TerminationReason::MaxEvaluations;
+        }
+        if let Some(elapsed) = options.deadline.expired() {
+            break TerminationReason::Timeout { elapsed };
+        }
+        let step = -svd.solve(&r, threshold).expect("U and V were computed");
+        let step_norm = step.norm();
+        let p_norm = p.norm();
+        p += &step;
+        r = problem.residuals(&p);
+        evaluations += 1;
+        cost = 0.5 * r.norm_squared();
+        iteration += 1;
+        options.record(&mut history, iteration, problem.params(&p), cost, None);
+        if step_norm < XTOL * (XTOL + p_norm) {
+            break TerminationReason::Xtol;
+        }
+    };
+
+    trace::finished(Method::LinearLSQ, termination, iteration, cost);
+    let (cost, jacobian) = problem.data_part(&r, jac);
+    let params = problem.params(&p);
+    Solution {
+        params,
+        cost,
+        jacobian,
+        termination,
+        iterations: iteration,
+        n_fev: problem.evaluations.get(),
+        history,
+        method: Method::LinearLSQ,
+        at_bounds: options.bounds.active(&params),
+        n_free: problem.n_free(),
+        regularization: problem.penalty_weights(),
+        trust_radius: None,
+        trace: None,
+    }
+}
+
+/// Stochastic gradient descent on the mean cost `cost / m`. Every epoch visits the data in a
+/// fresh random order, in mini-batches of `batch_size` points, and steps by `learning_rate`
+/// times the gradient of the mean over the batch, from forward differences at each of its
+/// points. The regularization penalty, spread evenly over the points, adds its exact gradient.
+///
+/// The steps keep fluctuating with the batches, so the solver stops after `n_epochs` epochs
+/// rather than on a gradient or step tolerance, or on `Ftol` when an epoch changes the cost by
+/// less than the tolerance. One iteration is one epoch, and `n_fev` counts the residuals evaluated
+/// in passes over all of them, rounded up. Scales and bounds are ignored.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn stochastic_gradient_descent<const N: usize>(
+    problem: &Problem<'_, N>,
+    p0: [f64; N],
+    options: &Options<N>,
+    batch_size: usize,
+    learning_rate: f64,
+    n_epochs: usize,
+    rng: &mut impl Rng,
+) -> Solution<N> {
+    let lambda = problem.regularization.unwrap_or(0.0);
+    let mut params = problem.params(&problem.reduce(&p0));
+    let mut r = problem.residuals(&problem.reduce(&params));
+    let m = problem.n_data(&r);
+    let mut cost = 0.5 * r.norm_squared();
+    let mut order: Vec<usize> = (0..m).collect();
+    let mut calls = 0;
+    let mut epoch = 0;
+    let mut history = options.start_history(params, cost);
+
+    let termination = loop {
+        if !cost.is_finite() {
+            break TerminationReason::NonFinite;
+        }
+        if epoch == n_epochs {
+            break TerminationReason::Epochs;
+        }
+        if let Some(elapsed) = options.deadline.expired() {
+            break TerminationReason::Timeout { elapsed };
+        }
+        order.shuffle(rng);
+        for batch in order.chunks(batch_size) {
+            let mut gradient = [0.0; N];
+            let r_batch = problem.source.batch_residuals(&params, batch);
+            for &i in &problem.free {
+                let mut stepped = params;
+                stepped[i] += problem.diff_step * params[i].abs().max(1.0);
+                let h = stepped[i] - params[i];
+                let r_stepped = problem.source.batch_residuals(&stepped, batch);
+                gradient[i] = r_batch
+                    .iter()
+                    .zip(&r_stepped)
+                    .map(|(r, r_step)| r * (r_step - r) / h)
+                    .sum();
+            }
+            calls += batch.len() * (1 + problem.n_free());
+            for &i in &problem.free {
+                let penalty = lambda * (params[i] - problem.reference[i]) / m as f64;
+                params[i] -= learning_rate * (gradient[i] / batch.len() as f64 + penalty);
+            }
+        }
+
+        epoch += 1;
+        r = problem.residuals(&problem.reduce(&params));
+        let cost_new = 0.5 * r.norm_squared();
+        options.record(&mut history, epoch, params, cost_new, None);
+        let stalled = (cost - cost_new).abs() <= FTOL * cost_new;
+        cost = cost_new;
+        if stalled {
+            break TerminationReason::Ftol;
+        }
+    };
+    problem
+        .evaluations
+        .set(problem.evaluations.get() + calls.div_ceil(m));
+
+    let method = Method::SGD {
+        batch_size,
+        learning_rate,
+    };
+    trace::finished(method, termination, epoch, cost);
+    let p = problem.reduce(&params);
+    let jac = problem.jacobian(&p, &r, &options.bounds);
+    let (cost, jacobian) = problem.data_part(&r, jac);
+    Solution {
+        params,
+        cost,
+        jacobian,
+        termination,
+        iterations: epoch,
+        n_fev: problem.evaluations.get(),
+        history,
+        method,
+        at_bounds: options.bounds.active(&params),
+        n_free: problem.n_free(),
+        regularization: problem.penalty_weights(),
+        trust_radius: None,
+        trace: None,
+    }
+}
+
+fn to_array<const N: usize>(p: &DVector<f64>) -> [f64; N] {
+    std::array::from_fn(|i| p[i])
+}
+
+fn column_norms(jac: &DMatrix<f64>) -> DVector<f64> {
+    DVector::from_iterator(jac.ncols(), jac.column_iter().map(|column| column.norm()))
+}
+
+/// `J diag(d)`, the Jacobian with respect to the scaled variables `p / d`.
+fn scale_columns(jac: &DMatrix<f64>, d: &DVector<f64>) -> DMatrix<f64> {
+    let mut jac = jac.clone();
+    for (mut column, d) in jac.column_iter_mut().zip(d.iter()) {
+        column *= *d;
+    }
+    jac
+}
+
+fn max_column_norm_squared(jac: &DMatrix<f64>) -> f64 {
+    jac.column_iter()
+        .map(|column| column.norm_squared())
+        .fold(0.0, f64::max)
+}
+
+/// Initial and largest LM damping of `lm` for the scaled Jacobian `jac_h`, relative to the
+/// largest diagonal entry of `J^T J` or absolute when it is zero.
+fn initial_damping(lm: &LmOptions, jac_h: &DMatrix<f64>) -> (f64, f64) {
+    match max_column_norm_squared(jac_h) {
+        curvature if lm.lambda_init * curvature > 0.0 => {
+            (lm.lambda_init * curvature, lm.lambda_max * curvature)
+        }
+        _ => (lm.lambda_init, lm.lambda_max),
+    }
+}
+
+/// The Levenberg-Marquardt system `(J^T J + damping I) step = -J^T r` through the SVD
+/// `J = U S V^T`, which is defined for any damping and rank. It is the SVD of the small
+/// triangular factor of `J = Q R`, with `U^T r = U_R^T Q^T r`.
+struct DampedSvd {
+    singular_values: DVector<f64>,
+    v_t: DMatrix<f64>,
+    u_r: DVector<f64>,
+    /// Singular values at or below this one are taken as zero.
+    cutoff: f64,
+}
+
+impl DampedSvd {
+    /// The system of `jac` and `r`, with a cutoff of `threshold` times the largest singular
+    /// value.
+    fn new(jac: &DMatrix<f64>, r: &DVector<f64>, threshold: f64) -> Self {
+        let qr = jac.clone().qr();
+        let mut q_r = r.clone();
+        qr.q_tr_mul(&mut q_r);
+        let svd = qr.r().svd(true, true);
+        let (u, v_t) = (svd.u.unwrap(), svd.v_t.unwrap());
+        Self {
+            u_r: u.tr_mul(&q_r.rows(0, u.nrows())),
+            v_t,
+            cutoff: threshold * svd.singular_values.max(),
+            singular_values: svd.singular_values,
+        }
+    }
+
+    /// The step `-V (S^2 + damping I)^-1 S U^T r`, leaving out the directions of the singular
+    /// values at or below the cutoff.
+    fn step(&self, damping: f64) -> DVector<f64> {
+        let coefficients = self.singular_values.zip_map(&self.u_r, |s, u_r| {
+            if s > self.cutoff {
+                -s / (s * s + damping) * u_r
+            } else {
+                0.0
+            }
+        });
+        self.v_t.tr_mul(&coefficients)
+    }
+}
+
+/// Whether the Jacobian is rank deficient up to `sqrt(EPSILON)` relative precision.
+fn is_near_singular(jac: &DMatrix<f64>) -> bool {
+    let singular_values = jac.singular_values();
+    jac.nrows() < jac.ncols()
+        || singular_values.min() <= f64::EPSILON.sqrt() * singular_values.max()
+}
+
+/// Value of the linearized cost change `0.5 * |J s|^2 + 0.5 * s^T diag(c) s + g^T s` for the
+/// step `s`, with the diagonal term only if `diag` is given.
+fn evaluate_quadratic(
+    jac: &DMatrix<f64>,
+    gradient: &DVector<f64>,
+    step: &DVector<f64>,
+    diag: Option<&DVector<f64>>,
+) -> f64 {
+    let curvature = diag.map_or(0.0, |diag| step.component_mul(diag).dot(step));
+    0.5 * ((jac * step).norm_squared() + curvature) + gradient.dot(step)
+}
+
+/// Coefficients `(a, b, c)` of the quadratic of [`evaluate_quadratic`] along the line
+/// `s0 + t * s`, as `a * t^2 + b * t + c`.
+fn build_quadratic_1d(
+    jac: &DMatrix<f64>,
+    gradient: &DVector<f64>,
+    s: &DVector<f64>,
+    s0: Option<&DVector<f64>>,
+    diag: Option<&DVector<f64>>,
+) -> (f64, f64, f64) {
+    let js = jac * s;
+    let mut a = js.norm_squared();
+    if let Some(diag) = diag {
+        a += s.component_mul(diag).dot(s);
+    }
+    let mut b = gradient.dot(s);
+    let mut c = 0.0;
+    if let Some(s0) = s0 {
+        let js0 = jac * s0;
+        b += js0.dot(&js);
+        c = 0.5 * js0.norm_squared() + gradient.dot(s0);
+        if let Some(diag) = diag {
+            b += s0.component_mul(diag).dot(s);
+            c += 0.5 * s0.component_mul(diag).dot(s0);
+        }
+    }
+    (0.5 * a, b, c)
+}
+
+fn gain_ratio(actual_reduction: f64, predicted_reduction: f64) -> f64 {
+    if predicted_reduction > 0.0 {
+        actual_reduction / predicted_reduction
+    } else if predicted_reduction == actual_reduction && actual_reduction == 0.0 {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+fn check_termination(
+    actual_reduction: f64,
+    cost: f64,
+    step_norm: f64,
+    p_norm: f64,
+    ratio: f64,
+) -> Option<TerminationReason> {
+    if actual_reduction < FTOL * cost && ratio > 0.25 {
+        Some(TerminationReason::Ftol)
+    } else if step_norm < XTOL * (XTOL + p_norm) {
+        Some(TerminationReason::Xtol)
+    } else {
+        None
+    }
+}
+
+/// Shrinks the radius after a poor or rejected step and grows it after a good one that hit the
+/// boundary, as `tr` says.
+fn update_trust_radius(
+    tr: &TrustRegionOptions,
+    radius: f64,
+    actual_reduction: f64,
+    predicted_reduction: f64,
+    step_norm: f64,
+    bound_hit: bool,
+) -> (f64, f64) {
+    let ratio = gain_ratio(actual_reduction, predicted_reduction);
+    let radius = if ratio < tr.eta_accept.max(0.25) {
+        tr.shrink_factor * step_norm
+    } else if ratio > 0.75 && bound_hit {
+        (tr.expand_factor * radius).min(tr.max_radius)
+    } else {
+        radius
+    };
+    (radius, ratio)
+}
+
+/// Initial trust radius of `tr`, by default `norm`, the size of the scaled initial guess, or 1
+/// when that is zero.
+fn initial_radius(tr: &TrustRegionOptions, norm: f64) -> f64 {
+    let radius = match tr.initial_radius {
+        Some(radius) => radius,
+        None if norm > 0.0 => norm,
+        None => 1.0,
+    };
+    radius.min(tr.max_radius)
+}
+
+/// Solves `min |J s + r|` subject to `|s| <= radius` given the SVD `J = U S V^T` and `uf = U^T r`.
+///
+/// Returns the step together with the Levenberg-Marquardt parameter `alpha` for which
+/// `s = -V (S^2 + alpha I)^-1 S uf`, found by Newton iterations on the secular equation
+/// (Moré, "The Levenberg-Marquardt algorithm: implementation and theory").
+fn solve_trust_region_subproblem(
+    m: usize,
+    uf: &DVector<f64>,
+    s: &DVector<f64>,
+    v_t: &DMatrix<f64>,
+    radius: f64,
+    initial_alpha: f64,
+) -> (DVector<f64>, f64) {
+    let n = v_t.ncols();
+    let suf = s.component_mul(uf);
+    let full_rank = m >= n && s.min() > f64::EPSILON * m as f64 * s.max();
+    if full_rank {
+        let p = -v_t.tr_mul(&uf.component_div(s));
+        if p.norm() <= radius {
+            return (p, 0.0);
+        }
+    }
+
+    let phi_and_derivative = |alpha: f64| {
+        let denom = s.map(|s| s * s + alpha);
+        let p_norm = suf.component_div(&denom).norm();
+        let phi_prime = -suf
+            .iter()
+            .zip(denom.iter())
+            .map(|(suf, denom)| suf * suf / denom.powi(3))
+            .sum::<f64>()
+            / p_norm;
+        (p_norm - radius, phi_prime)
+    };
+
+    let mut alpha_upper = suf.norm() / radius;
+    let mut alpha_lower = if full_rank {
+        let (phi, phi_prime) = phi_and_derivative(0.0);
+        -phi / phi_prime
+    } else {
+        0.0
+    };
+    let safeguard = |lower: f64, upper: f64| f64::max(0.001 * upper, (lower * upper).sqrt());
+
+    let mut alpha = if !full_rank && initial_alpha == 0.0 {
+        safeguard(alpha_lower, alpha_upper)
+    } else {
+        initial_alpha
+    };
+    for _ in 0..10 {
+        if alpha < alpha_lower || alpha > alpha_upper {
+            alpha = safeguard(alpha_lower, alpha_upper);
+        }
+        let (phi, phi_prime) = phi_and_derivative(alpha);
+        if phi < 0.0 {
+            alpha_upper = alpha;
+        }
+        let ratio = phi / phi_prime;
+        alpha_lower = alpha_lower.max(alpha - ratio);
+        alpha -= (phi + radius) * ratio / radius;
+        if phi.abs() < 0.01 * radius {
+            break;
+        }
+    }
+
+    let mut p = -v_t.tr_mul(&suf.component_div(&s.map(|s| s * s + alpha)));
+    // put the step exactly on the boundary, it moves only slightly
+    let p_norm = p.norm();
+    if p_norm > 0.0 {
+        p *= radius / p_norm;
+    }
+    (p, alpha)
+}
+
+/// Dogleg step from `p` inside the intersection of the trust box `|step| <= radii` with the
+/// bounds. Returns the step, the bound each parameter ends on (-1 lower, 1 upper, 0 none) and
+/// whether the step ends on the trust box.
+#[allow(clippy::too_many_arguments)]
+fn dogleg_step(
+    p: &DVector<f64>,
+    newton_step: &DVector<f64>,
+    gradient: &DVector<f64>,
+    a: f64,
+    b: f64,
+    radii: &DVector<f64>,
+    lower: &DVector<f64>,
+    upper: &DVector<f64>,
+) -> (DVector<f64>, Vec<i8>, bool) {
+    let lower_centered = lower - p;
+    let upper_centered = upper - p;
+    let lower_total = lower_centered.zip_map(radii, |lower, radius| lower.max(-radius));
+    let upper_total = upper_centered.zip_map(radii, |upper, radius| upper.min(radius));
+    let mut on_bound = vec![0; p.len()];
+    if in_bounds(newton_step, &lower_total, &upper_total) {
+        return (newton_step.clone(), on_bound, false);
+    }
+
+    let origin = DVector::zeros(p.len());
+    let (to_bounds, _) = step_size_to_bound(&origin, &-gradient, &lower_total, &upper_total);
+    let cauchy_step = -gradient * minimize_quadratic_1d(a, b, 0.0, to_bounds, 0.0).0;
+    let step_diff = newton_step - &cauchy_step;
+    let (step_size, hits) =
+        step_size_to_bound(&cauchy_step, &step_diff, &lower_total, &upper_total);
+    let mut radius_hit = false;
+    for i in 0..p.len() {
+        if hits[i] < 0 {
+            if lower_total[i] == lower_centered[i] {
+                on_bound[i] = -1;
+            }
+            radius_hit |= lower_total[i] == -radii[i];
+        } else if hits[i] > 0 {
+            if upper_total[i] == upper_centered[i] {
+                on_bound[i] = 1;
+            }
+            radius_hit |= upper_total[i] == radii[i];
+        }
+    }
+    (cauchy_step + step_diff * step_size, on_bound, radius_hit)
+}
+
+/// Chooses the TRF step in the bounded case. A trust-region step `p_h` (scaled by `d`) that
+/// stays within the bounds is taken as is; otherwise the best, by the quadratic model, of
+/// - the step stopped at the first bound and pulled back by `theta`,
+/// - that step continued along its reflection off the bound,
+/// - the minimizer along the scaled steepest descent direction.
+///
+/// Returns the step, its scaled counterpart and the predicted cost reduction.
+#[allow(clippy::too_many_arguments)]
+fn select_step(
+    p: &DVector<f64>,
+    jac_h: &DMatrix<f64>,
+    diag_h: &DVector<f64>,
+    gradient_h: &DVector<f64>,
+    step_h: DVector<f64>,
+    d: &DVector<f64>,
+    radius: f64,
+    lower: &DVector<f64>,
+    upper: &DVector<f64>,
+    theta: f64,
+) -> (DVector<f64>, DVector<f64>, f64) {
+    let step = step_h.component_mul(d);
+    if in_bounds(&(p + &step), lower, upper) {
+        let value = evaluate_quadratic(jac_h, gradient_h, &step_h, Some(diag_h));
+        return (step, step_h, -value);
+    }
+
+    let (stride, hits) = step_size_to_bound(p, &step, lower, upper);
+    let mut reflected_h = step_h.clone();
+    for (r, hit) in reflected_h.iter_mut().zip(&hits) {
+        if *hit != 0 {
+            *r = -*r;
+        }
+    }
+    let reflected = reflected_h.component_mul(d);
+    // the step up to the bound
+    let step = step * stride;
+    let step_h = step_h * stride;
+    let on_bound = p + &step;
+
+    // the reflected direction leaves either the trust region or the box first
+    let (_, to_radius) = intersect_trust_region(&step_h, &reflected_h, radius);
+    let (to_bound, _) = step_size_to_bound(&on_bound, &reflected, lower, upper);
+    let reflected_stride = to_bound.min(to_radius);
+    let (stride_lower, stride_upper) = if reflected_stride > 0.0 {
+        let stride_upper = if reflected_stride == to_bound {
+            theta * to_bound
+        } else {
+            to_radius
+        };
+        ((1.0 - theta) * stride / reflected_stride, stride_upper)
+    } else {
+        (0.0, -1.0)
+    };
+    let (reflected, reflected_h, reflected_value) = if stride_lower <= stride_upper {
+        let (a, b, c) =
+            build_quadratic_1d(jac_h, gradient_h, &reflected_h, Some(&step_h), Some(diag_h));
+        let (t, value) = minimize_quadratic_1d(a, b, stride_lower, stride_upper, c);
+        let reflected_h = &step_h + reflected_h * t;
+        (reflected_h.component_mul(d), reflected_h, value)
+    } else {
+        (reflected, reflected_h, f64::INFINITY)
+    };
+
+    // keep the step strictly inside the bounds
+    let step = step * theta;
+    let step_h = step_h * theta;
+    let step_value = evaluate_quadratic(jac_h, gradient_h, &step_h, Some(diag_h));
+
+    let descent_h = -gradient_h;
+    let descent = descent_h.component_mul(d);
+    let to_radius = radius / descent_h.norm();
+    let (to_bound, _) = step_size_to_bound(p, &descent, lower, upper);
+    let descent_stride = if to_bound < to_radius {
+        theta * to_bound
+    } else {
+        to_radius
+    };
+    let (a, b, _) = build_quadratic_1d(jac_h, gradient_h, &descent_h, None, Some(diag_h));
+    let (t, descent_value) = minimize_quadratic_1d(a, b, 0.0, descent_stride, 0.0);
+
+    if step_value < reflected_value && step_value < descent_value {
+        (step, step_h, -step_value)
+    } else if reflected_value < step_value && reflected_value < descent_value {
+        (reflected, reflected_h, -reflected_value)
+    } else {
+        (descent * t, descent_h * t, -descent_value)
+    }
+}
+
+/// Values of `t`, negative then positive, for which `|x + t * s| = radius`.
+fn intersect_trust_region(x: &DVector<f64>, s: &DVector<f64>, radius: f64) -> (f64, f64) {
+    let a = s.norm_squared();
+    let b = x.dot(s);
+    let c = x.norm_squared() - radius * radius;
+    let d = (b * b - a * c).sqrt();
+    // avoids the cancellation of `-b + d`
+    let q = -(b + d.copysign(b));
+    let (t1, t2) = (q / a, c / q);
+    if t1 < t2 {
+        (t1, t2)
+    } else {
+        (t2, t1)
+    }
+}
+
+/// Coleman-Li scaling vector `v` and its derivative `dv`: the distance to the bound that the
+/// negative gradient points at, or 1 where that bound is infinite.
+fn coleman_li_scaling(
+    p: &DVector<f64>,
+    gradient: &DVector<f64>,
+    lower: &DVector<f64>,
+    upper: &DVector<f64>,
+) -> (DVector<f64>, DVector<f64>) {
+    let mut v = DVector::from_element(p.len(), 1.0);
+    let mut dv = DVector::zeros(p.len());
+    for i in 0..p.len() {
+        if gradient[i] < 0.0 && upper[i].is_finite() {
+            v[i] = upper[i] - p[i];
+            dv[i] = -1.0;
+        } else if gradient[i] > 0.0 && lower[i].is_finite() {
+            v[i] = p[i] - lower[i];
+            dv[i] = 1.0;
+        }
+    }
+    (v, dv)
+}
+
+/// Moves parameters on or within `rstep * max(1, |bound|)` of a bound into the interior, by
+/// that distance, or by one ulp for `rstep = 0`.
+fn make_strictly_feasible(
+    p: &DVector<f64>,
+    lower: &DVector<f64>,
+    upper: &DVector<f64>,
+    rstep: f64,
+) -> DVector<f64> {
+    let mut p = p.clone();
+    for i in 0..p.len() {
+        let (l, u) = (lower[i], upper[i]);
+        let lower_margin = rstep * l.abs().max(1.0);
+        let upper_margin = rstep * u.abs().max(1.0);
+        let (lower_dist, upper_dist) = (p[i] - l, u - p[i]);
+        if rstep == 0.0 {
+            if p[i] <= l {
+                p[i] = l.next_up();
+            } else if p[i] >= u {
+                p[i] = u.next_down();
+            }
+        } else if l.is_finite() && lower_dist <= upper_dist.min(lower_margin) {
+            p[i] = l + lower_margin;
+        } else if u.is_finite() && upper_dist <= lower_dist.min(upper_margin) {
+            p[i] = u - upper_margin;
+        }
+        // bounds too tight for the margin
+        if !(l < p[i] && p[i] < u) {
+            p[i] = 0.5 * (l + u);
+        }
+    }
+    p
+}
+
+fn in_bounds(p: &DVector<f64>, lower: &DVector<f64>, upper: &DVector<f64>) -> bool {
+    p.iter()
+        .zip(lower.iter().zip(upper.iter()))
+        .all(|(p, (lower, upper))| lower <= p && p <= upper)
+}
+
+/// Largest `t` such that `p + t * direction` stays within `[lower, upper]`, together with the
+/// bounds reached at `t`: the sign of the direction for each parameter that reaches one, else 0.
+fn step_size_to_bound(
+    p: &DVector<f64>,
+    direction: &DVector<f64>,
+    lower: &DVector<f64>,
+    upper: &DVector<f64>,
+) -> (f64, Vec<i8>) {
+    let steps: Vec<f64> = (0..p.len())
+        .map(|i| {
+            if direction[i] == 0.0 {
+                f64::INFINITY
+            } else {
+                f64::max(
+                    (lower[i] - p[i]) / direction[i],
+                    (upper[i] - p[i]) / direction[i],
+                )
+            }
+        })
+        .collect();
+    let min_step = steps.iter().copied().fold(f64::INFINITY, f64::min);
+    let hits = steps
+        .iter()
+        .zip(direction.iter())
+        .map(|(&step, &direction)| {
+            if step == min_step && direction != 0.0 {
+                direction.signum() as i8
+            } else {
+                0
+            }
+        })
+        .collect();
+    (min_step, hits)
+}
+
+/// Minimizer and minimum of `a * t^2 + b * t + c` over `[lower, upper]`.
+fn minimize_quadratic_1d(a: f64, b: f64, lower: f64, upper: f64, c: f64) -> (f64, f64) {
+    let mut candidates = vec![lower, upper];
+    if a != 0.0 {
+        let extremum = -0.5 * b / a;
+        if lower < extremum && extremum < upper {
+            candidates.push(extremum);
+        }
+    }
+    candidates
+        .into_iter()
+        .map(|t| (t, t * (a * t + b) + c))
+        .min_by(|(_, y1), (_, y2)| y1.total_cmp(y2))
+        .unwrap()
+}
@@ -0,0 +1,259 @@
+//! Global fitting of several data sets at once, some parameters shared between all of them
+//! and the others fitted to each data set separately.
+//!
+//! The residuals of the data sets are stacked into one vector, and so are the parameters: the
+//! shared ones first, then the per-dataset ones of each data set in turn. The loop is the
+//! Levenberg-Marquardt iteration of the parent module on these vectors. A per-dataset
+//! parameter only enters the residuals of its own data set, so stepping one parameter slot in
+//! every data set at once gives all of their Jacobian columns from a single pass over the data.
+
+use std::cell::Cell;
+
+use nalgebra::{DMatrix, DVector};
+
+use super::{
+    check_termination, evaluate_quadratic, gain_ratio, initial_damping, scale_columns, DampedSvd,
+    Deadline, Scales, TerminationReason, DIFF_STEP, GTOL,
+};
+use crate::{trace, LmOptions, Method};
+
+/// A model function paired with several data sets, `shared[i]` telling whether parameter `i`
+/// is common to all of them.
+pub(crate) struct GlobalProblem<'a, const N: usize, F> {
+    func: &'a F,
+    datasets: &'a [(Vec<f64>, Vec<f64>)],
+    shared: [bool; N],
+    /// Position of each parameter among the shared or among the per-dataset ones.
+    slots: [usize; N],
+    n_shared: usize,
+    /// Step of the finite differences relative to `max(|p_i|, 1)`.
+    diff_step: f64,
+    /// Evaluations of the model over all data sets, including those of the Jacobian.
+    evaluations: Cell<usize>,
+}
+
+impl<'a, const N: usize, F: Fn(f64, [f64; N]) -> f64> GlobalProblem<'a, N, F> {
+    pub(crate) fn new(
+        func: &'a F,
+        datasets: &'a [(Vec<f64>, Vec<f64>)],
+        shared: [bool; N],
+    ) -> Self {
+        let mut counts = [0, 0];
+        let slots = shared.map(|shared| {
+            let count = &mut counts[usize::from(!shared)];
+            *count += 1;
+            *count - 1
+        });
+        Self {
+            func,
+            datasets,
+            shared,
+            slots,
+            n_shared: counts[0],
+            diff_step: DIFF_STEP,
+            evaluations: Cell::new(0),
+        }
+    }
+
+    /// Takes finite-difference steps of `diff_step` relative to the parameters, or of
+    /// [`DIFF_STEP`] for `None`.
+    pub(crate) fn with_diff_step(mut self, diff_step: Option<f64>) -> Self {
+        self.diff_step = diff_step.unwrap_or(DIFF_STEP);
+        self
+    }
+
+    /// Length of the stacked parameter vector.
+    pub(crate) fn n_params(&self) -> usize {
+        self.n_shared + self.datasets.len() * (N - self.n_shared)
+    }
+
+    /// Position of parameter `i` of data set `k` in the stacked parameter vector.
+    pub(crate) fn index(&self, k: usize, i: usize) -> usize {
+        if self.shared[i] {
+            self.slots[i]
+        } else {
+            self.n_shared + k * (N - self.n_shared) + self.slots[i]
+        }
+    }
+
+    /// The stacked parameter vector of the parameters of each data set, taking the shared
+    /// ones from the first.
+    pub(crate) fn stack(&self, params: &[[f64; N]]) -> DVector<f64> {
+        let mut p = DVector::zeros(self.n_params());
+        for (k, params) in params.iter().enumerate().rev() {
+            for (i, &value) in params.iter().enumerate() {
+                p[self.index(k, i)] = value;
+            }
+        }
+        p
+    }
+
+    /// The parameters of data set `k`.
+    pub(crate) fn params(&self, p: &DVector<f64>, k: usize) -> [f64; N] {
+        std::array::from_fn(|i| p[self.index(k, i)])
+    }
+
+    fn residuals(&self, p: &DVector<f64>) -> DVector<f64> {
+        self.evaluations.set(self.evaluations.get() + 1);
+        let residuals = self
+            .datasets
+            .iter()
+            .enumerate()
+            .flat_map(|(k, (x_data, y_data))| {
+                let params = self.params(p, k);
+                let points = x_data.iter().zip(y_data);
+                points.map(move |(&x, &y)| (self.func)(x, params) - y)
+            });
+        DVector::from_iterator(self.datasets.iter().map(|(x, _)| x.len()).sum(), residuals)
+    }
+
+    /// Forward-difference Jacobian of the residuals `r` at `p`, one pass over the data for
+    /// each of the `N` parameter slots.
+    fn jacobian(&self, p: &DVector<f64>, r: &DVector<f64>) -> DMatrix<f64> {
+        let mut jac = DMatrix::zeros(r.len(), p.len());
+        for i in 0..N {
+            let mut p_step = p.clone();
+            for k in 0..self.datasets.len() {
+                let j = self.index(k, i);
+                p_step[j] = p[j] + self.diff_step * p[j].abs().max(1.0);
+            }
+            let r_step = self.residuals(&p_step);
+            let mut row = 0;
+            for (k, (x_data, _)) in self.datasets.iter().enumerate() {
+                let j = self.index(k, i);
+                // use the step that is actually representable
+                let h = p_step[j] - p[j];
+                let rows = row..row + x_data.len();
+                for row in rows.clone() {
+                    jac[(row, j)] = (r_step[row] - r[row]) / h;
+                }
+                row = rows.end;
+            }
+        }
+        jac
+    }
+}
+
+/// Final state of a global fit, over the stacked parameter vector.
+pub(crate) struct GlobalSolution {
+    pub params: DVector<f64>,
+    /// `0.5 * sum(r_i^2)` at `params`, over all data sets.
+    pub cost: f64,
+    /// Jacobian of the stacked residuals at `params`.
+    pub jacobian: DMatrix<f64>,
+    pub termination: TerminationReason,
+    pub iterations: usize,
+    pub n_fev: usize,
+}
+
+impl GlobalSolution {
+    /// Whether the solver stopped on a convergence criterion at a finite point.
+    pub(crate) fn converged(&self) -> bool {
+        matches!(
+            self.termination,
+            TerminationReason::Gtol | TerminationReason::Ftol | TerminationReason::Xtol
+        ) && self.cost.is_finite()
+            && self.params.iter().all(|p| p.is_finite())
+    }
+}
+
+/// Minimizes the sum of squares over all data sets from `p0`, the stacked initial guess,
+/// scaling the parameters by the Jacobian with `jacobian_scaling` and leaving them unscaled
+/// otherwise, damping the steps as `lm` says and stopping once `deadline` expires.
+pub(crate) fn global_least_squares<const N: usize, F>(
+    problem: &GlobalProblem<'_, N, F>,
+    p0: DVector<f64>,
+    jacobian_scaling: bool,
+    lm: &LmOptions,
+    deadline: Deadline,
+) -> GlobalSolution
+where
+    F: Fn(f64, [f64; N]) -> f64,
+{
+    let n = p0.len();
+    let max_evaluations = 100 * (n + 1);
+    let mut p = p0;
+    let mut r = problem.residuals(&p);
+    let mut evaluations = 1;
+    let mut cost = 0.5 * r.norm_squared();
+    let mut jac = problem.jacobian(&p, &r);
+    let fixed_scale = (!jacobian_scaling).then(|| DVector::from_element(n, 1.0));
+    let mut scales = Scales::new(fixed_scale, &jac);
+    let (mut damping, max_damping) = initial_damping(lm, &scale_columns(&jac, &scales.scale()));
+    let mut iteration = 0;
+
+    let termination = loop {
+        if !cost.is_finite() || jac.iter().any(|j| !j.is_finite()) {
+            break TerminationReason::NonFinite;
+        }
+        let gradient = jac.tr_mul(&r);
+        let gradient_norm = gradient.amax();
+        if gradient_norm < GTOL {
+            break TerminationReason::Gtol;
+        }
+        if evaluations >= max_evaluations {
+            break TerminationReason::MaxEvaluations;
+        }
+        if let Some(elapsed) = deadline.expired() {
+            break TerminationReason::Timeout { elapsed };
+        }
+        if damping > max_damping {
+            break TerminationReason::MaxDamping;
+        }
+
+        // the damped system is solved for the scaled step `step / d`
+        let d = scales.scale();
+        let jac_h = scale_columns(&jac, &d);
+        let gradient_h = gradient.component_mul(&d);
+        let system = DampedSvd::new(&jac_h, &r, lm.svd_threshold);
+        let mut termination = None;
+        let mut accepted = false;
+        while !accepted
+            && termination.is_none()
+            && evaluations < max_evaluations
+            && damping <= max_damping
+        {
+            let step_h = system.step(damping);
+            let step = step_h.component_mul(&d);
+            let p_new = &p + &step;
+            let r_new = problem.residuals(&p_new);
+            evaluations += 1;
+
+            let cost_new = 0.5 * r_new.norm_squared();
+            let actual_reduction = cost - cost_new;
+            let predicted_reduction = -evaluate_quadratic(&jac_h, &gradient_h, &step_h, None);
+            let ratio = gain_ratio(actual_reduction, predicted_reduction);
+            let step_norm = step.norm();
+            termination = check_termination(actual_reduction, cost, step_norm, p.norm(), ratio);
+
+            if actual_reduction > 0.0 {
+                trace::damped_iteration(iteration, cost_new, damping, step_norm, gradient_norm);
+                damping /= lm.lambda_down_factor;
+                (p, r, cost) = (p_new, r_new, cost_new);
+                accepted = true;
+            } else {
+                trace::rejected_step(Method::LM, iteration, cost_new, step_norm);
+                damping *= lm.lambda_up_factor;
+            }
+        }
+
+        iteration += 1;
+        if accepted {
+            jac = problem.jacobian(&p, &r);
+            scales.update(&jac);
+        }
+        if let Some(termination) = termination {
+            break termination;
+        }
+    };
+
+    trace::finished(Method::LM, termination, iteration, cost);
+    GlobalSolution {
+        params: p,
+        cost,
+        jacobian: jac,
+        termination,
+        iterations: iteration,
+        n_fev: problem.evaluations.get(),
+    }
+}
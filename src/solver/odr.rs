@@ -0,0 +1,409 @@
+//! Orthogonal distance regression, for data with errors in `x` as well as in `y`.
+//!
+//! Every point gets an unknown correction `delta_i` of its `x`, and the weighted sum
+//! `sum(((f(x_i + delta_i, p) - y_i) / sy_i)^2 + (delta_i / sx_i)^2)` is minimized over the
+//! parameters and the corrections together, like ODRPACK (scipy's `odr`) does. The loop is the
+//! Levenberg-Marquardt iteration of the parent module on the corrections scaled to
+//! `u_i = delta_i / sx_i`. Since each correction only enters its own residual, the corrections
+//! are eliminated from every damped system, which leaves `N` unknowns however many points
+//! there are (Boggs, Byrd and Schnabel, "A stable and efficient algorithm for nonlinear
+//! orthogonal distance regression"), solved through the SVD of the weighted Jacobian of the
+//! parameters.
+
+use std::cell::Cell;
+
+use nalgebra::{DMatrix, DVector};
+
+use super::{
+    check_termination, gain_ratio, max_column_norm_squared, scale_columns, to_array, DampedSvd,
+    Deadline, Scales, Scaling, Solution, TerminationReason, DIFF_STEP, GTOL,
+};
+use crate::{trace, Error, LmOptions, Method};
+
+/// A model function paired with data whose points have standard deviations in both coordinates.
+pub(crate) struct OdrProblem<'a, const N: usize, F> {
+    func: &'a F,
+    x_data: &'a [f64],
+    y_data: &'a [f64],
+    x_sigma: &'a [f64],
+    y_sigma: &'a [f64],
+    /// Tikhonov weight `lambda`, adding `lambda * |p|^2` to the sum of squares.
+    regularization: Option<f64>,
+    /// Step of the finite differences relative to `max(|p_i|, 1)` and `max(|x_i|, 1)`.
+    diff_step: f64,
+    /// Evaluations of the model over the whole data set, including those of the Jacobian.
+    evaluations: Cell<usize>,
+}
+
+impl<'a, const N: usize, F: Fn(f64, [f64; N]) -> f64> OdrProblem<'a, N, F> {
+    pub(crate) fn new(
+        func: &'a F,
+        (x_data, y_data): (&'a [f64], &'a [f64]),
+        (x_sigma, y_sigma): (&'a [f64], &'a [f64]),
+        regularization: Option<f64>,
+    ) -> Self {
+        Self {
+            func,
+            x_data,
+            y_data,
+            x_sigma,
+            y_sigma,
+            regularization,
+            diff_step: DIFF_STEP,
+            evaluations: Cell::new(0),
+        }
+    }
+
+    /// Takes finite-difference steps of `diff_step` relative to the parameters and `x`, or of
+    /// [`DIFF_STEP`] for `None`.
+    pub(crate) fn with_diff_step(mut self, diff_step: Option<f64>) -> Self {
+        self.diff_step = diff_step.unwrap_or(DIFF_STEP);
+        self
+    }
+
+    /// `x_i + delta_i` for the scaled corrections `u`.
+    fn adjusted_x(&self, u: &DVector<f64>) -> Vec<f64> {
+        let x = self.x_data.iter().zip(self.x_sigma);
+        x.zip(u.iter()).map(|((x, sx), u)| x + sx * u).collect()
+    }
+
+    /// Weighted residuals `(f(x_i, p) - y_i) / sy_i` at the given, adjusted, `x`.
+    fn residuals(&self, x: &[f64], p: &DVector<f64>) -> DVector<f64> {
+        self.evaluations.set(self.evaluations.get() + 1);
+        let p = to_array::<N>(p);
+        let points = x.iter().zip(self.y_data).zip(self.y_sigma);
+        DVector::from_iterator(
+            x.len(),
+            points.map(|((&x, &y), sy)| ((self.func)(x, p) - y) / sy),
+        )
+    }
+
+    /// `0.5 * (|e|^2 + |u|^2)` plus the regularization penalty.
+    fn cost(&self, e: &DVector<f64>, u: &DVector<f64>, p: &DVector<f64>) -> f64 {
+        let penalty = self
+            .regularization
+            .map_or(0.0, |lambda| lambda * p.norm_squared());
+        0.5 * (e.norm_squared() + u.norm_squared() + penalty)
+    }
+
+    /// Forward-difference derivatives of the residuals `e` at `x`: the Jacobian with respect
+    /// to `p`, and the derivative of each residual with respect to its own scaled correction.
+    fn jacobian(
+        &self,
+        x: &[f64],
+        p: &DVector<f64>,
+        e: &DVector<f64>,
+    ) -> (DMatrix<f64>, DVector<f64>) {
+        let mut jac = DMatrix::zeros(x.len(), N);
+        let mut p_step = p.clone();
+        for j in 0..N {
+            p_step[j] = p[j] + self.diff_step * p[j].abs().max(1.0);
+            // use the step that is actually representable
+            let h = p_step[j] - p[j];
+            jac.set_column(j, &((self.residuals(x, &p_step) - e) / h));
+            p_step[j] = p[j];
+        }
+        let x_step: Vec<f64> = x
+            .iter()
+            .map(|&x| x + self.diff_step * x.abs().max(1.0))
+            .collect();
+        let e_step = self.residuals(&x_step, p);
+        let slope = DVector::from_fn(x.len(), |i, _| {
+            (e_step[i] - e[i]) / (x_step[i] - x[i]) * self.x_sigma[i]
+        });
+        (jac, slope)
+    }
+}
+
+/// Minimizes the weighted orthogonal distances from `p0` and zero corrections, damping the
+/// steps as `lm` says and stopping once `deadline` expires, and returns the solution with the
+/// adjusted `x_i + delta_i`.
+///
+/// The returned cost leaves out the regularization penalty, and its Jacobian is the one of
+/// the parameters with the corrections eliminated: row `i` of the Jacobian of `p`, weighted
+/// by `1 / sqrt(1 + g_i^2)` for the slope `g_i` of residual `i` in its scaled correction.
+/// That is the effective Jacobian ODRPACK derives the parameter covariance from.
+pub(crate) fn orthogonal_distance_regression<const N: usize, F>(
+    problem: &OdrProblem<'_, N, F>,
+    p0: [f64; N],
+    scaling: &Scaling<N>,
+    record_history: bool,
+    lm: &LmOptions,
+    deadline: Deadline,
+) -> (Solution<N>, Vec<f64>)
+where
+    F: Fn(f64, [f64; N]) -> f64,
+{
+    let max_evaluations = 100 * (N + 1);
+    let lambda = problem.regularization.unwrap_or(0.0);
+    let x_sigma = DVector::from_column_slice(problem.x_sigma);
+    let mut p = DVector::from_column_slice(&p0);
+    let mut u = DVector::zeros(problem.x_data.len());
+    let mut x = problem.adjusted_x(&u);
+    let mut e = problem.residuals(&x, &p);
+    let mut evaluations = 1;
+    let mut cost = problem.cost(&e, &u, &p);
+    let (mut jac, mut slope) = problem.jacobian(&x, &p, &e);
+    let fixed_scale = match scaling {
+        Scaling::Fixed(scale) => Some(DVector::from_column_slice(scale)),
+        Scaling::Jacobian => None,
+    };
+    let mut scales = Scales::new(fixed_scale, &jac);
+    let largest_curvature = max_column_norm_squared(&scale_columns(&jac, &scales.scale()))
+        .max(1.0 + slope.amax().powi(2));
+    let mut damping = lm.lambda_init * largest_curvature;
+    let max_damping = lm.lambda_max * largest_curvature;
+    let mut iteration = 0;
+    let mut history = record_history.then(|| vec![2.0 * cost]);
+
+    let termination = loop {
+        if !cost.is_finite() || jac.iter().chain(slope.iter()).any(|j| !j.is_finite()) {
+            break TerminationReason::NonFinite;
+        }
+        let gradient_p = jac.tr_mul(&e) + &p * lambda;
+        let gradient_u = slope.component_mul(&e) + &u;
+        let gradient_norm = gradient_p.amax().max(gradient_u.amax());
+        if gradient_norm < GTOL {
+            break TerminationReason::Gtol;
+        }
+        if evaluations >= max_evaluations {
+            break TerminationReason::MaxEvaluations;
+        }
+        if let Some(elapsed) = deadline.expired() {
+            break TerminationReason::Timeout { elapsed };
+        }
+        if damping > max_damping {
+            break TerminationReason::MaxDamping;
+        }
+
+        // the damped system for the scaled steps `step_p / d` and `step_u`, with `step_u`
+        // solved for in terms of `step_p` and substituted; it is the damped system of the
+        // Jacobian rows weighted by `sqrt(weights)` stacked on the regularization rows
+        // `sqrt(lambda) * d`, for the residuals `weighted_e / sqrt(weights)` and `sqrt(lambda) * p`
+        let d = scales.scale();
+        let jac_h = scale_columns(&jac, &d);
+        let mut termination = None;
+        let mut accepted = false;
+        while !accepted
+            && termination.is_none()
+            && evaluations < max_evaluations
+            && damping <= max_damping
+        {
+            let diagonal_u = slope.map(|g| g * g + 1.0 + damping);
+            let weights = diagonal_u.map(|s| (1.0 + damping) / s);
+            let weighted_e =
+                e.component_mul(&weights) - slope.component_mul(&u).component_div(&diagonal_u);
+            let m = jac_h.nrows();
+            let jac_w = DMatrix::from_fn(m + N, N, |i, j| match i.checked_sub(m) {
+                None => weights[i].sqrt() * jac_h[(i, j)],
+                Some(k) if k == j => lambda.sqrt() * d[j],
+                Some(_) => 0.0,
+            });
+            let r_w = DVector::from_fn(m + N, |i, _| match i.checked_sub(m) {
+                None => weighted_e[i] / weights[i].sqrt(),
+                Some(k) => lambda.sqrt() * p[k],
+            });
+            let step_h = DampedSvd::new(&jac_w, &r_w, lm.svd_threshold).step(damping);
+            let jac_step = &jac_h * &step_h;
+            let step_u = -(slope.component_mul(&(&e + &jac_step)) + &u).component_div(&diagonal_u);
+            let step = step_h.component_mul(&d);
+
+            let p_new = &p + &step;
+            let u_new = &u + &step_u;
+            let x_new = problem.adjusted_x(&u_new);
+            let e_new = problem.residuals(&x_new, &p_new);
+            evaluations += 1;
+
+            let cost_new = problem.cost(&e_new, &u_new, &p_new);
+            let e_linear = &e + jac_step + slope.component_mul(&step_u);
+            let actual_reduction = cost - cost_new;
+            let predicted_reduction = cost - problem.cost(&e_linear, &u_new, &p_new);
+            let ratio = gain_ratio(actual_reduction, predicted_reduction);
+            // sizes over the parameters and the unscaled corrections together
+            let step_norm = step.norm().hypot(step_u.component_mul(&x_sigma).norm());
+            let norm = p.norm().hypot(u.component_mul(&x_sigma).norm());
+            termination = check_termination(actual_reduction, cost, step_norm, norm, ratio);
+
+            if actual_reduction > 0.0 {
+                trace::damped_iteration(iteration, cost_new, damping, step_norm, gradient_norm);
+                damping /= lm.lambda_down_factor;
+                (p, u, x, e, cost) = (p_new, u_new, x_new, e_new, cost_new);
+                accepted = true;
+            } else {
+                trace::rejected_step(Method::LM, iteration, cost_new, step_norm);
+                damping *= lm.lambda_up_factor;
+            }
+        }
+
+        iteration += 1;
+        if let Some(history) = &mut history {
+            history.push(2.0 * cost);
+        }
+        if accepted {
+            (jac, slope) = problem.jacobian(&x, &p, &e);
+            scales.update(&jac);
+        }
+        if let Some(termination) = termination {
+            break termination;
+        }
+    };
+
+    trace::finished(Method::LM, termination, iteration, cost);
+    let weights = slope.map(|g| (1.0 + g * g).sqrt().recip());
+    let solution = Solution {
+        params: to_array(&p),
+        cost: 0.5 * (e.norm_squared() + u.norm_squared()),
+        jacobian: DMatrix::from_fn(jac.nrows(), N, |i, j| weights[i] * jac[(i, j)]),
+        termination,
+        iterations: iteration,
+        n_fev: problem.evaluations.get(),
+        history,
+        method: Method::LM,
+        at_bounds: [false; N],
+        n_free: N,
+        regularization: [problem.regularization.unwrap_or(0.0); N],
+        trust_radius: None,
+        trace: None,
+    };
+    (solution, x)
+}
+
+/// Standard deviations of one coordinate of the points kept by the input check, each 1 when
+/// `sigma` is `None`. `sigma` must match the data before non-finite points were dropped, and
+/// only its entries of finite points are kept when `n_kept` says that some were.
+pub(crate) fn checked_sigma(
+    sigma: Option<&[f64]>,
+    x_data: &[f64],
+    y_data: &[f64],
+    n_kept: usize,
+) -> Result<Vec<f64>, Error> {
+    let Some(sigma) = sigma else {
+        return Ok(vec![1.0; n_kept]);
+    };
+    if sigma.len() != x_data.len() {
+        return Err(Error::UnmatchedSigmaLength {
+            data_len: x_data.len(),
+            sigma_len: sigma.len(),
+        });
+    }
+    let is_finite = |i: &usize| x_data[*i].is_finite() && y_data[*i].is_finite();
+    let kept: Vec<usize> = if n_kept < x_data.len() {
+        (0..x_data.len()).filter(is_finite).collect()
+    } else {
+        (0..x_data.len()).collect()
+    };
+    kept.into_iter()
+        .map(|index| match sigma[index] {
+            value if value > 0.0 && value.is_finite() => Ok(value),
+            value => Err(Error::InvalidSigma { index, value }),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::Array;
+    use ndarray_rand::rand_distr::Normal;
+    use ndarray_rand::RandomExt;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use crate::{Config, CurveFit, Error};
+
+    fn line(x: f64, p: [f64; 2]) -> f64 {
+        p[0] * x + p[1]
+    }
+
+    #[test]
+    fn recovers_slope_with_errors_in_x() {
+        let x_true = Array::linspace(0., 4., 400);
+        let normal = Normal::new(0.0, 0.5).unwrap();
+        let mut rng = StdRng::seed_from_u64(7);
+        let x_noise = Array::random_using(x_true.dim(), normal, &mut rng);
+        let y_noise = Array::random_using(x_true.dim(), normal, &mut rng);
+        let y_data = (x_true.map(|&x| line(x, [2.0, 1.0])) + y_noise).to_vec();
+        let x_data = (&x_true + x_noise).to_vec();
+
+        // the noise in x attenuates the least-squares slope by var(x) / (var(x) + 0.25)
+        let ols = line.fit(&x_data, &y_data, Config::default()).unwrap();
+        let odr = line
+            .fit_odr(&x_data, &y_data, Config::default(), None, None)
+            .unwrap();
+        let (ols_slope, odr_slope) = (ols.curve.params()[0], odr.curve.params()[0]);
+        assert!(ols_slope < 1.8, "{ols_slope}");
+        assert!((odr_slope - 2.0).abs() < 0.1, "{odr_slope}");
+        assert!((odr_slope - 2.0).abs() < (ols_slope - 2.0).abs());
+        let [slope_error, _] = odr.std_errors();
+        assert!((odr_slope - 2.0).abs() < 3.0 * slope_error, "{slope_error}");
+        // the adjusted points lie closer to the true x than the measured ones
+        assert!(ols.x_adjusted.is_none());
+        let x_adjusted = odr.x_adjusted.as_ref().unwrap();
+        let distance =
+            |x: &[f64]| -> f64 { x.iter().zip(&x_true).map(|(a, b)| (a - b).powi(2)).sum() };
+        assert!(distance(x_adjusted) < 0.75 * distance(&x_data));
+        let weighted_distances: f64 = x_adjusted
+            .iter()
+            .zip(&x_data)
+            .zip(&y_data)
+            .map(|((&x, &x_measured), &y)| {
+                (line(x, odr.curve.params()) - y).powi(2) + (x - x_measured).powi(2)
+            })
+            .sum();
+        assert!((weighted_distances - odr.ssr).abs() < 1e-9 * odr.ssr);
+        // weights of the same ratio give the same fit, within the solver tolerance
+        let sigma = vec![0.5; x_data.len()];
+        let weighted = line
+            .fit_odr(
+                &x_data,
+                &y_data,
+                Config::default(),
+                Some(&sigma),
+                Some(&sigma),
+            )
+            .unwrap();
+        for (a, b) in weighted.curve.params().iter().zip(odr.curve.params()) {
+            assert!((a - b).abs() < 1e-5 * b.abs(), "{a} vs {b}");
+        }
+    }
+
+    #[test]
+    fn exact_x_is_ordinary_least_squares() {
+        let decay = |x: f64, p: [f64; 2]| p[0] * (-p[1] * x).exp();
+        let x_data: Vec<f64> = (0..30).map(|i| 0.2 * i as f64).collect();
+        let y_data: Vec<f64> = x_data
+            .iter()
+            .enumerate()
+            .map(|(i, &x)| decay(x, [3.0, 0.8]) + 0.02 * (1.7 * i as f64).sin())
+            .collect();
+        let x_sigma = vec![1e-9; x_data.len()];
+
+        let ols = decay.fit(&x_data, &y_data, Config::default()).unwrap();
+        let odr = decay
+            .fit_odr(&x_data, &y_data, Config::default(), Some(&x_sigma), None)
+            .unwrap();
+        for (a, b) in odr.curve.params().iter().zip(ols.curve.params()) {
+            assert!((a - b).abs() < 1e-6, "{a} vs {b}");
+        }
+        assert!((odr.ssr - ols.ssr).abs() < 1e-6 * ols.ssr);
+        for (a, b) in odr.std_errors().iter().zip(ols.std_errors()) {
+            assert!((a - b).abs() < 1e-4 * b, "{a} vs {b}");
+        }
+    }
+
+    #[test]
+    fn invalid_sigma() {
+        let (x_data, y_data) = ([0.0, 1.0, 2.0, 3.0], [1.0, 3.0, 5.0, 7.0]);
+        let cfg = Config::default();
+        assert!(matches!(
+            line.fit_odr(&x_data, &y_data, cfg, Some(&[1.0; 3]), None),
+            Err(Error::UnmatchedSigmaLength {
+                data_len: 4,
+                sigma_len: 3
+            })
+        ));
+        assert!(matches!(
+            line.fit_odr(&x_data, &y_data, cfg, None, Some(&[1.0, 1.0, 0.0, 1.0])),
+            Err(Error::InvalidSigma { index: 2, .. })
+        ));
+    }
+}
@@ -0,0 +1,234 @@
+//! Special functions and distributions needed for inference on fitted parameters.
+
+/// Natural logarithm of the gamma function for `x > 0` (Lanczos approximation, g = 7).
+pub(crate) fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        // reflection formula
+        let pi = std::f64::consts::PI;
+        return (pi / (pi * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let t = x + 7.5;
+    let series = COEFFICIENTS[1..]
+        .iter()
+        .enumerate()
+        .fold(COEFFICIENTS[0], |sum, (i, c)| sum + c / (x + i as f64 + 1.0));
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + series.ln()
+}
+
+/// Regularized incomplete beta function `I_x(a, b)`.
+pub(crate) fn regularized_incomplete_beta(x: f64, a: f64, b: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let ln_front = ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln();
+    // the continued fraction converges quickly only below the mean
+    if x < (a + 1.0) / (a + b + 2.0) {
+        ln_front.exp() * beta_continued_fraction(x, a, b) / a
+    } else {
+        1.0 - ln_front.exp() * beta_continued_fraction(1.0 - x, b, a) / b
+    }
+}
+
+/// Continued fraction of the incomplete beta function, evaluated with Lentz's method.
+fn beta_continued_fraction(x: f64, a: f64, b: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+    let mut c = 1.0;
+    let mut d = 1.0 - (a + b) * x / (a + 1.0);
+    if d.abs() < TINY {
+        d = TINY;
+    }
+    d = 1.0 / d;
+    let mut fraction = d;
+    for m in 1..=300 {
+        let m = m as f64;
+        for numerator in [
+            m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m)),
+            -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0)),
+        ] {
+            d = 1.0 + numerator * d;
+            if d.abs() < TINY {
+                d = TINY;
+            }
+            c = 1.0 + numerator / c;
+            if c.abs() < TINY {
+                c = TINY;
+            }
+            d = 1.0 / d;
+            fraction *= d * c;
+        }
+        if (d * c - 1.0).abs() < 1e-15 {
+            break;
+        }
+    }
+    fraction
+}
+
+/// Regularized upper incomplete gamma function `Q(a, x) = Γ(a, x) / Γ(a)` for `a > 0`.
+pub(crate) fn regularized_upper_gamma(a: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 1.0;
+    }
+    let ln_front = a * x.ln() - x - ln_gamma(a);
+    // the series of the lower function converges quickly below the mean, the continued
+    // fraction of the upper one above it
+    if x < a + 1.0 {
+        let mut term = 1.0 / a;
+        let mut sum = term;
+        for n in 1..=500 {
+            term *= x / (a + n as f64);
+            sum += term;
+            if term.abs() < 1e-16 * sum.abs() {
+                break;
+            }
+        }
+        1.0 - ln_front.exp() * sum
+    } else {
+        ln_front.exp() * gamma_continued_fraction(a, x)
+    }
+}
+
+/// Continued fraction of the upper incomplete gamma function, evaluated with Lentz's method.
+fn gamma_continued_fraction(a: f64, x: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+    let mut b = x + 1.0 - a;
+    let mut c = 1.0 / TINY;
+    let mut d = 1.0 / b;
+    let mut fraction = d;
+    for n in 1..=300 {
+        let numerator = -(n as f64) * (n as f64 - a);
+        b += 2.0;
+        d = numerator * d + b;
+        if d.abs() < TINY {
+            d = TINY;
+        }
+        c = b + numerator / c;
+        if c.abs() < TINY {
+            c = TINY;
+        }
+        d = 1.0 / d;
+        fraction *= d * c;
+        if (d * c - 1.0).abs() < 1e-15 {
+            break;
+        }
+    }
+    fraction
+}
+
+/// Survival function `P(X > chi2)` of the chi-squared distribution with `dof` degrees of
+/// freedom.
+pub(crate) fn chi_squared_sf(chi2: f64, dof: f64) -> f64 {
+    regularized_upper_gamma(0.5 * dof, 0.5 * chi2)
+}
+
+/// Cumulative distribution function of Student's t distribution.
+pub(crate) fn student_t_cdf(t: f64, dof: f64) -> f64 {
+    let tail = 0.5 * regularized_incomplete_beta(dof / (dof + t * t), 0.5 * dof, 0.5);
+    if t > 0.0 {
+        1.0 - tail
+    } else {
+        tail
+    }
+}
+
+/// Quantile (inverse CDF) of Student's t distribution, NaN for `p` outside `(0, 1)`.
+pub(crate) fn student_t_quantile(p: f64, dof: f64) -> f64 {
+    if !(p > 0.0 && p < 1.0 && dof > 0.0) {
+        return f64::NAN;
+    }
+    if p < 0.5 {
+        return -student_t_quantile(1.0 - p, dof);
+    }
+    let mut upper = 1.0;
+    while student_t_cdf(upper, dof) < p {
+        upper *= 2.0;
+    }
+    let mut lower = 0.0;
+    while upper - lower > 1e-14 * upper {
+        let mid = 0.5 * (lower + upper);
+        if student_t_cdf(mid, dof) < p {
+            lower = mid;
+        } else {
+            upper = mid;
+        }
+    }
+    0.5 * (lower + upper)
+}
+
+/// Quantile `q` of ascending `sorted` samples, interpolating linearly between order statistics.
+pub(crate) fn quantile(sorted: &[f64], q: f64) -> f64 {
+    let position = q * (sorted.len() - 1) as f64;
+    let below = position.floor() as usize;
+    let above = position.ceil() as usize;
+    sorted[below] + (position - below as f64) * (sorted[above] - sorted[below])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ln_gamma_matches_factorials() {
+        for (n, factorial) in [(1.0, 1.0), (2.0, 1.0), (5.0, 24.0), (11.0, 3_628_800.0)] {
+            assert!((ln_gamma(n) - f64::ln(factorial)).abs() < 1e-12);
+        }
+        assert!((ln_gamma(0.5) - std::f64::consts::PI.sqrt().ln()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn student_t_quantiles_match_tables() {
+        for (p, dof, expected) in [
+            (0.975, 1.0, 12.706_204_736_174_7),
+            (0.975, 8.0, 2.306_004_135_204_166),
+            (0.975, 30.0, 2.042_272_456_301_238),
+            (0.995, 5.0, 4.032_142_983_557_536),
+            (0.9, 3.0, 1.637_744_353_696_21),
+        ] {
+            let t = student_t_quantile(p, dof);
+            assert!((t - expected).abs() < 1e-9 * expected, "t({p}, {dof}) = {t}");
+            assert!((student_t_quantile(1.0 - p, dof) + expected).abs() < 1e-9 * expected);
+        }
+        assert!(student_t_quantile(1.5, 3.0).is_nan());
+    }
+
+    #[test]
+    fn chi_squared_tails_match_tables() {
+        for (chi2, dof, expected) in [
+            (3.841_458_820_694_124, 1.0, 0.05),
+            (0.454_936_423_119_572_7, 1.0, 0.5),
+            (18.307_038_053_275_146, 10.0, 0.05),
+            (9.341_817_765_591_96, 10.0, 0.5),
+            (2.558_212_160_400_869, 10.0, 0.99),
+            (124.342_113_404_237_5, 100.0, 0.05),
+        ] {
+            let p = chi_squared_sf(chi2, dof);
+            assert!((p - expected).abs() < 1e-9, "Q({chi2}, {dof}) = {p}");
+        }
+        // two degrees of freedom have the exponential tail exp(-chi2 / 2)
+        assert!((chi_squared_sf(5.0, 2.0) - (-2.5f64).exp()).abs() < 1e-14);
+        assert_eq!(chi_squared_sf(0.0, 4.0), 1.0);
+    }
+
+    #[test]
+    fn quantiles_interpolate() {
+        let sorted = [1.0, 2.0, 4.0, 8.0, 16.0];
+        assert_eq!(quantile(&sorted, 0.0), 1.0);
+        assert_eq!(quantile(&sorted, 0.5), 4.0);
+        assert_eq!(quantile(&sorted, 0.625), 6.0);
+        assert_eq!(quantile(&sorted, 1.0), 16.0);
+    }
+}
@@ -0,0 +1,306 @@
+//! Optional instrumentation of the solver internals through `tracing`.
+//!
+//! Events are emitted under the `curve_fit` target, so `RUST_LOG=curve_fit=debug` with any
+//! tracing subscriber shows them. Without the `tracing` feature every function here is an
+//! empty inline function and the solver loops carry no instrumentation cost.
+
+use crate::solver::TerminationReason;
+use crate::Method;
+
+/// Lets callers skip work, like a rank check, whose only purpose is to feed an event.
+pub(crate) const ENABLED: bool = cfg!(feature = "tracing");
+
+/// An accepted Levenberg-Marquardt step.
+#[inline(always)]
+pub(crate) fn damped_iteration(
+    iteration: usize,
+    cost: f64,
+    damping: f64,
+    step_norm: f64,
+    gradient_norm: f64,
+) {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+        target: "curve_fit",
+        method = ?Method::LM,
+        iteration,
+        cost,
+        damping,
+        step_norm,
+        gradient_norm,
+        "accepted step"
+    );
+    #[cfg(not(feature = "tracing"))]
+    let _ = (iteration, cost, damping, step_norm, gradient_norm);
+}
+
+/// An accepted step of a trust-region method, `trust_radius` being the radius for the next one.
+#[inline(always)]
+pub(crate) fn trust_region_iteration(
+    method: Method,
+    iteration: usize,
+    cost: f64,
+    trust_radius: f64,
+    step_norm: f64,
+    gradient_norm: f64,
+) {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+        target: "curve_fit",
+        ?method,
+        iteration,
+        cost,
+        trust_radius,
+        step_norm,
+        gradient_norm,
+        "accepted step"
+    );
+    #[cfg(not(feature = "tracing"))]
+    let _ = (
+        method,
+        iteration,
+        cost,
+        trust_radius,
+        step_norm,
+        gradient_norm,
+    );
+}
+
+/// An accepted L-BFGS step, `step_length` being its fraction of the quasi-Newton step.
+#[inline(always)]
+pub(crate) fn line_search_iteration(
+    iteration: usize,
+    cost: f64,
+    step_length: f64,
+    step_norm: f64,
+    gradient_norm: f64,
+) {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+        target: "curve_fit",
+        method = ?Method::LBFGS,
+        iteration,
+        cost,
+        step_length,
+        step_norm,
+        gradient_norm,
+        "accepted step"
+    );
+    #[cfg(not(feature = "tracing"))]
+    let _ = (iteration, cost, step_length, step_norm, gradient_norm);
+}
+
+/// A trial step that did not decrease the cost, `cost` is NaN when the model was not finite.
+#[inline(always)]
+pub(crate) fn rejected_step(method: Method, iteration: usize, cost: f64, step_norm: f64) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(target: "curve_fit", ?method, iteration, cost, step_norm, "rejected step");
+    #[cfg(not(feature = "tracing"))]
+    let _ = (method, iteration, cost, step_norm);
+}
+
+/// An initial guess outside the bounds of parameter `index`, moved onto the nearest bound.
+#[inline(always)]
+pub(crate) fn clamped_guess(index: usize, p0: f64, lower: f64, upper: f64) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(
+        target: "curve_fit",
+        index,
+        p0,
+        lower,
+        upper,
+        "initial guess clamped into bounds"
+    );
+    #[cfg(not(feature = "tracing"))]
+    let _ = (index, p0, lower, upper);
+}
+
+/// Outlier rejection that stopped after `rounds` fits because the next one would have kept
+/// only `n_inliers` points.
+#[inline(always)]
+pub(crate) fn underdetermined_rejection(rounds: usize, n_inliers: usize) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(
+        target: "curve_fit",
+        rounds,
+        n_inliers,
+        "outlier rejection left too few points"
+    );
+    #[cfg(not(feature = "tracing"))]
+    let _ = (rounds, n_inliers);
+}
+
+#[inline(always)]
+pub(crate) fn near_singular(method: Method, iteration: usize) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(target: "curve_fit", ?method, iteration, "jacobian is near singular");
+    #[cfg(not(feature = "tracing"))]
+    let _ = (method, iteration);
+}
+
+#[inline(always)]
+pub(crate) fn finished(
+    method: Method,
+    termination: TerminationReason,
+    iterations: usize,
+    cost: f64,
+) {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(target: "curve_fit", ?method, ?termination, iterations, cost, "solver finished");
+    #[cfg(not(feature = "tracing"))]
+    let _ = (method, termination, iterations, cost);
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Level, Metadata, Subscriber};
+
+    use crate::{Config, CurveFit, Method};
+
+    /// Level, message and field names of an event.
+    type Captured = (Level, String, Vec<&'static str>);
+
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<Captured>>>);
+
+    struct Fields {
+        message: String,
+        names: Vec<&'static str>,
+    }
+
+    impl Visit for Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            if field.name() == "message" {
+                self.message = format!("{value:?}");
+            } else {
+                self.names.push(field.name());
+            }
+        }
+    }
+
+    impl Subscriber for Capture {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, _: &Attributes<'_>) -> Id {
+            Id::from_u64(1)
+        }
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn event(&self, event: &Event<'_>) {
+            let mut fields = Fields {
+                message: String::new(),
+                names: vec![],
+            };
+            event.record(&mut fields);
+            self.0
+                .lock()
+                .unwrap()
+                .push((*event.metadata().level(), fields.message, fields.names));
+        }
+        fn enter(&self, _: &Id) {}
+        fn exit(&self, _: &Id) {}
+    }
+
+    fn capture(fit: impl FnOnce()) -> Vec<Captured> {
+        let capture = Capture::default();
+        tracing::subscriber::with_default(capture.clone(), fit);
+        let events = capture.0.lock().unwrap().clone();
+        events
+    }
+
+    #[test]
+    fn iterations_are_traced() {
+        let x_data = [0.0, 0.5, 1.0, 1.5, 2.0, 2.5];
+        let y_data = x_data.map(|x: f64| 3.0 * (-1.3 * x).exp());
+        let model = |x: f64, p: [f64; 2]| p[0] * (p[1] * x).exp();
+
+        for (method, radius) in [
+            (Method::LM, "damping"),
+            (Method::TRF, "trust_radius"),
+            (Method::DogBox, "trust_radius"),
+            (Method::LBFGS, "step_length"),
+        ] {
+            let events = capture(|| {
+                model
+                    .fit(
+                        &x_data,
+                        &y_data,
+                        Config {
+                            method,
+                            ..Default::default()
+                        },
+                    )
+                    .unwrap();
+            });
+
+            let steps: Vec<_> = events
+                .iter()
+                .filter(|(level, message, _)| *level == Level::DEBUG && message == "accepted step")
+                .collect();
+            assert!(!steps.is_empty(), "{method:?}");
+            for (_, _, names) in steps {
+                for name in ["iteration", "cost", radius, "step_norm", "gradient_norm"] {
+                    assert!(names.contains(&name), "{method:?} step without {name}");
+                }
+            }
+            assert!(events
+                .iter()
+                .any(|(_, message, _)| message == "solver finished"));
+        }
+    }
+
+    #[test]
+    fn rejected_steps_and_singular_jacobians_warn() {
+        let x_data = [0.0, 1.0, 2.0, 3.0];
+        let y_data = [1.0, 4.0, 7.0, 10.0];
+        // only the sum of the first two parameters is identifiable
+        let redundant = |x: f64, p: [f64; 3]| (p[0] + p[1]) * x + p[2];
+        let events = capture(|| {
+            redundant.fit(&x_data, &y_data, Config::default()).unwrap();
+        });
+        assert!(events
+            .iter()
+            .any(|(level, message, _)| *level == Level::WARN
+                && message == "jacobian is near singular"));
+
+        let x_data: Vec<f64> = (0..10).map(|i| 0.5 * i as f64).collect();
+        let y_data: Vec<f64> = x_data.iter().map(|x| 3.0 * (2.0 * x).sin()).collect();
+        let wave = |x: f64, p: [f64; 2]| p[0] * (p[1] * x).sin();
+        let events = capture(|| {
+            wave.fit(&x_data, &y_data, Config::default()).unwrap();
+        });
+        assert!(events
+            .iter()
+            .any(|(level, message, names)| *level == Level::WARN
+                && message == "rejected step"
+                && names.contains(&"step_norm")));
+    }
+
+    #[test]
+    fn clamped_guesses_warn() {
+        let x_data = [0.0, 1.0, 2.0, 3.0];
+        let y_data = [1.0, 3.0, 5.0, 7.0];
+        let line = |x: f64, p: [f64; 2]| p[0] * x + p[1];
+        let cfg = Config {
+            method: Method::TRF,
+            p0: 5.0,
+            ..Default::default()
+        };
+        let events = capture(|| {
+            line.fit_bounded(&x_data, &y_data, cfg, [0.0; 2], [10.0, 2.0])
+                .unwrap();
+        });
+        let clamped: Vec<_> = events
+            .iter()
+            .filter(|(_, message, _)| message == "initial guess clamped into bounds")
+            .collect();
+        assert_eq!(clamped.len(), 1);
+        assert_eq!(clamped[0].0, Level::WARN);
+        assert!(clamped[0].2.contains(&"index"));
+    }
+}